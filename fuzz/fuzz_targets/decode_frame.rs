@@ -0,0 +1,42 @@
+//! 任意のデータを1フレームずつデコードする
+#![no_main]
+
+use baremp3::decoder::*;
+use baremp3::types::*;
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    // 先頭1byteでデコードオプションを選択
+    let Some((&options, data)) = data.split_first() else {
+        return;
+    };
+    let mut decoder = MP3Decoder::new();
+    decoder.set_crc_policy(match options & 0x3 {
+        0 => MP3CrcPolicy::Ignore,
+        1 => MP3CrcPolicy::Reject,
+        _ => MP3CrcPolicy::Conceal,
+    });
+    decoder.set_concealment_mode(match (options >> 2) & 0x3 {
+        0 => MP3ConcealmentMode::Silence,
+        1 => MP3ConcealmentMode::Repeat,
+        _ => MP3ConcealmentMode::FadeOut,
+    });
+    decoder.set_num_sync_frames(1 + ((options >> 4) & 0x3) as usize);
+    decoder.set_gapless((options & 0x40) != 0);
+    decoder.set_deemphasis((options & 0x80) != 0);
+
+    let mut buffer = [[0.0f32; MP3_NUM_SAMPLES_PER_FRAME]; MP3_MAX_NUM_CHANNELS];
+    let mut pos = 0;
+    while pos < data.len() {
+        match decoder.decode_frame(&data[pos..], &mut buffer) {
+            Ok((size, header, _, _)) => {
+                // 欠落したフレームの隠蔽も試す
+                if (size & 1) != 0 {
+                    let _ = decoder.conceal_frame(&header, &mut buffer);
+                }
+                pos += size;
+            }
+            Err(_) => break,
+        }
+    }
+});
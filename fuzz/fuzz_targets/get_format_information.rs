@@ -0,0 +1,27 @@
+//! 任意のデータのフォーマット情報を取得し、ファイル全体をデコードする
+#![no_main]
+
+use baremp3::decoder::*;
+use baremp3::types::*;
+use libfuzzer_sys::fuzz_target;
+
+/// デコードする最大サンプル数（VBRタグのフレーム数が巨大な場合にメモリを使い切らないようにする）
+const MAX_NUM_SAMPLES: usize = 1 << 20;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(format) = get_format_information(data) else {
+        return;
+    };
+    let _ = get_replay_gain(data);
+    if format.num_samples > MAX_NUM_SAMPLES {
+        return;
+    }
+
+    let mut decoder = MP3Decoder::new();
+    decoder.set_gapless(true);
+    let mut output = vec![vec![0.0f32; format.num_samples]; format.num_channels];
+    let mut output: Vec<&mut [f32]> = output.iter_mut().map(|ch| ch.as_mut_slice()).collect();
+    let _ = decoder.decode_whole(data, &mut output);
+    let mut buffer = [[0.0f32; MP3_NUM_SAMPLES_PER_FRAME]; MP3_MAX_NUM_CHANNELS];
+    let _ = decoder.seek_to_sample(data, format.num_samples / 2, &mut buffer);
+});
@@ -0,0 +1,67 @@
+//! 任意のデータからタグを読み出す
+#![no_main]
+
+use baremp3::ape::*;
+use baremp3::id3v1::*;
+use baremp3::id3v2::*;
+use baremp3::lyrics3::*;
+use baremp3::tag::*;
+use libfuzzer_sys::fuzz_target;
+
+/// ID3v2フレームを展開して読み出す（チャプターのサブフレームも辿る）
+fn read_id3v2_frames(frames: ID3v2FrameIterator, depth: usize) {
+    let mut buffer = [0u8; 4096];
+    for raw in frames {
+        match raw.decode(&mut buffer) {
+            Ok(ID3v2Frame::Chapter { sub_frames, .. })
+            | Ok(ID3v2Frame::TableOfContents { sub_frames, .. })
+                if depth < 4 =>
+            {
+                read_id3v2_frames(sub_frames, depth + 1);
+            }
+            Ok(ID3v2Frame::Text { text, .. }) => {
+                let _ = text.chars().count();
+            }
+            _ => {}
+        }
+    }
+}
+
+fuzz_target!(|data: &[u8]| {
+    let _ = get_leading_tags_size(data);
+    let _ = get_trailing_tags_size(data);
+
+    let tags = decode_tags(data);
+    for field in [
+        MP3TagField::Title,
+        MP3TagField::Artist,
+        MP3TagField::Album,
+        MP3TagField::Year,
+        MP3TagField::Comment,
+        MP3TagField::Track,
+        MP3TagField::Genre,
+    ] {
+        if let Some(text) = tags.get(field) {
+            let _ = text.chars().count();
+        }
+    }
+    let _ = tags.replay_gain();
+    for tag in tags.id3v2.iter().flatten() {
+        read_id3v2_frames(tag.frames(), 0);
+    }
+
+    if let Ok(tag) = decode_id3v2_tag(data) {
+        read_id3v2_frames(tag.frames(), 0);
+    }
+    if let Some(tag) = decode_ape_tag(data) {
+        for item in tag.items() {
+            let _ = item.as_str();
+        }
+    }
+    if let Some(tag) = decode_lyrics3_tag(data) {
+        for _ in tag.fields() {}
+    }
+    if let Some(tag) = decode_id3v1_tag(data) {
+        let _ = tag.genre_name();
+    }
+});
@@ -0,0 +1,168 @@
+//! APEv1/APEv2タグのパーサ
+//! タグは項目の後ろに32byteのフッタ（APEv2では項目の前に同形式のヘッダも）を持つ
+
+/// ヘッダ・フッタのサイズ
+pub const APE_TAG_FOOTER_SIZE: usize = 32;
+/// ヘッダ・フッタの識別子
+const APE_TAG_PREAMBLE: &[u8; 8] = b"APETAGEX";
+/// タグがヘッダを持つことを示すフラグ
+const APE_TAG_FLAG_HAS_HEADER: u32 = 1 << 31;
+/// 読み取り専用を示すフラグ
+const APE_FLAG_READ_ONLY: u32 = 1 << 0;
+
+/// 項目の種類
+#[derive(PartialEq, Copy, Clone)]
+pub enum APEItemType {
+    /// UTF-8テキスト
+    Text = 0,
+    /// バイナリ
+    Binary = 1,
+    /// 外部リソースへの参照(UTF-8)
+    Locator = 2,
+    /// 予約済み
+    Reserved = 3,
+}
+
+/// APEタグの項目
+#[derive(PartialEq, Copy, Clone)]
+pub struct APEItem<'a> {
+    /// キー(ASCII)
+    pub key: &'a [u8],
+    /// 項目の種類
+    pub item_type: APEItemType,
+    /// 読み取り専用か
+    pub read_only: bool,
+    /// 値（テキストは複数の値が0で区切られうる）
+    pub value: &'a [u8],
+}
+
+impl<'a> APEItem<'a> {
+    /// 値を文字列スライスとして取得（テキストまたは参照の場合）
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.item_type {
+            APEItemType::Text | APEItemType::Locator => core::str::from_utf8(self.value).ok(),
+            _ => None,
+        }
+    }
+}
+
+/// APEタグの項目を順に取り出すイテレータ
+#[derive(PartialEq, Clone)]
+pub struct APEItemIterator<'a> {
+    /// 未読の項目領域
+    data: &'a [u8],
+}
+
+/// APEタグ
+#[derive(PartialEq, Copy, Clone)]
+pub struct APETag<'a> {
+    /// バージョン(1000: APEv1, 2000: APEv2)
+    pub version: u32,
+    /// 項目数
+    pub num_items: u32,
+    /// 読み取り専用か
+    pub read_only: bool,
+    /// 項目領域
+    items: &'a [u8],
+}
+
+impl<'a> APETag<'a> {
+    /// 項目を順に取り出すイテレータを取得
+    pub fn items(&self) -> APEItemIterator<'a> {
+        APEItemIterator { data: self.items }
+    }
+
+    /// キーに一致する項目を取得（大文字小文字は区別しない）
+    pub fn find_item(&self, key: &str) -> Option<APEItem<'a>> {
+        self.items()
+            .find(|item| item.key.eq_ignore_ascii_case(key.as_bytes()))
+    }
+}
+
+/// リトルエンディアンで32bit読み出し
+fn read_u32_le(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+impl<'a> Iterator for APEItemIterator<'a> {
+    type Item = APEItem<'a>;
+
+    fn next(&mut self) -> Option<APEItem<'a>> {
+        // 値のサイズ(4byte), フラグ(4byte), 0終端のキー, 値の順
+        let value_size = read_u32_le(self.data, 0)? as usize;
+        let flags = read_u32_le(self.data, 4)?;
+        let Some(key_size) = self.data[8..].iter().position(|&c| c == 0) else {
+            self.data = &[];
+            return None;
+        };
+        let value_start = 8 + key_size + 1;
+        let Some(value) = self.data.get(value_start..value_start + value_size) else {
+            self.data = &[];
+            return None;
+        };
+        let key = &self.data[8..8 + key_size];
+        self.data = &self.data[value_start + value_size..];
+
+        Some(APEItem {
+            key,
+            item_type: match (flags >> 1) & 0x3 {
+                0 => APEItemType::Text,
+                1 => APEItemType::Binary,
+                2 => APEItemType::Locator,
+                _ => APEItemType::Reserved,
+            },
+            read_only: (flags & APE_FLAG_READ_ONLY) != 0,
+            value,
+        })
+    }
+}
+
+/// データ末尾のAPEタグのフッタを読み出し (バージョン, 項目とフッタのサイズ, 項目数, フラグ)
+fn decode_ape_footer(data: &[u8]) -> Option<(u32, usize, u32, u32)> {
+    let footer = data.get(data.len().checked_sub(APE_TAG_FOOTER_SIZE)?..)?;
+    if !footer.starts_with(APE_TAG_PREAMBLE) {
+        return None;
+    }
+    let version = read_u32_le(footer, 8)?;
+    let size = read_u32_le(footer, 12)? as usize;
+    let num_items = read_u32_le(footer, 16)?;
+    let flags = read_u32_le(footer, 20)?;
+    if size < APE_TAG_FOOTER_SIZE {
+        return None;
+    }
+    Some((version, size, num_items, flags))
+}
+
+/// データ末尾のAPEタグのサイズを取得（ヘッダを含む, タグがなければ0）
+pub fn get_ape_tag_size(data: &[u8]) -> usize {
+    match decode_ape_footer(data) {
+        Some((version, size, _, flags)) => {
+            // APEv1はヘッダを持たない
+            let header_size = if version >= 2000 && (flags & APE_TAG_FLAG_HAS_HEADER) != 0 {
+                APE_TAG_FOOTER_SIZE
+            } else {
+                0
+            };
+            if size + header_size <= data.len() {
+                size + header_size
+            } else {
+                0
+            }
+        }
+        None => 0,
+    }
+}
+
+/// データ末尾のAPEタグのデコード
+pub fn decode_ape_tag(data: &[u8]) -> Option<APETag<'_>> {
+    let (version, size, num_items, flags) = decode_ape_footer(data)?;
+    let items_end = data.len() - APE_TAG_FOOTER_SIZE;
+    let items = data.get(data.len().checked_sub(size)?..items_end)?;
+    Some(APETag {
+        version,
+        num_items,
+        read_only: (flags & APE_FLAG_READ_ONLY) != 0,
+        items,
+    })
+}
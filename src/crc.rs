@@ -0,0 +1,35 @@
+/// CRC-16の生成多項式 x^16 + x^15 + x^2 + 1
+const MP3_CRC16_POLYNOMIAL: u16 = 0x8005;
+/// CRC-16の初期値
+pub const MP3_CRC16_INITIAL_VALUE: u16 = 0xFFFF;
+
+/// 先頭からnbitsビット分のデータでCRC-16を更新
+pub fn mp3_crc16_update(crc: u16, data: &[u8], nbits: usize) -> u16 {
+    let mut crc = crc;
+    for i in 0..nbits {
+        let bit = ((data[i / 8] >> (7 - (i % 8))) & 1) as u16;
+        let msb = crc >> 15;
+        crc <<= 1;
+        if (msb ^ bit) != 0 {
+            crc ^= MP3_CRC16_POLYNOMIAL;
+        }
+    }
+    crc
+}
+
+/// LAMEタグのCRC-16（多項式0x8005のビット反転順, 初期値0）を計算
+pub fn mp3_crc16_lame(data: &[u8]) -> u16 {
+    const REVERSED_POLYNOMIAL: u16 = MP3_CRC16_POLYNOMIAL.reverse_bits();
+    let mut crc = 0u16;
+    for &byte in data {
+        crc ^= byte as u16;
+        for _ in 0..8 {
+            crc = if (crc & 1) != 0 {
+                (crc >> 1) ^ REVERSED_POLYNOMIAL
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    crc
+}
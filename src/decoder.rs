@@ -1,23 +1,50 @@
+use crate::crc::*;
+use crate::deemphasis::*;
+use crate::frame::MP3FrameIterator;
 use crate::huffman::*;
 use crate::hybrid_synthesis::*;
+use crate::id3v2::decode_id3v2_header;
+use crate::layer1::*;
+use crate::layer2::*;
 use crate::maindata_buffer::*;
+use crate::replaygain::*;
+use crate::seek_index::MP3SeekIndex;
+use crate::tag::{decode_tags, get_leading_tags_size, get_trailing_tags_size};
 use crate::types::*;
+use crate::xing::*;
 
 use bitreader::BitReader;
-use core::cmp::min;
+use core::cmp::{max, min};
 use core::error;
 use core::fmt;
+use core::ops::Range;
 
 /// 同期コード
-const MP3_SYNC_CODE: u32 = 0xFFF;
+const MP3_SYNC_CODE: u32 = 0x7FF;
 /// 同期コード長(bit)
-const MP3_SYNC_CODE_LENGTH: usize = 12;
+const MP3_SYNC_CODE_LENGTH: usize = 11;
 /// フレームヘッダサイズ(byte)
 const MP3_FRAMEHEADER_SIZE: usize = 4;
 /// モノラルのサイドインフォメーションサイズ(byte)
 const MP3_SIDEINFORMATION_SIZE_MONO: usize = 17;
 /// ステレオのサイドインフォメーションサイズ(byte)
 const MP3_SIDEINFORMATION_SIZE_STEREO: usize = 32;
+/// MPEG2(LSF)のモノラルのサイドインフォメーションサイズ(byte)
+const MP3_SIDEINFORMATION_SIZE_MONO_LSF: usize = 9;
+/// MPEG2(LSF)のステレオのサイドインフォメーションサイズ(byte)
+const MP3_SIDEINFORMATION_SIZE_STEREO_LSF: usize = 17;
+/// フリーフォーマットで許容する最大ビットレート(bps)
+const MP3_MAX_FREE_FORMAT_BIT_RATE: usize = 640_000;
+/// デコーダの遅延サンプル数(528+1)
+const MP3_DECODER_DELAY: usize = 529;
+/// シーク時に遡るフレーム数の上限（ビットリザーバの最大511byteを満たすのに十分な数）
+const MP3_MAX_NUM_SEEK_PREROLL_FRAMES: usize = 16;
+/// シーク時に合成フィルタバンクの状態を復元するため、対象の直前でデコードするフレーム数
+const MP3_NUM_SEEK_WARMUP_FRAMES: usize = 2;
+/// フェードアウトによる隠蔽で1グラニュール(576サンプル)ごとに乗じるゲイン
+const MP3_CONCEALMENT_FADE_OUT_GAIN: f32 = 0.5;
+/// MPEG1のインテンシティステレオの不正位置
+const MPEG1_ILLEGAL_IS_POSITION: u8 = 7;
 
 /// 1グラニュールのスケールファクタ
 struct GranuleScaleFactor {
@@ -25,6 +52,21 @@ struct GranuleScaleFactor {
     long: [u8; MP3_NUM_CRITICAL_BANDS_LONG],
     /// shortブロックのクリティカルバンド(3つ分)
     short: [[u8; MP3_NUM_CRITICAL_BANDS_SHORT]; 3],
+    /// longブロックのインテンシティステレオの不正位置
+    illegal_is_pos_long: [u8; MP3_NUM_CRITICAL_BANDS_LONG],
+    /// shortブロックのインテンシティステレオの不正位置
+    illegal_is_pos_short: [u8; MP3_NUM_CRITICAL_BANDS_SHORT],
+}
+
+impl Default for GranuleScaleFactor {
+    fn default() -> Self {
+        Self {
+            long: [0u8; MP3_NUM_CRITICAL_BANDS_LONG],
+            short: [[0u8; MP3_NUM_CRITICAL_BANDS_SHORT]; 3],
+            illegal_is_pos_long: [MPEG1_ILLEGAL_IS_POSITION; MP3_NUM_CRITICAL_BANDS_LONG],
+            illegal_is_pos_short: [MPEG1_ILLEGAL_IS_POSITION; MP3_NUM_CRITICAL_BANDS_SHORT],
+        }
+    }
 }
 
 /// 1フレームのスケールファクタ
@@ -44,28 +86,10 @@ impl Default for MP3ScaleFactor {
         Self {
             ch: [
                 FrameScaleFactor {
-                    gr: [
-                        GranuleScaleFactor {
-                            long: [0u8; MP3_NUM_CRITICAL_BANDS_LONG],
-                            short: [[0u8; MP3_NUM_CRITICAL_BANDS_SHORT]; 3],
-                        },
-                        GranuleScaleFactor {
-                            long: [0u8; MP3_NUM_CRITICAL_BANDS_LONG],
-                            short: [[0u8; MP3_NUM_CRITICAL_BANDS_SHORT]; 3],
-                        },
-                    ],
+                    gr: [GranuleScaleFactor::default(), GranuleScaleFactor::default()],
                 },
                 FrameScaleFactor {
-                    gr: [
-                        GranuleScaleFactor {
-                            long: [0u8; MP3_NUM_CRITICAL_BANDS_LONG],
-                            short: [[0u8; MP3_NUM_CRITICAL_BANDS_SHORT]; 3],
-                        },
-                        GranuleScaleFactor {
-                            long: [0u8; MP3_NUM_CRITICAL_BANDS_LONG],
-                            short: [[0u8; MP3_NUM_CRITICAL_BANDS_SHORT]; 3],
-                        },
-                    ],
+                    gr: [GranuleScaleFactor::default(), GranuleScaleFactor::default()],
                 },
             ],
         }
@@ -80,6 +104,34 @@ pub struct MP3Decoder {
     synth_buffer: [MP3SynthesisBuffer; MP3_MAX_NUM_CHANNELS],
     /// メインデータ開始位置
     maindata_start: usize,
+    /// フリーフォーマットのフレームサイズ(byte, パディング除く) 未計測時は0
+    free_format_frame_size: usize,
+    /// CRC不一致フレームの扱い
+    crc_policy: MP3CrcPolicy,
+    /// デエンファシスを行うか
+    deemphasis: bool,
+    /// デエンファシスフィルタ
+    deemphasis_filter: [MP3DeemphasisFilter; MP3_MAX_NUM_CHANNELS],
+    /// デュアルチャンネルの出力チャンネル選択
+    dual_channel_output: MP3DualChannelOutput,
+    /// ギャップレス再生を行うか
+    gapless: bool,
+    /// ReplayGainの適用モード
+    replay_gain_mode: MP3ReplayGainMode,
+    /// ReplayGain適用時にピーク振幅に基づいてクリッピングを防止するか
+    clipping_prevention: bool,
+    /// ReplayGain情報
+    replay_gain: MP3ReplayGain,
+    /// 同期の確定に使う連続フレーム数
+    num_sync_frames: usize,
+    /// 直近のdecode_frameで読み飛ばした不正なデータの範囲
+    skipped_range: Option<Range<usize>>,
+    /// 破損・欠落フレームの隠蔽方法
+    concealment_mode: MP3ConcealmentMode,
+    /// 直近のフレームを隠蔽したか
+    concealed: bool,
+    /// メインデータバッファ内で後続フレームが参照できるデータサイズ(byte)
+    maindata_available: usize,
 }
 
 /// スケールファクタのビット幅テーブル
@@ -94,6 +146,111 @@ const SCALEFACTOR_DIVISION_START_INDEX_TABLE_LONG: [usize; 5] = [0, 6, 11, 16, 2
 /// スケールファクタの分割バンド開始インデックステーブル(short)
 const SCALEFACTOR_DIVISION_START_INDEX_TABLE_SHORT: [usize; 3] = [0, 6, 12];
 
+/// MPEG2(LSF)の分割ごとのスケールファクタ数テーブル [ブロック番号][ブロックタイプ番号(long, short, mixed)][分割]
+const LSF_SCALEFACTOR_NUM_BANDS_TABLE: [[[u8; 4]; 3]; 6] = [
+    [[6, 5, 5, 5], [9, 9, 9, 9], [6, 9, 9, 9]],
+    [[6, 5, 7, 3], [9, 9, 12, 6], [6, 9, 12, 6]],
+    [[11, 10, 0, 0], [18, 18, 0, 0], [15, 18, 0, 0]],
+    [[7, 7, 7, 0], [12, 12, 12, 0], [6, 15, 12, 0]],
+    [[6, 6, 6, 3], [12, 9, 9, 6], [6, 12, 9, 6]],
+    [[8, 8, 5, 0], [15, 12, 9, 0], [6, 18, 9, 0]],
+];
+
+/// ビットレートテーブル(MPEG1 Layer3)
+const BIT_RATE_TABLE_MPEG1_LAYER3: [MP3BitRate; 15] = [
+    MP3BitRate::Kbps0,
+    MP3BitRate::Kbps32,
+    MP3BitRate::Kbps40,
+    MP3BitRate::Kbps48,
+    MP3BitRate::Kbps56,
+    MP3BitRate::Kbps64,
+    MP3BitRate::Kbps80,
+    MP3BitRate::Kbps96,
+    MP3BitRate::Kbps112,
+    MP3BitRate::Kbps128,
+    MP3BitRate::Kbps160,
+    MP3BitRate::Kbps192,
+    MP3BitRate::Kbps224,
+    MP3BitRate::Kbps256,
+    MP3BitRate::Kbps320,
+];
+
+/// ビットレートテーブル(MPEG1 Layer1)
+const BIT_RATE_TABLE_MPEG1_LAYER1: [MP3BitRate; 15] = [
+    MP3BitRate::Kbps0,
+    MP3BitRate::Kbps32,
+    MP3BitRate::Kbps64,
+    MP3BitRate::Kbps96,
+    MP3BitRate::Kbps128,
+    MP3BitRate::Kbps160,
+    MP3BitRate::Kbps192,
+    MP3BitRate::Kbps224,
+    MP3BitRate::Kbps256,
+    MP3BitRate::Kbps288,
+    MP3BitRate::Kbps320,
+    MP3BitRate::Kbps352,
+    MP3BitRate::Kbps384,
+    MP3BitRate::Kbps416,
+    MP3BitRate::Kbps448,
+];
+
+/// ビットレートテーブル(MPEG2, MPEG2.5 Layer1)
+const BIT_RATE_TABLE_LSF_LAYER1: [MP3BitRate; 15] = [
+    MP3BitRate::Kbps0,
+    MP3BitRate::Kbps32,
+    MP3BitRate::Kbps48,
+    MP3BitRate::Kbps56,
+    MP3BitRate::Kbps64,
+    MP3BitRate::Kbps80,
+    MP3BitRate::Kbps96,
+    MP3BitRate::Kbps112,
+    MP3BitRate::Kbps128,
+    MP3BitRate::Kbps144,
+    MP3BitRate::Kbps160,
+    MP3BitRate::Kbps176,
+    MP3BitRate::Kbps192,
+    MP3BitRate::Kbps224,
+    MP3BitRate::Kbps256,
+];
+
+/// ビットレートテーブル(MPEG1 Layer2)
+const BIT_RATE_TABLE_MPEG1_LAYER2: [MP3BitRate; 15] = [
+    MP3BitRate::Kbps0,
+    MP3BitRate::Kbps32,
+    MP3BitRate::Kbps48,
+    MP3BitRate::Kbps56,
+    MP3BitRate::Kbps64,
+    MP3BitRate::Kbps80,
+    MP3BitRate::Kbps96,
+    MP3BitRate::Kbps112,
+    MP3BitRate::Kbps128,
+    MP3BitRate::Kbps160,
+    MP3BitRate::Kbps192,
+    MP3BitRate::Kbps224,
+    MP3BitRate::Kbps256,
+    MP3BitRate::Kbps320,
+    MP3BitRate::Kbps384,
+];
+
+/// ビットレートテーブル(MPEG2, MPEG2.5 Layer2, Layer3)
+const BIT_RATE_TABLE_LSF_LAYER3: [MP3BitRate; 15] = [
+    MP3BitRate::Kbps0,
+    MP3BitRate::Kbps8,
+    MP3BitRate::Kbps16,
+    MP3BitRate::Kbps24,
+    MP3BitRate::Kbps32,
+    MP3BitRate::Kbps40,
+    MP3BitRate::Kbps48,
+    MP3BitRate::Kbps56,
+    MP3BitRate::Kbps64,
+    MP3BitRate::Kbps80,
+    MP3BitRate::Kbps96,
+    MP3BitRate::Kbps112,
+    MP3BitRate::Kbps128,
+    MP3BitRate::Kbps144,
+    MP3BitRate::Kbps160,
+];
+
 impl Default for MP3GranuleInformation {
     fn default() -> Self {
         Self {
@@ -115,44 +272,129 @@ impl Default for MP3GranuleInformation {
     }
 }
 
-/// サイドインフォメーションのサイズを計算
+impl Default for MP3SideInformation {
+    fn default() -> Self {
+        Self {
+            maindata_begin: 0,
+            private_bits: 0,
+            ch: [
+                MP3ChannelSideInformation {
+                    scfsi: [false; 4],
+                    gr: [
+                        MP3GranuleInformation::default(),
+                        MP3GranuleInformation::default(),
+                    ],
+                },
+                MP3ChannelSideInformation {
+                    scfsi: [false; 4],
+                    gr: [
+                        MP3GranuleInformation::default(),
+                        MP3GranuleInformation::default(),
+                    ],
+                },
+            ],
+        }
+    }
+}
+
+/// サイドインフォメーションのサイズを計算（Layer3以外はサイドインフォメーションなし）
 macro_rules! get_sideinformation_size {
+    ($header:expr) => {{
+        match (&$header.layer, &$header.version, &$header.channel_mode) {
+            (MP3Layer::Layer3, MPEGVersion::MPEGVersion1, MP3ChannelMode::Monoral) => {
+                MP3_SIDEINFORMATION_SIZE_MONO
+            }
+            (MP3Layer::Layer3, MPEGVersion::MPEGVersion1, _) => MP3_SIDEINFORMATION_SIZE_STEREO,
+            (MP3Layer::Layer3, _, MP3ChannelMode::Monoral) => MP3_SIDEINFORMATION_SIZE_MONO_LSF,
+            (MP3Layer::Layer3, _, _) => MP3_SIDEINFORMATION_SIZE_STEREO_LSF,
+            (_, _, _) => 0,
+        }
+    }};
+}
+
+/// インテンシティステレオが有効か判定
+macro_rules! is_intensity_stereo {
     ($header:expr) => {{
         match $header.channel_mode {
-            MP3ChannelMode::Monoral => MP3_SIDEINFORMATION_SIZE_MONO,
-            _ => MP3_SIDEINFORMATION_SIZE_STEREO,
+            MP3ChannelMode::JointStereo => matches!(
+                $header.ext_channel_mode,
+                MP3ExtChannelMode::IntensityStereo | MP3ExtChannelMode::MSIntensityStereo
+            ),
+            _ => false,
         }
     }};
 }
 
-/// メインデータに含まれるデータサイズ(byte)を取得
-fn get_maindata_size(header: &MP3FrameHeader) -> usize {
-    let mut size: usize = 0;
+/// フレームあたりサンプル数を取得
+pub fn get_num_samples_per_frame(header: &MP3FrameHeader) -> usize {
+    match header.layer {
+        MP3Layer::Layer1 => MP3_NUM_SAMPLES_PER_FRAME_LAYER1,
+        // Layer2はMPEG2(LSF)でも1152サンプル
+        MP3Layer::Layer2 => MP3_NUM_SAMPLES_PER_FRAME,
+        _ => get_num_granules!(header) * MP3_NUM_SAMPLES_PER_GRANULE,
+    }
+}
+
+/// パディングのサイズ(byte)を取得
+fn get_padding_size(header: &MP3FrameHeader) -> usize {
+    match (header.padding, &header.layer) {
+        (false, _) => 0,
+        // Layer1は4byteのスロット単位
+        (true, MP3Layer::Layer1) => 4,
+        (true, _) => 1,
+    }
+}
+
+/// ビットレート(bits_per_second)からフレームサイズ(byte)を計算
+fn get_frame_size(header: &MP3FrameHeader, bit_rate: usize) -> usize {
+    match header.layer {
+        // Layer1は4byteのスロット単位 12 * bits_per_second / sampling_rate(Hz) * 4
+        MP3Layer::Layer1 => 12 * bit_rate / header.sampling_rate as usize * 4,
+        // 1フレームあたりサンプル数 * bits_per_second / sampling_rate(Hz) をバイト単位に換算
+        _ => get_num_samples_per_frame(header) / 8 * bit_rate / header.sampling_rate as usize,
+    }
+}
 
-    // 1152(1フレームあたりサンプル数) * bits_per_second / sampling_rate(Hz) をバイト単位に換算
-    size += 144 * header.bit_rate as usize / header.sampling_rate as usize;
+/// メインデータに含まれるデータサイズ(byte)を取得
+fn get_maindata_size(header: &MP3FrameHeader, frame_size: usize) -> Option<usize> {
+    // パディングがある場合はサイズ増加
+    let mut size = frame_size + get_padding_size(header);
 
     // ヘッダ分（同期コード含む）を減算
-    size -= MP3_FRAMEHEADER_SIZE;
+    size = size.checked_sub(MP3_FRAMEHEADER_SIZE)?;
 
     // サイドインフォメーション分を減算
-    size -= get_sideinformation_size!(header);
-
-    // パディングがある場合は1byte増加
-    if header.padding {
-        size += 1;
-    }
+    size = size.checked_sub(get_sideinformation_size!(header))?;
 
     // CRC16の2byteを減算
     if header.error_protection {
-        size -= 2;
+        size = size.checked_sub(2)?;
     }
 
-    size
+    Some(size)
+}
+
+/// フリーフォーマットのフレームサイズ(byte, パディング除く)を次の同期ヘッダまでの距離から計測
+fn measure_free_format_frame_size(header: &MP3FrameHeader, data: &[u8]) -> Option<usize> {
+    let mut pos = MP3_FRAMEHEADER_SIZE;
+    while pos < data.len() {
+        let sync_pos = pos + find_sync_code(&data[pos..])?;
+        // バージョン・レイヤー・サンプリングレートが一致するフリーフォーマットのヘッダを探す
+        if let Some(next_header) = decode_frame_header(&data[sync_pos..])
+            && next_header.version == header.version
+            && next_header.layer == header.layer
+            && next_header.sampling_rate == header.sampling_rate
+            && next_header.bit_rate == MP3BitRate::Kbps0
+        {
+            return sync_pos.checked_sub(get_padding_size(header));
+        }
+        pos = sync_pos + 1;
+    }
+    None
 }
 
 /// 同期コードの検索
-fn find_sync_code(data: &[u8]) -> Option<usize> {
+pub(crate) fn find_sync_code(data: &[u8]) -> Option<usize> {
     // 同期コードの照合パターン
     const MP3_SYNC_CODE_SHIFT: u32 = 16 - MP3_SYNC_CODE_LENGTH as u32;
     const MP3_SYNC_CODE_PATTERN: u32 = MP3_SYNC_CODE << MP3_SYNC_CODE_SHIFT;
@@ -166,7 +408,10 @@ fn find_sync_code(data: &[u8]) -> Option<usize> {
     let mut pattern = data[0] as u32;
     for pos in 1..(data.len() - 1) {
         pattern = (pattern << 8) | data[pos] as u32;
-        if (pattern & MP3_SYNC_CODE_PATTERN) == MP3_SYNC_CODE_PATTERN {
+        // 予約済みのバージョン(0b01)は除外
+        if (pattern & MP3_SYNC_CODE_PATTERN) == MP3_SYNC_CODE_PATTERN
+            && ((pattern >> (MP3_SYNC_CODE_SHIFT - 2)) & 0x3) != 0x1
+        {
             return Some(pos - 1);
         }
     }
@@ -185,51 +430,56 @@ fn decode_frame_header(data: &[u8]) -> Option<MP3FrameHeader> {
     let mut breader = BitReader::new(data);
 
     // 同期コードのチェック
-    if breader.read_u32(MP3_SYNC_CODE_LENGTH as u8).unwrap() != MP3_SYNC_CODE {
+    if breader.read_u32(MP3_SYNC_CODE_LENGTH as u8).ok()? != MP3_SYNC_CODE {
         return None;
     }
 
     // ヘッダの内容読み取り
-    Some(MP3FrameHeader {
-        version: match breader.read_u8(1).unwrap() {
-            0 => MPEGVersion::MPEGVersion2,
-            1 => MPEGVersion::MPEGVersion1,
-            _ => return None,
-        },
-        layer: match 4 - breader.read_u8(2).unwrap() {
-            1 => MP3Layer::Layer1,
-            2 => MP3Layer::Layer2,
-            3 => MP3Layer::Layer3,
-            _ => return None,
-        },
-        error_protection: !breader.read_bool().unwrap(),
-        bit_rate: match breader.read_u8(4).unwrap() {
-            0 => MP3BitRate::Kbps0,
-            1 => MP3BitRate::Kbps32,
-            2 => MP3BitRate::Kbps40,
-            3 => MP3BitRate::Kbps48,
-            4 => MP3BitRate::Kbps56,
-            5 => MP3BitRate::Kbps64,
-            6 => MP3BitRate::Kbps80,
-            7 => MP3BitRate::Kbps96,
-            8 => MP3BitRate::Kbps112,
-            9 => MP3BitRate::Kbps128,
-            10 => MP3BitRate::Kbps160,
-            11 => MP3BitRate::Kbps192,
-            12 => MP3BitRate::Kbps224,
-            13 => MP3BitRate::Kbps256,
-            14 => MP3BitRate::Kbps320,
-            _ => return None,
-        },
-        sampling_rate: match breader.read_u8(2).unwrap() {
-            0 => MP3SamplingRate::Hz44100,
-            1 => MP3SamplingRate::Hz48000,
-            2 => MP3SamplingRate::Hz32000,
-            _ => return None,
+    let version = match breader.read_u8(2).ok()? {
+        0 => MPEGVersion::MPEGVersion25,
+        2 => MPEGVersion::MPEGVersion2,
+        3 => MPEGVersion::MPEGVersion1,
+        _ => return None,
+    };
+    let layer = match 4 - breader.read_u8(2).ok()? {
+        1 => MP3Layer::Layer1,
+        2 => MP3Layer::Layer2,
+        3 => MP3Layer::Layer3,
+        _ => return None,
+    };
+    let error_protection = !breader.read_bool().ok()?;
+    let bit_rate = match breader.read_u8(4).ok()? as usize {
+        15 => return None,
+        index => match (&version, &layer) {
+            (MPEGVersion::MPEGVersion1, MP3Layer::Layer1) => BIT_RATE_TABLE_MPEG1_LAYER1[index],
+            (MPEGVersion::MPEGVersion1, MP3Layer::Layer2) => BIT_RATE_TABLE_MPEG1_LAYER2[index],
+            (MPEGVersion::MPEGVersion1, MP3Layer::Layer3) => BIT_RATE_TABLE_MPEG1_LAYER3[index],
+            (_, MP3Layer::Layer1) => BIT_RATE_TABLE_LSF_LAYER1[index],
+            (_, _) => BIT_RATE_TABLE_LSF_LAYER3[index],
         },
-        padding: breader.read_bool().unwrap(),
-        extension: breader.read_u8(1).unwrap() as u8,
-        channel_mode: match breader.read_u8(2).unwrap() {
+    };
+    let sampling_rate = match (&version, breader.read_u8(2).ok()?) {
+        (MPEGVersion::MPEGVersion1, 0) => MP3SamplingRate::Hz44100,
+        (MPEGVersion::MPEGVersion1, 1) => MP3SamplingRate::Hz48000,
+        (MPEGVersion::MPEGVersion1, 2) => MP3SamplingRate::Hz32000,
+        (MPEGVersion::MPEGVersion2, 0) => MP3SamplingRate::Hz22050,
+        (MPEGVersion::MPEGVersion2, 1) => MP3SamplingRate::Hz24000,
+        (MPEGVersion::MPEGVersion2, 2) => MP3SamplingRate::Hz16000,
+        (MPEGVersion::MPEGVersion25, 0) => MP3SamplingRate::Hz11025,
+        (MPEGVersion::MPEGVersion25, 1) => MP3SamplingRate::Hz12000,
+        (MPEGVersion::MPEGVersion25, 2) => MP3SamplingRate::Hz8000,
+        _ => return None,
+    };
+
+    Some(MP3FrameHeader {
+        version,
+        layer,
+        error_protection,
+        bit_rate,
+        sampling_rate,
+        padding: breader.read_bool().ok()?,
+        extension: breader.read_u8(1).ok()?,
+        channel_mode: match breader.read_u8(2).ok()? {
             0 => MP3ChannelMode::Stereo,
             1 => MP3ChannelMode::JointStereo,
             2 => MP3ChannelMode::DualChannel,
@@ -237,18 +487,17 @@ fn decode_frame_header(data: &[u8]) -> Option<MP3FrameHeader> {
             _ => return None,
         },
         ext_channel_mode: {
-            let flags = breader.read_u8(2).unwrap();
-            if (flags & 0x1) != 0 {
-                MP3ExtChannelMode::IntensityStereo
-            } else if (flags & 0x2) != 0 {
-                MP3ExtChannelMode::MSStereo
-            } else {
-                MP3ExtChannelMode::NONE
+            let flags = breader.read_u8(2).ok()?;
+            match flags {
+                0x1 => MP3ExtChannelMode::IntensityStereo,
+                0x2 => MP3ExtChannelMode::MSStereo,
+                0x3 => MP3ExtChannelMode::MSIntensityStereo,
+                _ => MP3ExtChannelMode::NONE,
             }
         },
-        copyright: breader.read_bool().unwrap(),
-        original: breader.read_bool().unwrap(),
-        emphasis: match breader.read_u32(2).unwrap() {
+        copyright: breader.read_bool().ok()?,
+        original: breader.read_bool().ok()?,
+        emphasis: match breader.read_u32(2).ok()? {
             0 => MP3EmphasisMode::NONE,
             1 => MP3EmphasisMode::FiftyFifteenMs,
             2 => MP3EmphasisMode::Reserved,
@@ -260,11 +509,8 @@ fn decode_frame_header(data: &[u8]) -> Option<MP3FrameHeader> {
 
 /// サイドインフォメーションのデコード
 fn decode_side_information(header: &MP3FrameHeader, data: &[u8]) -> Option<MP3SideInformation> {
-    // MPEG1以外は対応していない
-    match header.version {
-        MPEGVersion::MPEGVersion1 => {}
-        MPEGVersion::MPEGVersion2 => return None,
-    }
+    // MPEG1か否か
+    let mpeg1 = matches!(header.version, MPEGVersion::MPEGVersion1);
 
     // データサイズ不足
     if data.len() < get_sideinformation_size!(header) {
@@ -302,30 +548,37 @@ fn decode_side_information(header: &MP3FrameHeader, data: &[u8]) -> Option<MP3Si
     let mut breader = BitReader::new(data);
 
     // メインデータ開始位置（負のオフセット）
-    side_info.maindata_begin = breader.read_u16(9).unwrap();
+    side_info.maindata_begin = breader.read_u16(if mpeg1 { 9 } else { 8 }).ok()?;
     // プライベートビット
-    side_info.private_bits = if num_channels == 1 {
-        breader.read_u8(5).unwrap()
-    } else {
-        breader.read_u8(3).unwrap()
+    side_info.private_bits = match (mpeg1, num_channels) {
+        (true, 1) => breader.read_u8(5).ok()?,
+        (true, _) => breader.read_u8(3).ok()?,
+        (false, 1) => breader.read_u8(1).ok()?,
+        (false, _) => breader.read_u8(2).ok()?,
     };
-    // scfsi
-    for ch in 0..num_channels {
-        for i in 0..4 {
-            side_info.ch[ch].scfsi[i] = breader.read_bool().unwrap();
+    // scfsi（MPEG2にはない）
+    if mpeg1 {
+        for ch in 0..num_channels {
+            for i in 0..4 {
+                side_info.ch[ch].scfsi[i] = breader.read_bool().ok()?;
+            }
         }
     }
     // グラニュール
-    for gr in 0..2 {
+    for gr in 0..get_num_granules!(header) {
         for ch in 0..num_channels {
             let granule: &mut MP3GranuleInformation = &mut side_info.ch[ch].gr[gr];
-            granule.part2_3_length = breader.read_u16(12).unwrap();
-            granule.big_values = breader.read_u16(9).unwrap();
-            granule.global_gain = breader.read_u8(8).unwrap();
-            granule.scalefac_compress = breader.read_u8(4).unwrap();
-            granule.window_switching_flag = breader.read_bool().unwrap();
+            granule.part2_3_length = breader.read_u16(12).ok()?;
+            granule.big_values = breader.read_u16(9).ok()?;
+            // 1グラニュールのサンプル数を超える組数は不正
+            if 2 * granule.big_values as usize > MP3_NUM_SAMPLES_PER_GRANULE {
+                return None;
+            }
+            granule.global_gain = breader.read_u8(8).ok()?;
+            granule.scalefac_compress = breader.read_u16(if mpeg1 { 4 } else { 9 }).ok()?;
+            granule.window_switching_flag = breader.read_bool().ok()?;
             if granule.window_switching_flag {
-                granule.block_type = match breader.read_u8(2).unwrap() {
+                granule.block_type = match breader.read_u8(2).ok()? {
                     1 => MP3BlockType::Start,
                     2 => MP3BlockType::Short,
                     3 => MP3BlockType::Stop,
@@ -334,12 +587,12 @@ fn decode_side_information(header: &MP3FrameHeader, data: &[u8]) -> Option<MP3Si
                     _ => return None,
                 };
 
-                granule.mixed_block_flag = breader.read_bool().unwrap();
+                granule.mixed_block_flag = breader.read_bool().ok()?;
                 for i in 0..2 {
-                    granule.table_select[i] = breader.read_u8(5).unwrap();
+                    granule.table_select[i] = breader.read_u8(5).ok()?;
                 }
                 for i in 0..3 {
-                    granule.subblock_gain[i] = breader.read_u8(3).unwrap();
+                    granule.subblock_gain[i] = breader.read_u8(3).ok()?;
                 }
 
                 granule.region0_count = match granule.block_type {
@@ -350,14 +603,19 @@ fn decode_side_information(header: &MP3FrameHeader, data: &[u8]) -> Option<MP3Si
             } else {
                 granule.block_type = MP3BlockType::Normal;
                 for i in 0..3 {
-                    granule.table_select[i] = breader.read_u8(5).unwrap();
+                    granule.table_select[i] = breader.read_u8(5).ok()?;
                 }
-                granule.region0_count = breader.read_u8(4).unwrap();
-                granule.region1_count = breader.read_u8(3).unwrap();
+                granule.region0_count = breader.read_u8(4).ok()?;
+                granule.region1_count = breader.read_u8(3).ok()?;
             }
-            granule.preflag = breader.read_bool().unwrap();
-            granule.scalefac_scale = breader.read_u8(1).unwrap();
-            granule.count1table_select = breader.read_u8(1).unwrap();
+            granule.preflag = if mpeg1 {
+                breader.read_bool().ok()?
+            } else {
+                // MPEG2ではscalefac_compressから決まる（インテンシティステレオの右チャンネルは常に無効）
+                granule.scalefac_compress >= 500 && !(is_intensity_stereo!(header) && ch == 1)
+            };
+            granule.scalefac_scale = breader.read_u8(1).ok()?;
+            granule.count1table_select = breader.read_u8(1).ok()?;
         }
     }
 
@@ -372,10 +630,7 @@ fn decode_granule_scale_factor(
     scfsi: &[bool; 4],
     first_gr_scale_factor: &GranuleScaleFactor,
 ) -> GranuleScaleFactor {
-    let mut gr_scale_factor = GranuleScaleFactor {
-        long: [0u8; MP3_NUM_CRITICAL_BANDS_LONG],
-        short: [[0u8; MP3_NUM_CRITICAL_BANDS_SHORT]; 3],
-    };
+    let mut gr_scale_factor = GranuleScaleFactor::default();
 
     match granule.block_type {
         MP3BlockType::Short if granule.window_switching_flag => {
@@ -386,7 +641,7 @@ fn decode_granule_scale_factor(
                         .get_bits(SCALEFACTOR_BITS_TABLE[0][granule.scalefac_compress as usize])
                         as u8;
                 }
-                for sfb in 0..6 {
+                for sfb in 3..6 {
                     for win in 0..3 {
                         gr_scale_factor.short[win][sfb] = buffer
                             .get_bits(SCALEFACTOR_BITS_TABLE[0][granule.scalefac_compress as usize])
@@ -447,6 +702,101 @@ fn decode_granule_scale_factor(
     gr_scale_factor
 }
 
+/// MPEG2(LSF)のスケールファクタのデコード
+fn decode_granule_scale_factor_lsf(
+    buffer: &mut MP3MainDataBuffer,
+    header: &MP3FrameHeader,
+    granule: &MP3GranuleInformation,
+    ch: usize,
+) -> GranuleScaleFactor {
+    let mut gr_scale_factor = GranuleScaleFactor::default();
+
+    // scalefac_compressからブロック番号と分割ごとのビット幅を決定
+    let sfc = granule.scalefac_compress as usize;
+    let (block_number, slen) = if is_intensity_stereo!(header) && ch == 1 {
+        // インテンシティステレオの右チャンネル
+        let sfc = sfc >> 1;
+        if sfc < 180 {
+            (3, [sfc / 36, (sfc % 36) / 6, (sfc % 36) % 6, 0])
+        } else if sfc < 244 {
+            let sfc = sfc - 180;
+            (4, [(sfc & 0x3F) >> 4, (sfc & 0xF) >> 2, sfc & 0x3, 0])
+        } else {
+            let sfc = sfc - 244;
+            (5, [sfc / 3, sfc % 3, 0, 0])
+        }
+    } else if sfc < 400 {
+        (0, [(sfc >> 4) / 5, (sfc >> 4) % 5, (sfc & 0xF) >> 2, sfc & 0x3])
+    } else if sfc < 500 {
+        let sfc = sfc - 400;
+        (1, [(sfc >> 2) / 5, (sfc >> 2) % 5, sfc & 0x3, 0])
+    } else {
+        let sfc = sfc - 500;
+        (2, [sfc / 3, sfc % 3, 0, 0])
+    };
+
+    // ブロックタイプ番号
+    let block_type_number = match granule.block_type {
+        MP3BlockType::Short if granule.window_switching_flag => {
+            if granule.mixed_block_flag {
+                2
+            } else {
+                1
+            }
+        }
+        _ => 0,
+    };
+
+    // 先頭から順にスケールファクタを読み込み、各バンドに割り当て
+    let mut index = 0;
+    for (i, &num_bands) in LSF_SCALEFACTOR_NUM_BANDS_TABLE[block_number][block_type_number]
+        .iter()
+        .enumerate()
+    {
+        let illegal_is_pos = ((1u32 << slen[i]) - 1) as u8;
+        for _ in 0..num_bands {
+            let scale_factor = buffer.get_bits(slen[i] as u8) as u8;
+            match block_type_number {
+                // ロングブロック
+                0 => {
+                    gr_scale_factor.long[index] = scale_factor;
+                    gr_scale_factor.illegal_is_pos_long[index] = illegal_is_pos;
+                }
+                // ミックスドブロックのロング部分
+                2 if index < 6 => {
+                    gr_scale_factor.long[index] = scale_factor;
+                    gr_scale_factor.illegal_is_pos_long[index] = illegal_is_pos;
+                }
+                // ショートブロック（バンド毎に3ウィンドウ分並ぶ）
+                _ => {
+                    let (sfb, win) = if block_type_number == 2 {
+                        (3 + (index - 6) / 3, (index - 6) % 3)
+                    } else {
+                        (index / 3, index % 3)
+                    };
+                    gr_scale_factor.short[win][sfb] = scale_factor;
+                    gr_scale_factor.illegal_is_pos_short[sfb] = illegal_is_pos;
+                }
+            }
+            index += 1;
+        }
+    }
+
+    gr_scale_factor
+}
+
+/// ミックスドブロックでロングブロックとして扱うスケールファクタバンド数を取得
+fn get_num_mixed_long_bands(sfb_table: &ScaleFactorBandIndex) -> usize {
+    // ショートブロックの3バンド目の開始位置までがロングブロック
+    // （8kHz以外は低域2サブバンド分に一致）
+    let short_start = 3 * sfb_table.short[3];
+    sfb_table
+        .long
+        .iter()
+        .position(|&index| index == short_start)
+        .unwrap_or(8)
+}
+
 /// 量子化データのハフマン符号デコード
 fn decode_huffman(
     buffer: &mut MP3MainDataBuffer,
@@ -454,7 +804,7 @@ fn decode_huffman(
     granule: &MP3GranuleInformation,
     part2_start: u64,
     output: &mut [f32; MP3_NUM_SAMPLES_PER_GRANULE],
-) {
+) -> Result<(), MP3DecodeError> {
     /// ビット読み出し位置positionがcount1 data内にあるか判定
     macro_rules! positon_isin_count1data {
         ($position:expr,$part2_start:expr,$part3_end:expr) => {{
@@ -472,20 +822,46 @@ fn decode_huffman(
     let (region1_start, region2_start) = match granule.block_type {
         MP3BlockType::Short if granule.window_switching_flag => {
             // ショートブロックではregion2がない
-            (36, MP3_NUM_SAMPLES_PER_GRANULE)
+            if granule.mixed_block_flag {
+                (2 * MP3_DCT_SIZE, MP3_NUM_SAMPLES_PER_GRANULE)
+            } else {
+                // 3ウィンドウ分の3バンドがregion0
+                let short_table = &get_scalefactorband_index_table!(header.sampling_rate).short;
+                (3 * short_table[3] as usize, MP3_NUM_SAMPLES_PER_GRANULE)
+            }
         }
         _ => {
+            // テーブルを超えるリージョンはグラニュール末尾まで
             let long_table = &get_scalefactorband_index_table!(header.sampling_rate).long;
+            let region_start = |sfb: usize| {
+                long_table
+                    .get(sfb)
+                    .map_or(MP3_NUM_SAMPLES_PER_GRANULE, |&start| start as usize)
+            };
             (
-                long_table[granule.region0_count as usize + 1] as usize,
-                long_table[granule.region0_count as usize + 1 + granule.region1_count as usize + 1]
-                    as usize,
+                region_start(granule.region0_count as usize + 1),
+                region_start(granule.region0_count as usize + granule.region1_count as usize + 2),
             )
         }
     };
 
+    /// part2_startから読み出したビット数
+    macro_rules! get_num_read_bits {
+        () => {{
+            (buffer.get_total_read_bits() + MP3_MAINDATA_BUFFER_SIZE_BITS - part2_start)
+                % MP3_MAINDATA_BUFFER_SIZE_BITS
+        }};
+    }
+
+    // スケールファクタがpart2_3_lengthを超えていれば不正
+    if get_num_read_bits!() > granule.part2_3_length as u64 {
+        return Err(MP3DecodeError::InvalidFormat);
+    }
+
     // bigvalueの復号
-    assert!((2 * granule.big_values as usize) <= MP3_NUM_SAMPLES_PER_GRANULE);
+    if (2 * granule.big_values as usize) > MP3_NUM_SAMPLES_PER_GRANULE {
+        return Err(MP3DecodeError::InvalidSideInformation);
+    }
     for i in (0..(2 * granule.big_values as usize)).step_by(2) {
         let index = if i < region1_start {
             granule.table_select[0]
@@ -498,6 +874,10 @@ fn decode_huffman(
         let xy = mp3_huffman_decode_big_value(index as usize, buffer);
         output[i + 0] = xy.0 as f32;
         output[i + 1] = xy.1 as f32;
+        // part2_3_lengthを超えて読み出したら不正
+        if get_num_read_bits!() > granule.part2_3_length as u64 {
+            return Err(MP3DecodeError::InvalidFormat);
+        }
     }
 
     // count1(-1,0,1)の復号
@@ -536,6 +916,108 @@ fn decode_huffman(
     if position != part3_end {
         buffer.seek(part3_end);
     }
+
+    Ok(())
+}
+
+/// インテンシティステレオ位置の計算
+/// 右チャンネルのハフマン復号結果から0値領域の境界を求め、境界以降のスケールファクタバンドに位置を設定
+fn get_intensity_stereo_position(
+    header: &MP3FrameHeader,
+    granule: &MP3GranuleInformation,
+    scale_factor: &GranuleScaleFactor,
+    quantized: &[f32; MP3_NUM_SAMPLES_PER_GRANULE],
+    is_pos: &mut [u8; MP3_NUM_SAMPLES_PER_GRANULE],
+) {
+    /// 不正位置を無効値に置き換え（MPEG1ではテーブルの範囲外となる不正位置以上も無効とする）
+    macro_rules! to_is_position {
+        ($pos:expr,$illegal_pos:expr) => {{
+            if $pos >= $illegal_pos {
+                MP3_INVALID_IS_POSITION
+            } else {
+                $pos
+            }
+        }};
+    }
+
+    /// longブロックのインテンシティステレオ位置の設定
+    fn set_long_position(
+        sfb_long_index: &[u16; MP3_NUM_CRITICAL_BANDS_LONG],
+        scale_factor: &GranuleScaleFactor,
+        quantized: &[f32; MP3_NUM_SAMPLES_PER_GRANULE],
+        sfb_end: usize,
+        is_pos: &mut [u8; MP3_NUM_SAMPLES_PER_GRANULE],
+    ) {
+        // 非零値を含む最大のバンドの次のバンドから適用
+        let nonzero_end = sfb_long_index[sfb_end] as usize;
+        let sfb_start = match quantized[..nonzero_end].iter().rposition(|&x| x != 0.0) {
+            Some(last) => sfb_long_index
+                .iter()
+                .position(|&index| index as usize > last)
+                .unwrap_or(sfb_end),
+            None => 0,
+        };
+        for sfb in sfb_start..sfb_end {
+            // 最終バンドはスケールファクタを持たないため直前のバンドの値を使用
+            let sfb_pos = min(sfb, MP3_NUM_CRITICAL_BANDS_LONG - 3);
+            let pos = to_is_position!(
+                scale_factor.long[sfb_pos],
+                scale_factor.illegal_is_pos_long[sfb_pos]
+            );
+            is_pos[sfb_long_index[sfb] as usize..sfb_long_index[sfb + 1] as usize].fill(pos);
+        }
+    }
+
+    is_pos.fill(MP3_INVALID_IS_POSITION);
+
+    let sfb_table = get_scalefactorband_index_table!(header.sampling_rate);
+    match granule.block_type {
+        MP3BlockType::Short if granule.window_switching_flag => {
+            let sfb_short_index = &sfb_table.short;
+            // ミックスドブロックではショートの3バンド目から
+            let sfb_start = if granule.mixed_block_flag { 3 } else { 0 };
+            let mut max_sfb = sfb_start;
+            for win in 0..3 {
+                // ウィンドウ内で非零値を含む最大のバンドの次のバンドから適用
+                let mut is_sfb_start = sfb_start;
+                for sfb in (sfb_start..MP3_NUM_CRITICAL_BANDS_SHORT).rev() {
+                    let width = (sfb_short_index[sfb + 1] - sfb_short_index[sfb]) as usize;
+                    let offset = 3 * sfb_short_index[sfb] as usize + win * width;
+                    if quantized[offset..offset + width].iter().any(|&x| x != 0.0) {
+                        is_sfb_start = sfb + 1;
+                        break;
+                    }
+                }
+                max_sfb = max(max_sfb, is_sfb_start);
+                for sfb in is_sfb_start..MP3_NUM_CRITICAL_BANDS_SHORT {
+                    // 最終バンドはスケールファクタを持たないため直前のバンドの値を使用
+                    let sfb_pos = min(sfb, MP3_NUM_CRITICAL_BANDS_SHORT - 2);
+                    let pos = to_is_position!(
+                        scale_factor.short[win][sfb_pos],
+                        scale_factor.illegal_is_pos_short[sfb_pos]
+                    );
+                    let width = (sfb_short_index[sfb + 1] - sfb_short_index[sfb]) as usize;
+                    let offset = 3 * sfb_short_index[sfb] as usize + win * width;
+                    is_pos[offset..offset + width].fill(pos);
+                }
+            }
+            // ミックスドブロックでショート部分がすべて0ならばロング部分にも適用
+            if granule.mixed_block_flag && max_sfb == sfb_start {
+                let sfb_end = get_num_mixed_long_bands(sfb_table);
+                set_long_position(&sfb_table.long, scale_factor, quantized, sfb_end, is_pos);
+            }
+        }
+        _ => {
+            // ロングブロック
+            set_long_position(
+                &sfb_table.long,
+                scale_factor,
+                quantized,
+                MP3_NUM_CRITICAL_BANDS_LONG - 1,
+                is_pos,
+            );
+        }
+    }
 }
 
 /// 逆量子化
@@ -595,6 +1077,8 @@ fn dequantize(
             // ミックスドブロック
             let sfb_long_index = &get_scalefactorband_index_table!(header.sampling_rate).long;
             let sfb_short_index = &get_scalefactorband_index_table!(header.sampling_rate).short;
+            let num_long_bands =
+                get_num_mixed_long_bands(get_scalefactorband_index_table!(header.sampling_rate));
             // クリティカルバンド境界の初期化
             let mut next_cb_bound = sfb_long_index[1] as usize;
             let mut cb_width = sfb_short_index[1];
@@ -606,9 +1090,9 @@ fn dequantize(
                 // クリティカルバンド境界の更新
                 if i == next_cb_bound as usize {
                     cb += 1;
-                    if i < sfb_long_index[8] as usize {
+                    if i < sfb_long_index[num_long_bands] as usize {
                         next_cb_bound = sfb_long_index[cb + 1] as usize;
-                    } else if i == sfb_long_index[8] as usize {
+                    } else if i == sfb_long_index[num_long_bands] as usize {
                         next_cb_bound = 3 * sfb_short_index[4] as usize;
                         cb = 3;
                         cb_width = sfb_short_index[cb + 1] - sfb_short_index[cb];
@@ -623,7 +1107,7 @@ fn dequantize(
                 // サブバンド内インデックス
                 // 量子化ステップ幅計算（スケールファクタ適用）
                 let gain = global_gain
-                    * if (i / MP3_DCT_SIZE) >= 2 {
+                    * if i >= sfb_long_index[num_long_bands] as usize {
                         let short_index = (i - cb_begin as usize) / cb_width as usize;
                         2.0f64.powf(
                             -2.0 * granule.subblock_gain[short_index] as f64
@@ -702,6 +1186,10 @@ pub enum MP3DecodeError {
     InsufficientBuffer,
     /// データサイズが不十分
     InsufficientData,
+    /// CRCが一致しない
+    CrcMismatch,
+    /// フレームを完成させるためのデータが不足（ストリーミングデコード時）
+    NeedMoreData,
 }
 
 impl fmt::Display for MP3DecodeError {
@@ -717,8 +1205,23 @@ impl error::Error for MP3DecodeError {
 }
 
 /// フレーム情報のデコード
-fn decode_frame_information(
+pub(crate) fn decode_frame_information(
+    data: &[u8],
+    free_format_frame_size: &mut usize,
+) -> Result<(usize, usize, MP3FrameHeader, MP3SideInformation), MP3DecodeError> {
+    let (read_pos, maindata_size, header, side_info) =
+        decode_frame_information_unclamped(data, free_format_frame_size)?;
+
+    // メインデータサイズの計算
+    let maindata_size = min(data.len() - read_pos, maindata_size);
+
+    Ok((read_pos, maindata_size, header, side_info))
+}
+
+/// フレーム情報のデコード（メインデータサイズをデータ末尾で切り詰めない）
+fn decode_frame_information_unclamped(
     data: &[u8],
+    free_format_frame_size: &mut usize,
 ) -> Result<(usize, usize, MP3FrameHeader, MP3SideInformation), MP3DecodeError> {
     let mut read_pos = 0;
 
@@ -733,47 +1236,278 @@ fn decode_frame_information(
     let Some(header) = decode_frame_header(&data[read_pos..]) else {
         return Err(MP3DecodeError::InvalidHeader);
     };
-    read_pos += MP3_FRAMEHEADER_SIZE;
 
-    // サイドインフォメーションをデコード
-    let Some(side_info) = decode_side_information(&header, &data[read_pos..]) else {
-        return Err(MP3DecodeError::InvalidSideInformation);
+    // フレームサイズの計算
+    let frame_size = match header.bit_rate {
+        MP3BitRate::Kbps0 => {
+            // フリーフォーマットは未計測であれば次の同期ヘッダまでの距離から計測
+            // 次の同期ヘッダがなければ（最終フレーム）データ末尾までとする
+            if *free_format_frame_size == 0 {
+                match measure_free_format_frame_size(&header, &data[read_pos..]) {
+                    Some(size) => {
+                        *free_format_frame_size = size;
+                        size
+                    }
+                    None => (data.len() - read_pos).saturating_sub(get_padding_size(&header)),
+                }
+            } else {
+                *free_format_frame_size
+            }
+        }
+        _ => get_frame_size(&header, header.bit_rate as usize),
     };
-    read_pos += get_sideinformation_size!(header);
+    // 最大ビットレートを超えるサイズは不正
+    if frame_size > get_frame_size(&header, MP3_MAX_FREE_FORMAT_BIT_RATE) {
+        return Err(MP3DecodeError::InvalidFormat);
+    }
+    let Some(maindata_size) = get_maindata_size(&header, frame_size) else {
+        return Err(MP3DecodeError::InvalidFormat);
+    };
+    // Layer3はメインデータバッファにmaindata_beginの最大値(511byte)分と合わせて収まらなければ不正
+    if header.layer == MP3Layer::Layer3 && maindata_size + 512 > MP3_MAINDATA_BUFFER_SIZE {
+        return Err(MP3DecodeError::InvalidFormat);
+    }
 
-    // CRC16の読み飛ばし
+    read_pos += MP3_FRAMEHEADER_SIZE;
+
+    // CRC16の読み飛ばし（ヘッダの直後に配置される）
     if header.error_protection {
         read_pos += 2;
     }
+    if read_pos > data.len() {
+        return Err(MP3DecodeError::InsufficientData);
+    }
 
-    // メインデータサイズの計算
-    let maindata_size = min(data.len() - read_pos, get_maindata_size(&header));
+    // サイドインフォメーションをデコード（Layer3のみ）
+    let side_info = match header.layer {
+        MP3Layer::Layer3 => {
+            let Some(side_info) = decode_side_information(&header, &data[read_pos..]) else {
+                return Err(MP3DecodeError::InvalidSideInformation);
+            };
+            read_pos += get_sideinformation_size!(header);
+            side_info
+        }
+        _ => MP3SideInformation::default(),
+    };
 
     Ok((read_pos, maindata_size, header, side_info))
 }
 
-/// ID3v2タグ全体のサイズを計算
+/// 先頭のヘッダから計算したフレーム長の位置に、一貫したヘッダを持つフレームがnum_frames個続くか検証
+/// audio_end（末尾のタグの先頭）に達した場合はそれまでのフレームで判定する
+fn validate_frame_sync(
+    data: &[u8],
+    audio_end: usize,
+    num_frames: usize,
+    mut free_format_frame_size: usize,
+) -> bool {
+    let Some(first) = decode_frame_header(data) else {
+        return false;
+    };
+    let is_monoral =
+        |header: &MP3FrameHeader| matches!(header.channel_mode, MP3ChannelMode::Monoral);
+    let mut pos = 0;
+    for _ in 0..num_frames {
+        match decode_frame_header(&data[pos..]) {
+            Some(header)
+                if header.version == first.version
+                    && header.layer == first.layer
+                    && header.sampling_rate == first.sampling_rate
+                    && is_monoral(&header) == is_monoral(&first) => {}
+            _ => return false,
+        }
+        let Ok((header_size, maindata_size, _, _)) =
+            decode_frame_information_unclamped(&data[pos..], &mut free_format_frame_size)
+        else {
+            return false;
+        };
+        pos += header_size + maindata_size;
+        if pos >= audio_end {
+            break;
+        }
+        // フレーム間に挟まれたID3v2タグを読み飛ばす
+        pos += get_leading_tags_size(&data[pos..]);
+        if pos >= audio_end {
+            break;
+        }
+    }
+    true
+}
+
+/// 同期が確定するフレームの先頭位置を探索
+/// 先頭以外の候補はnum_frames個のフレームが続くことを確認する
+pub(crate) fn find_frame_start(
+    data: &[u8],
+    audio_end: usize,
+    num_frames: usize,
+    free_format_frame_size: usize,
+) -> Option<usize> {
+    let mut pos = 0;
+    while pos < audio_end {
+        let sync_pos = pos + find_sync_code(&data[pos..audio_end])?;
+        // 先頭から始まるフレームは同期が取れているとみなし、候補のみ検査
+        let num_frames = if sync_pos == 0 { 1 } else { num_frames };
+        if validate_frame_sync(
+            &data[sync_pos..],
+            audio_end - sync_pos,
+            num_frames,
+            free_format_frame_size,
+        ) {
+            return Some(sync_pos);
+        }
+        pos = sync_pos + 1;
+    }
+    None
+}
+
+/// 先頭のフレームからフレーム長の位置にあるヘッダをたどり、num_frames個後のフレームの位置を求める
+/// 途中で終端に達した場合はデータ長、バージョン・レイヤー・サンプリングレートが一貫したヘッダが続かなければNone
+fn skip_frames(data: &[u8], num_frames: usize) -> Option<usize> {
+    let first = decode_frame_header(data)?;
+    let mut free_format_frame_size = 0;
+    let mut pos = 0;
+    for _ in 0..num_frames {
+        if pos >= data.len() {
+            return Some(data.len());
+        }
+        match decode_frame_header(&data[pos..]) {
+            Some(header)
+                if header.version == first.version
+                    && header.layer == first.layer
+                    && header.sampling_rate == first.sampling_rate => {}
+            _ => return None,
+        }
+        let (header_size, maindata_size, _, _) =
+            decode_frame_information_unclamped(&data[pos..], &mut free_format_frame_size).ok()?;
+        pos += header_size + maindata_size;
+        // フレーム間に挟まれたID3v2タグを読み飛ばす
+        if let Some(data) = data.get(pos..) {
+            pos += get_leading_tags_size(data);
+        }
+    }
+    Some(min(pos, data.len()))
+}
+
+/// CRC16の検査（frameはフレームヘッダ先頭から）
+fn check_crc(header: &MP3FrameHeader, frame: &[u8]) -> bool {
+    const CRC_SIZE: usize = 2;
+
+    // データサイズ不足
+    if frame.len() < MP3_FRAMEHEADER_SIZE + CRC_SIZE {
+        return false;
+    }
+    let data = &frame[MP3_FRAMEHEADER_SIZE + CRC_SIZE..];
+
+    // 保護されるビット数
+    let protected_bits = match header.layer {
+        MP3Layer::Layer1 => get_layer1_crc_protected_bits(header),
+        MP3Layer::Layer2 => match get_layer2_crc_protected_bits(header, data) {
+            Ok(bits) => bits,
+            Err(_) => return false,
+        },
+        MP3Layer::Layer3 => get_sideinformation_size!(header) * 8,
+    };
+    if data.len() * 8 < protected_bits {
+        return false;
+    }
+
+    // ヘッダの後半16bitと保護されたデータから計算
+    let mut crc = mp3_crc16_update(MP3_CRC16_INITIAL_VALUE, &frame[2..4], 16);
+    crc = mp3_crc16_update(crc, data, protected_bits);
+
+    crc == u16::from_be_bytes([frame[4], frame[5]])
+}
+
+/// 末尾のタグ(APE, Lyrics3v2, TAG+, ID3v1)を除いた音声データの終端位置を取得
+pub(crate) fn get_audio_end(data: &[u8]) -> usize {
+    data.len() - get_trailing_tags_size(data)
+}
+
+/// ID3v2タグ全体のサイズを計算（フッタを含む）
 pub fn get_id3v2tag_size(data: &[u8]) -> Result<usize, MP3DecodeError> {
-    const ID3V2HEADER_SIZE: usize = 10;
+    Ok(decode_id3v2_header(data)?.tag_size())
+}
 
-    // サイズ不足
-    if data.len() < ID3V2HEADER_SIZE {
-        return Err(MP3DecodeError::InsufficientData);
+/// フレームヘッダの内容をフォーマット情報に反映
+fn update_format_information(format: &mut MP3FormatInformation, header: &MP3FrameHeader) {
+    // ステレオチャンネルを検知
+    format.num_channels = match header.channel_mode {
+        MP3ChannelMode::Stereo | MP3ChannelMode::JointStereo | MP3ChannelMode::DualChannel => 2,
+        _ => format.num_channels,
+    };
+    // デュアルチャンネルを検知
+    if matches!(header.channel_mode, MP3ChannelMode::DualChannel) {
+        format.dual_channel = true;
     }
+    format.sampling_rate = header.sampling_rate;
+    format.bit_rate = header.bit_rate;
+    format.num_samples_per_frame = get_num_samples_per_frame(header);
+}
 
-    // タグが不正
-    if data[0] != b'I' || data[1] != b'D' || data[2] != b'3' {
-        return Err(MP3DecodeError::InvalidFormat);
+/// 平均ビットレート(bps)の計算
+fn get_average_bit_rate(
+    num_bytes: usize,
+    num_samples: usize,
+    sampling_rate: MP3SamplingRate,
+) -> usize {
+    if num_samples == 0 {
+        return 0;
     }
+    (8 * num_bytes as u64 * sampling_rate as u64 / num_samples as u64) as usize
+}
 
-    // タグ以降のサイズを計算
-    // https://eleken.y-lab.org/report/other/mp3tags.shtml を参照
-    let size = ((data[6] as usize) << 21)
-        + ((data[7] as usize) << 14)
-        + ((data[8] as usize) << 7)
-        + ((data[9] as usize) << 0);
+/// Xing/Infoタグのフレームであればフレームサイズとタグ情報を取得
+fn decode_xing_frame(
+    data: &[u8],
+    free_format_frame_size: &mut usize,
+) -> Option<(usize, MP3FrameHeader, MP3XingInformation)> {
+    let (header_size, maindata_size, header, _) =
+        decode_frame_information(data, free_format_frame_size).ok()?;
+    // タグはLayer3のメインデータ先頭に配置される
+    if header.layer != MP3Layer::Layer3 {
+        return None;
+    }
+    let frame_start = find_sync_code(data)?;
+    let xing = mp3_decode_xing_information(
+        &data[frame_start..header_size + maindata_size],
+        header_size - frame_start,
+    )?;
+    Some((header_size + maindata_size, header, xing))
+}
 
-    Ok(ID3V2HEADER_SIZE + size)
+/// VBRIタグのフレームであればフレームサイズとタグ情報を取得
+fn decode_vbri_frame(
+    data: &[u8],
+    free_format_frame_size: &mut usize,
+) -> Option<(usize, MP3FrameHeader, MP3VbriInformation)> {
+    let (header_size, maindata_size, header, _) =
+        decode_frame_information(data, free_format_frame_size).ok()?;
+    if header.layer != MP3Layer::Layer3 {
+        return None;
+    }
+    let frame_start = find_sync_code(data)?;
+    let vbri = mp3_decode_vbri_information(&data[frame_start..header_size + maindata_size])?;
+    Some((header_size + maindata_size, header, vbri))
+}
+
+/// VBRタグ（Xing/Info, VBRI）のフレームか判定
+pub(crate) fn is_vbr_tag_frame(
+    data: &[u8],
+    header: &MP3FrameHeader,
+    header_size: usize,
+    maindata_size: usize,
+) -> bool {
+    if header.layer != MP3Layer::Layer3 {
+        return false;
+    }
+    let frame_end = header_size + maindata_size;
+    if mp3_decode_xing_information(&data[header_size..frame_end], 0).is_some() {
+        return true;
+    }
+    match find_sync_code(data) {
+        Some(frame_start) => mp3_decode_vbri_information(&data[frame_start..frame_end]).is_some(),
+        None => false,
+    }
 }
 
 /// フォーマット情報の取得
@@ -782,36 +1516,81 @@ pub fn get_format_information(data: &[u8]) -> Result<MP3FormatInformation, MP3De
     let mut format = MP3FormatInformation {
         num_channels: 1,
         num_samples: 0,
+        num_samples_per_frame: MP3_NUM_SAMPLES_PER_FRAME,
+        dual_channel: false,
         sampling_rate: MP3SamplingRate::Hz44100,
         bit_rate: MP3BitRate::Kbps128,
+        average_bit_rate: 0,
+        vbr: false,
+        xing: None,
+        vbri: None,
     };
 
-    // ID3v2タグをスキップ
-    let mut read_pos = match get_id3v2tag_size(data) {
-        Ok(size) => size,
-        Err(err) => match err {
-            MP3DecodeError::InvalidFormat => 0,
-            _ => {
-                return Err(err);
-            }
-        },
-    };
+    // 末尾のタグを除く
+    let data = &data[..get_audio_end(data)];
+
+    // 先頭のID3v2タグをスキップ
+    let mut read_pos = get_leading_tags_size(data);
+
+    // 先頭フレームのXing/Infoタグを確認
+    let mut free_format_frame_size = 0;
+    let mut tag = None;
+    if let Some((tag_frame_size, header, xing)) =
+        decode_xing_frame(&data[read_pos..], &mut free_format_frame_size)
+    {
+        format.xing = Some(xing);
+        format.vbr = xing.vbr;
+        read_pos += tag_frame_size;
+        tag = xing
+            .num_frames
+            .map(|num_frames| (tag_frame_size, header, num_frames, xing.num_bytes));
+    } else if let Some((tag_frame_size, header, vbri)) =
+        decode_vbri_frame(&data[read_pos..], &mut free_format_frame_size)
+    {
+        format.vbri = Some(vbri);
+        format.vbr = true;
+        read_pos += tag_frame_size;
+        tag = Some((
+            tag_frame_size,
+            header,
+            vbri.num_frames,
+            Some(vbri.num_bytes),
+        ));
+    }
+
+    // フレーム数が記録されていれば全フレームを走査せずに求める
+    if let Some((tag_frame_size, header, num_frames, num_bytes)) = tag {
+        // タグのフレームはビットレートが異なりうるため、後続の音声フレームのヘッダを優先
+        match decode_frame_information(&data[read_pos..], &mut free_format_frame_size) {
+            Ok((_, _, audio_header, _)) => update_format_information(&mut format, &audio_header),
+            Err(_) => update_format_information(&mut format, &header),
+        }
+        format.num_samples = num_frames as usize * format.num_samples_per_frame;
+        // バイト数はタグのフレームを含むため差し引く
+        let num_bytes = match num_bytes {
+            Some(num_bytes) => (num_bytes as usize).saturating_sub(tag_frame_size),
+            None => data.len() - read_pos,
+        };
+        format.average_bit_rate =
+            get_average_bit_rate(num_bytes, format.num_samples, format.sampling_rate);
+        return Ok(format);
+    }
 
     // 先頭からフレーム情報のみを取得
+    let mut num_bytes = 0;
     loop {
-        match decode_frame_information(&data[read_pos..]) {
+        // フレーム間に挟まれたID3v2タグを読み飛ばす
+        read_pos += get_leading_tags_size(&data[read_pos..]);
+        match decode_frame_information(&data[read_pos..], &mut free_format_frame_size) {
             Ok((header_size, maindata_size, header, _)) => {
-                // ステレオチャンネルを検知
-                format.num_channels = match header.channel_mode {
-                    MP3ChannelMode::Stereo
-                    | MP3ChannelMode::JointStereo
-                    | MP3ChannelMode::DualChannel => 2,
-                    _ => format.num_channels,
-                };
-                format.sampling_rate = header.sampling_rate;
-                format.bit_rate = header.bit_rate;
-                format.num_samples += MP3_NUM_SAMPLES_PER_FRAME;
+                // ビットレートが変化していればVBR
+                if format.num_samples > 0 && header.bit_rate != format.bit_rate {
+                    format.vbr = true;
+                }
+                update_format_information(&mut format, &header);
+                format.num_samples += format.num_samples_per_frame;
                 read_pos += header_size + maindata_size;
+                num_bytes += header_size + maindata_size;
             }
             Err(e) => match e {
                 MP3DecodeError::EndOfStream => break,
@@ -819,10 +1598,88 @@ pub fn get_format_information(data: &[u8]) -> Result<MP3FormatInformation, MP3De
             },
         }
     }
+    format.average_bit_rate =
+        get_average_bit_rate(num_bytes, format.num_samples, format.sampling_rate);
 
     Ok(format)
 }
 
+/// VBRタグのTOCからサンプル位置に対応するデータ先頭からのバイト位置を取得
+pub fn get_seek_position(
+    data: &[u8],
+    format: &MP3FormatInformation,
+    sample: usize,
+) -> Option<usize> {
+    if format.num_samples == 0 {
+        return None;
+    }
+    let sample = min(sample, format.num_samples);
+
+    // タグのフレームは先頭のID3v2タグの直後
+    let tag_start = get_leading_tags_size(data);
+
+    // Xing: 再生位置の百分率ごとにファイル位置(/256)が記録されている
+    if let Some(xing) = format.xing
+        && let (Some(toc), Some(num_bytes)) = (xing.toc, xing.num_bytes)
+    {
+        let percent = sample as f32 * 100.0 / format.num_samples as f32;
+        let index = min(percent as usize, toc.len() - 1);
+        let start = toc[index] as f32;
+        let end = if index < toc.len() - 1 {
+            toc[index + 1] as f32
+        } else {
+            256.0
+        };
+        // 区間内は線形補間
+        let pos = start + (end - start) * (percent - index as f32);
+        return Some(tag_start + (pos * num_bytes as f32 / 256.0) as usize);
+    }
+
+    // VBRI: タグのフレームに続く音声フレームについて、一定フレーム数ごとの区間のバイト数が記録されている
+    if let Some(vbri) = format.vbri
+        && vbri.frames_per_toc_entry > 0
+    {
+        let (tag_frame_size, _, _) = decode_vbri_frame(data.get(tag_start..)?, &mut 0)?;
+        // TOCはタグのフレームから読み出す
+        let frame = &data[tag_start..];
+        let frame = &frame[find_sync_code(frame)?..];
+        let entry_samples = vbri.frames_per_toc_entry as usize * format.num_samples_per_frame;
+        let index = min(sample / entry_samples, vbri.num_toc_entries);
+        let mut pos = tag_start + tag_frame_size;
+        for entry in 0..index {
+            pos += mp3_read_vbri_toc_entry(frame, &vbri, entry)? as usize;
+        }
+        // 区間内は線形補間
+        if index < vbri.num_toc_entries {
+            let offset = sample - index * entry_samples;
+            pos += mp3_read_vbri_toc_entry(frame, &vbri, index)? as usize * offset / entry_samples;
+        }
+        return Some(pos);
+    }
+
+    None
+}
+
+/// ReplayGain情報の取得（ID3v2, APE, LAMEタグの順に優先）
+pub fn get_replay_gain(data: &[u8]) -> MP3ReplayGain {
+    let mut replay_gain = decode_tags(data).replay_gain();
+
+    // 不足している情報をLAMEタグで補う
+    let data = &data[get_leading_tags_size(data)..];
+    if let Some((_, _, xing)) = decode_xing_frame(data, &mut 0)
+        && let Some(lame) = xing.lame
+    {
+        replay_gain.track_gain = replay_gain.track_gain.or(lame.track_gain);
+        replay_gain.album_gain = replay_gain.album_gain.or(lame.album_gain);
+        // ピークが0ならば未計測
+        if lame.peak > 0.0 {
+            replay_gain.track_peak = replay_gain.track_peak.or(Some(lame.peak));
+        }
+    }
+
+    replay_gain
+}
+
 impl MP3Decoder {
     /// デコーダ生成
     pub fn new() -> Self {
@@ -830,9 +1687,87 @@ impl MP3Decoder {
             maindata_buffer: MP3MainDataBuffer::new(),
             synth_buffer: [MP3SynthesisBuffer::new(), MP3SynthesisBuffer::new()],
             maindata_start: 0,
+            free_format_frame_size: 0,
+            crc_policy: MP3CrcPolicy::Ignore,
+            deemphasis: false,
+            deemphasis_filter: [MP3DeemphasisFilter::new(), MP3DeemphasisFilter::new()],
+            dual_channel_output: MP3DualChannelOutput::Both,
+            gapless: false,
+            replay_gain_mode: MP3ReplayGainMode::Disabled,
+            clipping_prevention: true,
+            replay_gain: MP3ReplayGain {
+                track_gain: None,
+                track_peak: None,
+                album_gain: None,
+                album_peak: None,
+            },
+            num_sync_frames: 1,
+            skipped_range: None,
+            concealment_mode: MP3ConcealmentMode::Silence,
+            concealed: false,
+            maindata_available: 0,
         }
     }
 
+    /// CRC不一致フレームの扱いを設定
+    pub fn set_crc_policy(&mut self, policy: MP3CrcPolicy) {
+        self.crc_policy = policy;
+    }
+
+    /// デエンファシスを行うか設定（デフォルトで無効）
+    pub fn set_deemphasis(&mut self, enable: bool) {
+        self.deemphasis = enable;
+    }
+
+    /// デュアルチャンネルの出力チャンネルを設定
+    pub fn set_dual_channel_output(&mut self, output: MP3DualChannelOutput) {
+        self.dual_channel_output = output;
+    }
+
+    /// ギャップレス再生を行うか設定
+    /// 有効時はLAMEタグに従い、先頭の遅延と末尾のパディングを取り除いて出力
+    pub fn set_gapless(&mut self, enable: bool) {
+        self.gapless = enable;
+    }
+
+    /// 同期の確定に使う連続フレーム数を設定
+    /// 与えたデータの先頭がフレームでなく同期を探索する場合、同期コードの候補から計算したフレーム長の位置に
+    /// バージョン・レイヤー・サンプリングレート・チャンネル数が一致するヘッダがnum_frames個続く候補のみを
+    /// フレームとして扱う（1で候補のみ検査）
+    pub fn set_num_sync_frames(&mut self, num_frames: usize) {
+        self.num_sync_frames = max(num_frames, 1);
+    }
+
+    /// 直近のdecode_frameで同期の探索により読み飛ばした不正なデータの範囲（与えたデータの先頭から）
+    pub fn skipped_range(&self) -> Option<Range<usize>> {
+        self.skipped_range.clone()
+    }
+
+    /// 破損・欠落フレームの隠蔽方法を設定（デフォルトは無音）
+    pub fn set_concealment_mode(&mut self, mode: MP3ConcealmentMode) {
+        self.concealment_mode = mode;
+    }
+
+    /// 直近のdecode_frame, conceal_frameで出力したフレームが隠蔽したものか
+    pub fn is_concealed(&self) -> bool {
+        self.concealed
+    }
+
+    /// ReplayGainの適用モードを設定
+    pub fn set_replay_gain_mode(&mut self, mode: MP3ReplayGainMode) {
+        self.replay_gain_mode = mode;
+    }
+
+    /// ReplayGain適用時のクリッピング防止を設定（デフォルトで有効）
+    pub fn set_clipping_prevention(&mut self, enable: bool) {
+        self.clipping_prevention = enable;
+    }
+
+    /// 適用するReplayGain情報を設定（decode_wholeはデータから取得した情報で上書きする）
+    pub fn set_replay_gain(&mut self, replay_gain: MP3ReplayGain) {
+        self.replay_gain = replay_gain;
+    }
+
     /// デコーダ内部状態リセット
     pub fn reset(&mut self) {
         self.maindata_buffer.reset();
@@ -840,15 +1775,65 @@ impl MP3Decoder {
             buf.reset();
         }
         self.maindata_start = 0;
+        self.maindata_available = 0;
+        self.free_format_frame_size = 0;
+        for filter in &mut self.deemphasis_filter {
+            filter.reset();
+        }
+    }
+
+    /// データの欠落を通知 欠落以前のメインデータを参照するフレームは隠蔽する
+    pub(crate) fn invalidate_maindata(&mut self) {
+        self.maindata_available = 0;
+    }
+
+    /// 同期コードから始まるデータについて、先頭フレームのサイズとVBRタグのフレームかを取得
+    /// フレームの終端が確定しなければNoneを返す（ストリーム終端ではデータ末尾までとする）
+    pub(crate) fn measure_stream_frame(
+        &mut self,
+        data: &[u8],
+        end_of_stream: bool,
+    ) -> Result<Option<(usize, bool)>, MP3DecodeError> {
+        // ヘッダとサイドインフォメーションの最大サイズ
+        const MAX_FRAME_INFORMATION_SIZE: usize =
+            MP3_FRAMEHEADER_SIZE + 2 + MP3_SIDEINFORMATION_SIZE_STEREO;
+        if data.len() < MAX_FRAME_INFORMATION_SIZE && !end_of_stream {
+            return Ok(None);
+        }
+        let Some(header) = decode_frame_header(data) else {
+            return Err(MP3DecodeError::InvalidHeader);
+        };
+
+        // フリーフォーマットは次のヘッダが届くまでサイズが定まらない
+        if header.bit_rate == MP3BitRate::Kbps0 && self.free_format_frame_size == 0 {
+            match measure_free_format_frame_size(&header, data) {
+                Some(size) => self.free_format_frame_size = size,
+                None if !end_of_stream => return Ok(None),
+                None => {}
+            }
+        }
+
+        let (header_size, maindata_size, header, _) =
+            decode_frame_information_unclamped(data, &mut self.free_format_frame_size)?;
+        let frame_size = header_size + maindata_size;
+        if frame_size > data.len() {
+            if !end_of_stream {
+                return Ok(None);
+            }
+            return Ok(Some((data.len(), false)));
+        }
+        let is_tag = is_vbr_tag_frame(&data[..frame_size], &header, header_size, maindata_size);
+        Ok(Some((frame_size, is_tag)))
     }
 
-    /// メインデータのデコード
+    /// メインデータのデコード（必要なデータが不足している・不正であればfalse）
     fn decode_maindata(
         &mut self,
         header: &MP3FrameHeader,
         side_info: &MP3SideInformation,
+        maindata_size: usize,
         output: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
-    ) {
+    ) -> bool {
         // バイト境界に揃える
         self.maindata_buffer.align_next_byte();
 
@@ -861,8 +1846,8 @@ impl MP3Decoder {
         } else {
             // maindata_beginの後でバッファを折り返して先頭に戻った場合、負値になるためバッファ一周分補正
             if (MP3_MAINDATA_BUFFER_SIZE + self.maindata_start) < maindata_offset {
-                // 必要なデータ不足（フレーム破棄などで対処）
-                return;
+                // 必要なデータ不足（呼び出し元で隠蔽）
+                return false;
             }
             MP3_MAINDATA_BUFFER_SIZE + self.maindata_start - maindata_offset
         };
@@ -871,7 +1856,7 @@ impl MP3Decoder {
         self.maindata_buffer.skip(discard_bytes as u64 * 8);
 
         // メインデータ開始位置の更新
-        self.maindata_start += get_maindata_size(&header);
+        self.maindata_start += maindata_size;
         // バッファの回り込み
         if self.maindata_start > MP3_MAINDATA_BUFFER_SIZE {
             self.maindata_start -= MP3_MAINDATA_BUFFER_SIZE;
@@ -883,9 +1868,14 @@ impl MP3Decoder {
             _ => 2,
         };
 
+        // インテンシティステレオが有効か
+        let intensity_stereo = is_intensity_stereo!(header);
+
         let mut scale_factor = MP3ScaleFactor::default();
+        let mut is_pos =
+            [[MP3_INVALID_IS_POSITION; MP3_NUM_SAMPLES_PER_GRANULE]; MP3_NUM_GRANLES_PER_FRAME];
 
-        for gr in 0..MP3_NUM_GRANLES_PER_FRAME {
+        for gr in 0..get_num_granules!(header) {
             for ch in 0..num_channels {
                 let output_ref = <&mut [f32; MP3_NUM_SAMPLES_PER_GRANULE]>::try_from(
                     &mut output[ch]
@@ -895,22 +1885,45 @@ impl MP3Decoder {
                 let part2_start = self.maindata_buffer.get_total_read_bits();
 
                 // スケールファクタのデコード
-                scale_factor.ch[ch].gr[gr] = decode_granule_scale_factor(
-                    &mut self.maindata_buffer,
-                    &side_info.ch[ch].gr[gr],
-                    gr == (MP3_NUM_GRANLES_PER_FRAME - 1),
-                    &side_info.ch[ch].scfsi,
-                    &scale_factor.ch[ch].gr[0],
-                );
+                scale_factor.ch[ch].gr[gr] = match header.version {
+                    MPEGVersion::MPEGVersion1 => decode_granule_scale_factor(
+                        &mut self.maindata_buffer,
+                        &side_info.ch[ch].gr[gr],
+                        gr == (MP3_NUM_GRANLES_PER_FRAME - 1),
+                        &side_info.ch[ch].scfsi,
+                        &scale_factor.ch[ch].gr[0],
+                    ),
+                    _ => decode_granule_scale_factor_lsf(
+                        &mut self.maindata_buffer,
+                        header,
+                        &side_info.ch[ch].gr[gr],
+                        ch,
+                    ),
+                };
 
-                // ハフマン符号による量子化データデコード
-                decode_huffman(
+                // ハフマン符号による量子化データデコード（不正なデータは呼び出し元で隠蔽）
+                if decode_huffman(
                     &mut self.maindata_buffer,
                     header,
                     &side_info.ch[ch].gr[gr],
                     part2_start,
                     output_ref,
-                );
+                )
+                .is_err()
+                {
+                    return false;
+                }
+
+                // インテンシティステレオ位置の計算（右チャンネルの量子化値を使用）
+                if intensity_stereo && ch == 1 {
+                    get_intensity_stereo_position(
+                        header,
+                        &side_info.ch[ch].gr[gr],
+                        &scale_factor.ch[ch].gr[gr],
+                        output_ref,
+                        &mut is_pos[gr],
+                    );
+                }
 
                 // 逆量子化
                 dequantize(
@@ -923,17 +1936,212 @@ impl MP3Decoder {
         }
 
         // ハイブリッドフィルタバンク合成
-        mp3_hybrid_synthesis(&header, &side_info, &mut self.synth_buffer, output);
+        mp3_hybrid_synthesis(&header, &side_info, &is_pos, &mut self.synth_buffer, output);
+
+        true
+    }
+
+    /// 直前のフレームから1フレーム分の隠蔽信号を合成
+    fn synthesize_concealment(
+        &mut self,
+        header: &MP3FrameHeader,
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) {
+        let num_channels = match header.channel_mode {
+            MP3ChannelMode::Monoral => 1,
+            _ => 2,
+        };
+        let gain = match self.concealment_mode {
+            MP3ConcealmentMode::Silence => 0.0,
+            MP3ConcealmentMode::Repeat => 1.0,
+            MP3ConcealmentMode::FadeOut => MP3_CONCEALMENT_FADE_OUT_GAIN,
+        };
+        let num_samples = get_num_samples_per_frame(header);
+
+        for (synth_buffer, output) in self
+            .synth_buffer
+            .iter_mut()
+            .zip(buffer.iter_mut())
+            .take(num_channels)
+        {
+            match header.layer {
+                MP3Layer::Layer3 => {
+                    for granule in
+                        output[..num_samples].chunks_exact_mut(MP3_NUM_SAMPLES_PER_GRANULE)
+                    {
+                        let output_ref =
+                            <&mut [f32; MP3_NUM_SAMPLES_PER_GRANULE]>::try_from(granule).unwrap();
+                        mp3_conceal_granule(synth_buffer, gain, output_ref);
+                    }
+                }
+                _ => {
+                    mp3_conceal_subband(
+                        synth_buffer,
+                        gain,
+                        num_samples / MP3_MAX_NUM_SUBBAND,
+                        &mut output[..num_samples],
+                    );
+                }
+            }
+        }
+        self.concealed = true;
+    }
+
+    /// 欠落したフレームを隠蔽した信号を出力
+    /// headerには欠落したフレームのヘッダ（直前のフレームのものでよい）を与える
+    /// 欠落以前のメインデータを参照する後続のフレームも隠蔽される
+    pub fn conceal_frame(
+        &mut self,
+        header: &MP3FrameHeader,
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) -> Result<(), MP3DecodeError> {
+        let num_channels = match header.channel_mode {
+            MP3ChannelMode::Monoral => 1,
+            _ => 2,
+        };
+        if buffer.len() < num_channels {
+            return Err(MP3DecodeError::InsufficientBuffer);
+        }
+
+        self.maindata_available = 0;
+        self.synthesize_concealment(header, buffer);
+        self.post_process(header, buffer);
+
+        Ok(())
+    }
+
+    /// 合成後の処理（デエンファシス・出力チャンネル選択・ReplayGain）
+    fn post_process(
+        &mut self,
+        header: &MP3FrameHeader,
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) {
+        // デエンファシス
+        if self.deemphasis {
+            let num_channels = match header.channel_mode {
+                MP3ChannelMode::Monoral => 1,
+                _ => 2,
+            };
+            mp3_deemphasis(
+                header,
+                &mut self.deemphasis_filter[..num_channels],
+                buffer,
+                get_num_samples_per_frame(header),
+            );
+        }
+
+        // デュアルチャンネルの出力チャンネル選択
+        if matches!(header.channel_mode, MP3ChannelMode::DualChannel) {
+            let num_samples = get_num_samples_per_frame(header);
+            let (channel_a, channel_b) = buffer.split_at_mut(1);
+            let (channel_a, channel_b) = (&mut channel_a[0], &mut channel_b[0]);
+            match self.dual_channel_output {
+                MP3DualChannelOutput::Both => {}
+                MP3DualChannelOutput::ChannelA => {
+                    channel_b[..num_samples].fill(0.0);
+                }
+                MP3DualChannelOutput::ChannelB => {
+                    channel_a[..num_samples].copy_from_slice(&channel_b[..num_samples]);
+                    channel_b[..num_samples].fill(0.0);
+                }
+                MP3DualChannelOutput::MirrorA => {
+                    channel_b[..num_samples].copy_from_slice(&channel_a[..num_samples]);
+                }
+            }
+        }
+
+        // 合成後にReplayGainを適用
+        let scale = get_replay_gain_scale(
+            &self.replay_gain,
+            self.replay_gain_mode,
+            self.clipping_prevention,
+        );
+        if scale != 1.0 {
+            let num_channels = match header.channel_mode {
+                MP3ChannelMode::Monoral => 1,
+                _ => 2,
+            };
+            mp3_apply_gain(
+                buffer,
+                num_channels,
+                get_num_samples_per_frame(header),
+                scale,
+            );
+        }
+    }
+
+    /// 同期の探索で読み飛ばした範囲に追加
+    fn extend_skipped_range(&mut self, skipped: Range<usize>) {
+        let start = self
+            .skipped_range
+            .as_ref()
+            .map_or(skipped.start, |range| range.start);
+        self.skipped_range = Some(start..skipped.end);
     }
 
     /// 1フレームデコード
+    /// (読み込んだデータサイズ, フレームヘッダ, サイドインフォメーション, 破損したフレームを隠蔽したか)を返す
     pub fn decode_frame(
         &mut self,
         data: &[u8],
         buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
-    ) -> Result<(usize, MP3FrameHeader, MP3SideInformation), MP3DecodeError> {
-        // フレーム情報をデコード
-        let (header_size, maindata_size, header, side_info) = decode_frame_information(data)?;
+    ) -> Result<(usize, MP3FrameHeader, MP3SideInformation, bool), MP3DecodeError> {
+        // 末尾のタグを除く
+        let audio_end = max(get_audio_end(data), get_leading_tags_size(data));
+        self.decode_frame_at(&data[..audio_end], buffer)
+    }
+
+    /// 末尾のタグを除いたデータ（区切ったフレーム・データの途中以降）から1フレームデコード
+    /// 末尾のタグの検出は行わない 返り値はdecode_frameと同じ
+    pub(crate) fn decode_frame_at(
+        &mut self,
+        data: &[u8],
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) -> Result<(usize, MP3FrameHeader, MP3SideInformation, bool), MP3DecodeError> {
+        // フレーム間に挟まれたID3v2タグを読み飛ばす
+        let mut tag_frame_size = get_leading_tags_size(data);
+        self.skipped_range = None;
+
+        let (header_size, maindata_size, header, side_info) = loop {
+            // 同期が確定するフレームまでの不正なデータを読み飛ばす
+            let Some(frame_start) = find_frame_start(
+                &data[tag_frame_size..],
+                data.len() - tag_frame_size,
+                self.num_sync_frames,
+                self.free_format_frame_size,
+            ) else {
+                return Err(MP3DecodeError::EndOfStream);
+            };
+            if frame_start > 0 {
+                self.extend_skipped_range(tag_frame_size..tag_frame_size + frame_start);
+            }
+            tag_frame_size += frame_start;
+
+            // フレーム情報をデコード
+            match decode_frame_information(
+                &data[tag_frame_size..],
+                &mut self.free_format_frame_size,
+            ) {
+                // Xing/Info, VBRIタグのフレームは音声としてデコードせずに読み飛ばす
+                Ok((header_size, maindata_size, header, _))
+                    if is_vbr_tag_frame(
+                        &data[tag_frame_size..],
+                        &header,
+                        header_size,
+                        maindata_size,
+                    ) =>
+                {
+                    tag_frame_size += header_size + maindata_size;
+                }
+                Ok(information) => break information,
+                Err(_) => {
+                    // 同期コードの誤検出とみなし、不正なデータとして読み飛ばして次の同期コードを探す
+                    self.extend_skipped_range(tag_frame_size..tag_frame_size + 1);
+                    tag_frame_size += 1;
+                }
+            }
+        };
+        let data = &data[tag_frame_size..];
 
         // バッファチャンネル数チェック
         match header.channel_mode {
@@ -948,14 +2156,302 @@ impl MP3Decoder {
             _ => {}
         }
 
-        // メインデータをバッファに入力
-        self.maindata_buffer
-            .put_data(&data[header_size..header_size + maindata_size]);
+        // CRCの検査
+        let mut crc_mismatch = false;
+        if header.error_protection && self.crc_policy != MP3CrcPolicy::Ignore {
+            let frame_start =
+                header_size - get_sideinformation_size!(header) - 2 - MP3_FRAMEHEADER_SIZE;
+            if !check_crc(&header, &data[frame_start..header_size + maindata_size]) {
+                if self.crc_policy == MP3CrcPolicy::Reject {
+                    return Err(MP3DecodeError::CrcMismatch);
+                }
+                crc_mismatch = true;
+            }
+        }
+
+        // 同期の探索で不正なデータを読み飛ばした場合、それ以前のメインデータは参照できない
+        if self.skipped_range.is_some() {
+            self.maindata_available = 0;
+        }
+
+        let maindata = &data[header_size..header_size + maindata_size];
+        let decoded = match header.layer {
+            MP3Layer::Layer1 => {
+                !crc_mismatch
+                    && mp3_layer1_decode(&header, maindata, &mut self.synth_buffer, buffer).is_ok()
+            }
+            MP3Layer::Layer2 => {
+                // Layer2はビットリザーバを使わないため、フレーム内のデータのみでデコード
+                !crc_mismatch
+                    && mp3_layer2_decode(&header, maindata, &mut self.synth_buffer, buffer).is_ok()
+            }
+            MP3Layer::Layer3 => {
+                // 参照するメインデータがバッファに揃っているか
+                let sufficient = side_info.maindata_begin as usize <= self.maindata_available;
+
+                // メインデータをバッファに入力（破損フレームも後続フレームが参照できるように入力）
+                self.maindata_buffer.put_data(maindata);
+                self.maindata_available = min(
+                    self.maindata_available + maindata_size,
+                    MP3_MAINDATA_BUFFER_SIZE,
+                );
+
+                if !crc_mismatch && sufficient {
+                    // メインデータのデコード
+                    self.decode_maindata(&header, &side_info, maindata_size, buffer)
+                } else {
+                    // メインデータ開始位置のみ更新
+                    self.maindata_start += maindata_size;
+                    if self.maindata_start > MP3_MAINDATA_BUFFER_SIZE {
+                        self.maindata_start -= MP3_MAINDATA_BUFFER_SIZE;
+                    }
+                    false
+                }
+            }
+        };
+
+        // デコードできなかったフレームは隠蔽
+        self.concealed = false;
+        if !decoded {
+            self.synthesize_concealment(&header, buffer);
+        }
+
+        self.post_process(&header, buffer);
+
+        Ok((
+            tag_frame_size + header_size + maindata_size,
+            header,
+            side_info,
+            self.concealed,
+        ))
+    }
+
+    /// シークの準備としてハンドルをリセットし、サンプル位置に加えるオフセットを取得
+    fn begin_seek(&mut self, data: &[u8]) -> usize {
+        // ハンドルをリセット
+        self.reset();
+
+        // ReplayGain情報を取得
+        if self.replay_gain_mode != MP3ReplayGainMode::Disabled {
+            self.replay_gain = get_replay_gain(data);
+        }
+
+        // ギャップレス再生ではエンコーダ遅延・デコーダ遅延の分だけ後ろにずらす
+        let lame = if self.gapless {
+            let data = &data[..get_audio_end(data)];
+            decode_xing_frame(&data[get_leading_tags_size(data)..], &mut 0)
+                .and_then(|(_, _, xing)| xing.lame)
+        } else {
+            None
+        };
+        match lame {
+            Some(lame) => MP3_DECODER_DELAY + lame.encoder_delay as usize,
+            None => 0,
+        }
+    }
+
+    /// start_offsetのフレーム（先頭のサンプル位置start_sample）から走査してサンプル位置へシーク
+    fn seek_from(
+        &mut self,
+        data: &[u8],
+        start_offset: usize,
+        start_sample: usize,
+        sample: usize,
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) -> Result<(usize, usize), MP3DecodeError> {
+        // 対象のフレームまで走査し、直前のフレームの(位置, メインデータサイズ, maindata_begin)を記録
+        let mut frames = [(0, 0, 0); MP3_MAX_NUM_SEEK_PREROLL_FRAMES];
+        let mut num_frames = 0;
+        let mut frame_start_sample = start_sample;
+        let mut num_skip_samples = None;
+        let mut iter = MP3FrameIterator::new(&data[start_offset..]);
+        iter.set_num_sync_frames(self.num_sync_frames);
+        for frame in iter {
+            frames[num_frames % MP3_MAX_NUM_SEEK_PREROLL_FRAMES] = (
+                start_offset + frame.offset,
+                frame.maindata.len(),
+                frame.side_info.maindata_begin as usize,
+            );
+            num_frames += 1;
+            let num_frame_samples = get_num_samples_per_frame(&frame.header);
+            if sample < frame_start_sample + num_frame_samples {
+                num_skip_samples = Some(sample.saturating_sub(frame_start_sample));
+                break;
+            }
+            frame_start_sample += num_frame_samples;
+        }
+
+        // 終端以降であれば次のデコードで終端に達する
+        let Some(num_skip_samples) = num_skip_samples else {
+            return Ok((data.len(), 0));
+        };
+
+        // 対象と直前のフレームが参照するメインデータを全てバッファに入力できるところまで遡る
+        let target = num_frames - 1;
+        let oldest = num_frames.saturating_sub(MP3_MAX_NUM_SEEK_PREROLL_FRAMES);
+        let warmup_start = max(oldest, target.saturating_sub(MP3_NUM_SEEK_WARMUP_FRAMES));
+        let frame_at = |index: usize| frames[index % MP3_MAX_NUM_SEEK_PREROLL_FRAMES];
+        let mut preroll_start = warmup_start;
+        while preroll_start > oldest
+            && (warmup_start..=target).any(|index| {
+                let buffered: usize = (preroll_start..index).map(|i| frame_at(i).1).sum();
+                buffered < frame_at(index).2
+            })
+        {
+            preroll_start -= 1;
+        }
+
+        // 先行するフレームをデコードして状態を復元（出力は破棄）
+        for index in preroll_start..target {
+            let _ = self.decode_frame_at(&data[frame_at(index).0..], buffer);
+        }
+
+        // 対象のフレームをデコードし、サンプル位置より前の出力を取り除く
+        let offset = frame_at(target).0;
+        let (size, header, _, _) = self.decode_frame_at(&data[offset..], buffer)?;
+        let num_samples = get_num_samples_per_frame(&header).saturating_sub(num_skip_samples);
+        for samples in buffer.iter_mut() {
+            samples.copy_within(num_skip_samples..num_skip_samples + num_samples, 0);
+        }
+
+        Ok((offset + size, num_samples))
+    }
+
+    /// 同期を探索しながらの走査をせずに、サンプル位置へのシークで走査を始めるフレームの(位置, 先頭のサンプル位置)を求める
+    /// CBRではビットレートからフレーム位置を計算し、VBR（Xing/VBRIタグあり）では先頭の音声フレームから
+    /// フレームヘッダをたどって数える（TOCの位置はフレーム番号が定まらないため使わない）
+    fn find_seek_start(&self, data: &[u8], sample: usize) -> Option<(usize, usize)> {
+        // 先頭のVBRタグを確認
+        let tag_start = get_leading_tags_size(data);
+        let mut free_format_frame_size = 0;
+        let (first, vbr) = if let Some((tag_frame_size, _, xing)) =
+            decode_xing_frame(data.get(tag_start..)?, &mut free_format_frame_size)
+        {
+            (tag_start + tag_frame_size, xing.vbr)
+        } else if let Some((tag_frame_size, _, _)) =
+            decode_vbri_frame(data.get(tag_start..)?, &mut free_format_frame_size)
+        {
+            (tag_start + tag_frame_size, true)
+        } else {
+            (tag_start, false)
+        };
+        let num_sync_frames = max(self.num_sync_frames, 2);
+
+        // 先頭の音声フレーム
+        let first = first
+            + find_frame_start(
+                data.get(first..)?,
+                data.len() - first,
+                self.num_sync_frames,
+                0,
+            )?;
+        let header = decode_frame_header(&data[first..])?;
+        let num_samples_per_frame = get_num_samples_per_frame(&header);
+        let frame_index =
+            (sample / num_samples_per_frame).saturating_sub(MP3_MAX_NUM_SEEK_PREROLL_FRAMES);
+        let start_sample = frame_index * num_samples_per_frame;
+
+        // VBR: 先頭の音声フレームからフレームヘッダをたどって数える
+        if vbr {
+            let offset = first + skip_frames(&data[first..], frame_index)?;
+            return Some((offset, start_sample));
+        }
+
+        // CBR: 先頭フレームのビットレートから位置を計算（フリーフォーマットは計算できない）
+        if header.bit_rate == MP3BitRate::Kbps0 {
+            return None;
+        }
+        let estimate = first
+            + (start_sample as u64 * header.bit_rate as u64 / (8 * header.sampling_rate as u64))
+                as usize;
+        if estimate >= data.len() {
+            return Some((data.len(), start_sample));
+        }
+
+        // パディングの分だけ前後しうるため、周辺で一貫したヘッダが続く位置を探す
+        let max_padding = match header.layer {
+            MP3Layer::Layer1 => 4,
+            _ => 1,
+        };
+        (estimate.saturating_sub(max_padding)..=estimate + max_padding)
+            .find(|&pos| {
+                data.get(pos..).is_some_and(|data| {
+                    decode_frame_header(data).is_some_and(|candidate| {
+                        candidate.version == header.version
+                            && candidate.layer == header.layer
+                            && candidate.sampling_rate == header.sampling_rate
+                            && candidate.bit_rate == header.bit_rate
+                    }) && validate_frame_sync(data, data.len(), num_sync_frames, 0)
+                })
+            })
+            .map(|pos| (pos, start_sample))
+    }
+
+    /// TOCまたはビットレートから求めた位置から走査してサンプル位置へシーク
+    /// 位置を求められなければ先頭から走査する
+    fn seek_without_index(
+        &mut self,
+        data: &[u8],
+        sample: usize,
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) -> Result<(usize, usize), MP3DecodeError> {
+        let (start_offset, start_sample) = self.find_seek_start(data, sample).unwrap_or((0, 0));
+        self.seek_from(data, start_offset, start_sample, sample, buffer)
+    }
+
+    /// サンプル位置へのシーク
+    /// 対象のフレームの近くの位置をビットレート（CBR）またはフレームヘッダをたどった数（VBR）から求め、
+    /// ビットリザーバと合成フィルタバンクの状態を復元するために先行するフレームをデコードする
+    /// 対象のフレームのサンプル位置以降の出力をbufferの先頭から書き込み、
+    /// 次にdecode_frameに与えるデータ位置(byte)と書き込んだサンプル数を返す
+    /// ギャップレス再生時のサンプル位置はdecode_wholeの出力と同じくエンコーダ遅延・デコーダ遅延を除く
+    pub fn seek_to_sample(
+        &mut self,
+        data: &[u8],
+        sample: usize,
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) -> Result<(usize, usize), MP3DecodeError> {
+        let sample = sample + self.begin_seek(data);
+        let data = &data[..get_audio_end(data)];
+        self.seek_without_index(data, sample, buffer)
+    }
 
-        // メインデータのデコード
-        self.decode_maindata(&header, &side_info, buffer);
+    /// シークインデックスを使用したサンプル位置へのシーク
+    /// インデックスのエントリから走査するため、VBRでも先頭からフレームヘッダをたどる必要がない 返り値はseek_to_sampleと同じ
+    pub fn seek_to_sample_with_index(
+        &mut self,
+        data: &[u8],
+        index: &MP3SeekIndex,
+        sample: usize,
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) -> Result<(usize, usize), MP3DecodeError> {
+        let sample = sample + self.begin_seek(data);
+        let data = &data[..get_audio_end(data)];
+
+        // 遡ってデコードするフレームを走査できるよう、対象より前のエントリから走査
+        let num_back_entries = MP3_MAX_NUM_SEEK_PREROLL_FRAMES.div_ceil(index.interval());
+        let Some(entry) = index
+            .find_entry(sample)
+            .and_then(|entry| index.entry(entry.saturating_sub(num_back_entries)))
+        else {
+            return self.seek_without_index(data, sample, buffer);
+        };
 
-        Ok((header_size + maindata_size, header, side_info))
+        // エントリの位置にフレームがあるか検証
+        let offset = entry.offset as usize;
+        let mut free_format_frame_size = 0;
+        match data
+            .get(offset..)
+            .map(|data| decode_frame_information(data, &mut free_format_frame_size))
+        {
+            Some(Ok((_, _, _, side_info)))
+                if find_sync_code(&data[offset..]) == Some(0)
+                    && side_info.maindata_begin == entry.maindata_begin =>
+            {
+                self.seek_from(data, offset, entry.sample as usize, sample, buffer)
+            }
+            _ => Err(MP3DecodeError::InvalidFormat),
+        }
     }
 
     /// 全データフレームデコード
@@ -967,6 +2463,9 @@ impl MP3Decoder {
         // ハンドルをリセット
         self.reset();
 
+        if output.is_empty() {
+            return Err(MP3DecodeError::InsufficientBuffer);
+        }
         let num_channels = if output.len() == 2 {
             if output[1].len() > 0 {
                 2
@@ -980,26 +2479,51 @@ impl MP3Decoder {
         // 出力バッファ確保
         let mut buffer = [[0.0f32; MP3_NUM_SAMPLES_PER_FRAME]; MP3_MAX_NUM_CHANNELS];
         let mut num_samples = 0;
-        // ID3v2タグをスキップ
-        let mut read_pos = match get_id3v2tag_size(data) {
-            Ok(size) => size,
-            Err(err) => match err {
-                MP3DecodeError::InvalidFormat => 0,
-                _ => {
-                    return Err(err);
-                }
-            },
+        let mut num_decoded_samples = 0;
+        // ReplayGain情報を取得
+        if self.replay_gain_mode != MP3ReplayGainMode::Disabled {
+            self.replay_gain = get_replay_gain(data);
+        }
+
+        // 末尾のタグを除く
+        let data = &data[..get_audio_end(data)];
+        // 先頭のID3v2タグをスキップ
+        let mut read_pos = get_leading_tags_size(data);
+
+        // ギャップレス再生ではLAMEタグのエンコーダ遅延・パディングを取得
+        let lame = if self.gapless {
+            decode_xing_frame(&data[read_pos..], &mut 0).and_then(|(_, _, xing)| xing.lame)
+        } else {
+            None
+        };
+        // 先頭で読み捨てるサンプル数（デコーダ遅延+エンコーダ遅延）
+        let mut num_skip_samples = match lame {
+            Some(lame) => MP3_DECODER_DELAY + lame.encoder_delay as usize,
+            None => 0,
         };
+
         loop {
             // 1フレームデコードを繰り返す
-            match self.decode_frame(&data[read_pos..], &mut buffer) {
-                Ok((size, _, _)) => {
+            match self.decode_frame_at(&data[read_pos..], &mut buffer) {
+                Ok((size, header, _, _)) => {
+                    let num_frame_samples = get_num_samples_per_frame(&header);
+                    let num_skip = min(num_skip_samples, num_frame_samples);
+                    let num_output_samples = num_frame_samples - num_skip;
+                    // 出力に収まらない
+                    if output[..num_channels]
+                        .iter()
+                        .any(|ch_output| ch_output.len() < num_samples + num_output_samples)
+                    {
+                        return Err(MP3DecodeError::InsufficientBuffer);
+                    }
                     for ch in 0..num_channels {
-                        output[ch][num_samples..num_samples + MP3_NUM_SAMPLES_PER_FRAME]
-                            .copy_from_slice(&buffer[ch])
+                        output[ch][num_samples..num_samples + num_output_samples]
+                            .copy_from_slice(&buffer[ch][num_skip..num_frame_samples])
                     }
                     read_pos += size;
-                    num_samples += MP3_NUM_SAMPLES_PER_FRAME;
+                    num_skip_samples -= num_skip;
+                    num_samples += num_output_samples;
+                    num_decoded_samples += num_frame_samples;
                 }
                 Err(e) => match e {
                     MP3DecodeError::EndOfStream => break,
@@ -1008,6 +2532,15 @@ impl MP3Decoder {
             }
         }
 
+        // 元のPCMのサンプル数(デコードしたサンプル数-エンコーダ遅延-パディング)に切り詰め
+        if let Some(lame) = lame {
+            num_samples = min(
+                num_samples,
+                num_decoded_samples
+                    .saturating_sub(lame.encoder_delay as usize + lame.padding as usize),
+            );
+        }
+
         Ok((read_pos, num_samples))
     }
 }
@@ -0,0 +1,79 @@
+use crate::types::*;
+
+/// 50/15usエンファシスの時定数(s) (極, 零点)
+const FIFTY_FIFTEEN_US_TIME_CONSTANTS: (f32, f32) = (50.0e-6, 15.0e-6);
+/// CCITT J.17エンファシスの極の角周波数(rad/s)
+const CCITT_J17_POLE_ANGULAR_FREQUENCY: f32 = 3000.0;
+/// CCITT J.17エンファシスの低域と高域のパワー比
+const CCITT_J17_POWER_RATIO: f32 = 75.0;
+
+/// デエンファシスフィルタ（1次のシェルビングフィルタ）
+pub struct MP3DeemphasisFilter {
+    /// 1サンプル前の入力
+    prev_input: f32,
+    /// 1サンプル前の出力
+    prev_output: f32,
+}
+
+impl MP3DeemphasisFilter {
+    /// フィルタ生成
+    pub fn new() -> Self {
+        Self {
+            prev_input: 0.0,
+            prev_output: 0.0,
+        }
+    }
+
+    /// フィルタ状態リセット
+    pub fn reset(&mut self) {
+        self.prev_input = 0.0;
+        self.prev_output = 0.0;
+    }
+}
+
+/// フィルタ係数(b0, b1, a1)の計算
+fn get_filter_coefficients(header: &MP3FrameHeader) -> Option<(f32, f32, f32)> {
+    // アナログフィルタ H(s) = (1 + s * tz) / (1 + s * tp) の時定数
+    let (tp, tz) = match header.emphasis {
+        MP3EmphasisMode::FiftyFifteenMs => FIFTY_FIFTEEN_US_TIME_CONSTANTS,
+        MP3EmphasisMode::CCITTJ17 => (
+            1.0 / CCITT_J17_POLE_ANGULAR_FREQUENCY,
+            1.0 / (CCITT_J17_POLE_ANGULAR_FREQUENCY * CCITT_J17_POWER_RATIO.sqrt()),
+        ),
+        _ => return None,
+    };
+
+    // 双一次変換 s = k * (1 - z^-1) / (1 + z^-1), k = 2 * サンプリングレート
+    let k = 2.0 * header.sampling_rate as u32 as f32;
+    let norm = 1.0 + k * tp;
+    Some((
+        (1.0 + k * tz) / norm,
+        (1.0 - k * tz) / norm,
+        (1.0 - k * tp) / norm,
+    ))
+}
+
+/// デエンファシス処理
+pub fn mp3_deemphasis(
+    header: &MP3FrameHeader,
+    filter: &mut [MP3DeemphasisFilter],
+    buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    num_samples: usize,
+) {
+    // エンファシスがなければ状態をリセットして終了
+    let Some((b0, b1, a1)) = get_filter_coefficients(header) else {
+        for f in filter.iter_mut() {
+            f.reset();
+        }
+        return;
+    };
+
+    for (f, ch_buffer) in filter.iter_mut().zip(buffer.iter_mut()) {
+        for sample in ch_buffer[..num_samples].iter_mut() {
+            let output = b0 * *sample + b1 * f.prev_input - a1 * f.prev_output;
+            f.prev_input = *sample;
+            f.prev_output = output;
+            *sample = output;
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! デコードせずにフレームを列挙するイテレータ
+use crate::decoder::*;
+use crate::tag::get_leading_tags_size;
+use crate::types::*;
+
+use core::cmp::max;
+use core::ops::Range;
+
+/// フレーム情報
+pub struct MP3FrameInformation {
+    /// データ先頭からのフレーム先頭位置(byte)
+    pub offset: usize,
+    /// フレームサイズ(byte, ヘッダ含む)
+    pub size: usize,
+    /// フレームヘッダ
+    pub header: MP3FrameHeader,
+    /// サイドインフォメーション（Layer3以外は既定値）
+    pub side_info: MP3SideInformation,
+    /// フレーム内のメインデータ領域（データ先頭からの位置）
+    /// Layer3ではside_info.maindata_beginだけ前のフレームのメインデータから始まりうる
+    pub maindata: Range<usize>,
+}
+
+/// データ中のフレームを先頭から順に列挙するイテレータ
+/// タグ(ID3v2, Xing/Info, VBRI, APE, Lyrics3v2, ID3v1)とフレーム間の不正なデータは読み飛ばす
+pub struct MP3FrameIterator<'a> {
+    /// 末尾のタグを除いたデータ
+    data: &'a [u8],
+    /// 次に読み出す位置
+    pos: usize,
+    /// フリーフォーマットのフレームサイズ(byte, パディング除く) 未計測時は0
+    free_format_frame_size: usize,
+    /// 同期の確定に必要な連続フレーム数
+    num_sync_frames: usize,
+}
+
+impl<'a> MP3FrameIterator<'a> {
+    /// イテレータ生成
+    pub fn new(data: &'a [u8]) -> Self {
+        Self {
+            data: &data[..get_audio_end(data)],
+            pos: 0,
+            free_format_frame_size: 0,
+            num_sync_frames: 2,
+        }
+    }
+
+    /// 不正なデータの後で同期を確定するのに必要な連続フレーム数を設定（デフォルトは2、最小1）
+    pub fn set_num_sync_frames(&mut self, num_frames: usize) {
+        self.num_sync_frames = max(num_frames, 1);
+    }
+}
+
+impl Iterator for MP3FrameIterator<'_> {
+    type Item = MP3FrameInformation;
+
+    fn next(&mut self) -> Option<MP3FrameInformation> {
+        loop {
+            // フレーム間に挟まれたID3v2タグを読み飛ばす
+            self.pos += get_leading_tags_size(&self.data[self.pos..]);
+
+            // 同期が確定するフレームまでの不正なデータを読み飛ばす
+            let offset = self.pos
+                + find_frame_start(
+                    &self.data[self.pos..],
+                    self.data.len() - self.pos,
+                    self.num_sync_frames,
+                    self.free_format_frame_size,
+                )?;
+            let data = &self.data[offset..];
+            let Ok((header_size, maindata_size, header, side_info)) =
+                decode_frame_information(data, &mut self.free_format_frame_size)
+            else {
+                // 同期コードの誤検出とみなして次の同期コードを探す
+                self.pos = offset + 1;
+                continue;
+            };
+            let size = header_size + maindata_size;
+            self.pos = offset + size;
+
+            // VBRタグのフレームは列挙しない
+            if is_vbr_tag_frame(data, &header, header_size, maindata_size) {
+                continue;
+            }
+
+            return Some(MP3FrameInformation {
+                offset,
+                size,
+                header,
+                side_info,
+                maindata: offset + header_size..offset + size,
+            });
+        }
+    }
+}
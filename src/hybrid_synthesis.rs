@@ -5,10 +5,13 @@ pub const MP3_DCT_SIZE: usize = 18;
 /// DCTのサイズ（ショート）
 pub const MP3_DCT_SIZE_SHORT: usize = MP3_DCT_SIZE / 3;
 /// 最大サブバンド数
-const MP3_MAX_NUM_SUBBAND: usize = 32;
+pub const MP3_MAX_NUM_SUBBAND: usize = 32;
 /// サブバンド合成の逆窓関数テーブルサイズ
 const MP3_DEWINDOW_SIZE: usize = 512;
 
+/// インテンシティステレオを適用しない位置を示す値
+pub const MP3_INVALID_IS_POSITION: u8 = u8::MAX;
+
 /// スケールファクタバンドインデックス
 pub struct ScaleFactorBandIndex {
     pub long: [u16; MP3_NUM_CRITICAL_BANDS_LONG],
@@ -20,10 +23,16 @@ pub struct MP3SynthesisBuffer {
     synth_buffer: [f32; 2 * MP3_DEWINDOW_SIZE],
     synth_buffer_offset: usize,
     imdct_buffer: [f32; MP3_MAX_NUM_SUBBAND * MP3_DCT_SIZE],
+    /// 直前のグラニュールのスペクトル（IMDCT入力, 隠蔽処理で使用）
+    spectrum: [f32; MP3_NUM_SAMPLES_PER_GRANULE],
+    /// 直前のサブバンド合成入力（隠蔽処理で使用）
+    subband_history: [[f32; MP3_MAX_NUM_SUBBAND]; MP3_DCT_SIZE],
+    /// サブバンド合成入力の次の書き込み位置
+    subband_history_pos: usize,
 }
 
 /// スケールファクタバンドインデックステーブル
-pub const SCALEFACTORBAND_INDEX_TABLE: [ScaleFactorBandIndex; 3] = [
+pub const SCALEFACTORBAND_INDEX_TABLE: [ScaleFactorBandIndex; 7] = [
     // 44.1kHz
     ScaleFactorBandIndex {
         long: [
@@ -48,6 +57,38 @@ pub const SCALEFACTORBAND_INDEX_TABLE: [ScaleFactorBandIndex; 3] = [
         ],
         short: [0, 4, 8, 12, 16, 22, 30, 42, 58, 78, 104, 138, 180, 192],
     },
+    // 22.05kHz
+    ScaleFactorBandIndex {
+        long: [
+            0, 6, 12, 18, 24, 30, 36, 44, 54, 66, 80, 96, 116, 140, 168, 200, 238, 284, 336, 396,
+            464, 522, 576,
+        ],
+        short: [0, 4, 8, 12, 18, 24, 32, 42, 56, 74, 100, 132, 174, 192],
+    },
+    // 24kHz
+    ScaleFactorBandIndex {
+        long: [
+            0, 6, 12, 18, 24, 30, 36, 44, 54, 66, 80, 96, 114, 136, 162, 194, 232, 278, 332, 394,
+            464, 540, 576,
+        ],
+        short: [0, 4, 8, 12, 18, 26, 36, 48, 62, 80, 104, 136, 180, 192],
+    },
+    // 16kHz
+    ScaleFactorBandIndex {
+        long: [
+            0, 6, 12, 18, 24, 30, 36, 44, 54, 66, 80, 96, 116, 140, 168, 200, 238, 284, 336, 396,
+            464, 522, 576,
+        ],
+        short: [0, 4, 8, 12, 18, 26, 36, 48, 62, 80, 104, 134, 174, 192],
+    },
+    // 8kHz
+    ScaleFactorBandIndex {
+        long: [
+            0, 12, 24, 36, 48, 60, 72, 88, 108, 132, 160, 192, 232, 280, 336, 400, 476, 566, 568,
+            570, 572, 574, 576,
+        ],
+        short: [0, 8, 16, 24, 36, 52, 72, 96, 124, 160, 162, 164, 166, 192],
+    },
 ];
 
 /// ロングブロックの窓関数テーブル
@@ -3642,11 +3683,29 @@ macro_rules! get_scalefactorband_index_table {
             MP3SamplingRate::Hz44100 => &SCALEFACTORBAND_INDEX_TABLE[0],
             MP3SamplingRate::Hz48000 => &SCALEFACTORBAND_INDEX_TABLE[1],
             MP3SamplingRate::Hz32000 => &SCALEFACTORBAND_INDEX_TABLE[2],
+            MP3SamplingRate::Hz22050 => &SCALEFACTORBAND_INDEX_TABLE[3],
+            MP3SamplingRate::Hz24000 => &SCALEFACTORBAND_INDEX_TABLE[4],
+            // 11.025kHz, 12kHzは16kHzと同一
+            MP3SamplingRate::Hz16000 | MP3SamplingRate::Hz11025 | MP3SamplingRate::Hz12000 => {
+                &SCALEFACTORBAND_INDEX_TABLE[5]
+            }
+            MP3SamplingRate::Hz8000 => &SCALEFACTORBAND_INDEX_TABLE[6],
         }
     }};
 }
 pub(crate) use get_scalefactorband_index_table;
 
+/// フレームヘッダからグラニュール数を取得
+macro_rules! get_num_granules {
+    ($header:expr) => {{
+        match $header.version {
+            MPEGVersion::MPEGVersion1 => MP3_NUM_GRANLES_PER_FRAME,
+            _ => MP3_NUM_GRANLES_PER_FRAME_LSF,
+        }
+    }};
+}
+pub(crate) use get_num_granules;
+
 impl MP3SynthesisBuffer {
     /// バッファ生成
     pub fn new() -> Self {
@@ -3654,6 +3713,9 @@ impl MP3SynthesisBuffer {
             synth_buffer: [0.0f32; 2 * MP3_DEWINDOW_SIZE],
             synth_buffer_offset: 64,
             imdct_buffer: [0.0f32; MP3_MAX_NUM_SUBBAND * MP3_DCT_SIZE],
+            spectrum: [0.0f32; MP3_NUM_SAMPLES_PER_GRANULE],
+            subband_history: [[0.0f32; MP3_MAX_NUM_SUBBAND]; MP3_DCT_SIZE],
+            subband_history_pos: 0,
         }
     }
 
@@ -3662,31 +3724,72 @@ impl MP3SynthesisBuffer {
         self.synth_buffer.fill(0.0f32);
         self.imdct_buffer.fill(0.0f32);
         self.synth_buffer_offset = 64;
+        self.spectrum.fill(0.0f32);
+        for history in &mut self.subband_history {
+            history.fill(0.0f32);
+        }
+        self.subband_history_pos = 0;
     }
 }
 
 /// ステレオ信号処理
-fn stereo_processing(header: &MP3FrameHeader, buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]]) {
-    match &header.channel_mode {
+fn stereo_processing(
+    header: &MP3FrameHeader,
+    side_info: &MP3SideInformation,
+    is_pos: &[[u8; MP3_NUM_SAMPLES_PER_GRANULE]; MP3_NUM_GRANLES_PER_FRAME],
+    buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+) {
+    // 2の平方根の逆
+    const INVERSE_SQRT2: f32 = 1.0 / 1.41421356237309504880;
+    // MPEG1のインテンシティステレオの左右の係数 tan(is_pos * PI / 12) = r とした時の (r / (1 + r), 1 / (1 + r))
+    const IS_RATIO_TABLE: [(f32, f32); 7] = [
+        (0.0, 1.0),
+        (0.21132487, 0.7886751),
+        (0.36602542, 0.6339746),
+        (0.5, 0.5),
+        (0.6339746, 0.36602542),
+        (0.7886751, 0.21132487),
+        (1.0, 0.0),
+    ];
+
+    let (ms_stereo, intensity_stereo) = match &header.channel_mode {
         MP3ChannelMode::JointStereo => match &header.ext_channel_mode {
-            MP3ExtChannelMode::IntensityStereo => {
-                panic!("Intensity stereo is not supported");
-            }
-            MP3ExtChannelMode::MSStereo => {
-                // 2の平方根の逆
-                const INVERSE_SQRT2: f32 = 1.0 / 1.41421356237309504880;
-                let num_samples = buffer[0].len();
-                assert_eq!(num_samples, buffer[1].len());
-                for i in 0..num_samples {
-                    let l = buffer[0][i];
-                    let r = buffer[1][i];
-                    buffer[0][i] = (l + r) * INVERSE_SQRT2;
-                    buffer[1][i] = (l - r) * INVERSE_SQRT2;
-                }
-            }
-            MP3ExtChannelMode::NONE => {}
+            MP3ExtChannelMode::IntensityStereo => (false, true),
+            MP3ExtChannelMode::MSStereo => (true, false),
+            MP3ExtChannelMode::MSIntensityStereo => (true, true),
+            MP3ExtChannelMode::NONE => return,
         },
-        _ => {}
+        _ => return,
+    };
+
+    for (gr, gr_is_pos) in is_pos.iter().enumerate().take(get_num_granules!(header)) {
+        // MPEG2のインテンシティステレオの減衰率（右チャンネルのscalefac_compressの最下位ビットで選択）
+        let lsf_is_ratio: f32 = if (side_info.ch[1].gr[gr].scalefac_compress & 1) == 0 {
+            // 2^(-1/4)
+            0.8408964
+        } else {
+            // 2^(-1/2)
+            core::f32::consts::FRAC_1_SQRT_2
+        };
+        for (i, &pos) in gr_is_pos.iter().enumerate() {
+            let index = gr * MP3_NUM_SAMPLES_PER_GRANULE + i;
+            let l = buffer[0][index];
+            let r = buffer[1][index];
+            if intensity_stereo && pos != MP3_INVALID_IS_POSITION {
+                // インテンシティステレオ: 左チャンネルを比率で振り分け
+                let (kl, kr) = match header.version {
+                    MPEGVersion::MPEGVersion1 => IS_RATIO_TABLE[pos as usize],
+                    _ if pos == 0 => (1.0, 1.0),
+                    _ if (pos & 1) == 1 => (lsf_is_ratio.powi((pos as i32 + 1) / 2), 1.0),
+                    _ => (1.0, lsf_is_ratio.powi(pos as i32 / 2)),
+                };
+                buffer[0][index] = l * kl;
+                buffer[1][index] = l * kr;
+            } else if ms_stereo {
+                buffer[0][index] = (l + r) * INVERSE_SQRT2;
+                buffer[1][index] = (l - r) * INVERSE_SQRT2;
+            }
+        }
     }
 }
 
@@ -3873,6 +3976,42 @@ fn phase_inversion(buffer: &mut [f32; MP3_NUM_SAMPLES_PER_GRANULE]) {
     }
 }
 
+/// 1タイムスロット分のサブバンド合成処理
+pub fn mp3_subband_synthesis(
+    buffer: &mut MP3SynthesisBuffer,
+    input: &[f32; MP3_MAX_NUM_SUBBAND],
+    output: &mut [f32; MP3_MAX_NUM_SUBBAND],
+) {
+    // バッファ参照位置を64サンプル動かす 0 -> 960 -> 896 -> 832 -> ...
+    buffer.synth_buffer_offset =
+        (buffer.synth_buffer_offset.wrapping_sub(64)) & (2 * MP3_DEWINDOW_SIZE - 1);
+
+    // 合成フィルタを畳み込み・バッファに書き込み
+    for i in 0..64 {
+        let mut sum = 0.0f32;
+        for j in 0..MP3_MAX_NUM_SUBBAND {
+            sum += input[j] * SYNTHESIS_FILTER_COEFFICIENTS[i][j];
+        }
+        buffer.synth_buffer[buffer.synth_buffer_offset + i] = sum;
+    }
+
+    // 合成窓適用
+    for (sb, out) in output.iter_mut().enumerate() {
+        let mut sum = 0.0f32;
+        for j in 0..16 {
+            let k = sb + (j << 5);
+            let index = (buffer.synth_buffer_offset + (k + (((j + 1) >> 1) << 6)))
+                & (2 * MP3_DEWINDOW_SIZE - 1);
+            sum += MP3_DEWINDOW_TABLE[k] * buffer.synth_buffer[index];
+        }
+        *out = sum;
+    }
+
+    // 隠蔽処理のために入力を記録
+    buffer.subband_history[buffer.subband_history_pos] = *input;
+    buffer.subband_history_pos = (buffer.subband_history_pos + 1) % MP3_DCT_SIZE;
+}
+
 /// サブバンド合成処理
 fn subband_synthesis(
     buffer: &mut MP3SynthesisBuffer,
@@ -3887,30 +4026,15 @@ fn subband_synthesis(
             filter_in[sb] = input[subband_to_index!(sb, ssb)];
         }
 
-        // バッファ参照位置を64サンプル動かす 0 -> 960 -> 896 -> 832 -> ...
-        buffer.synth_buffer_offset =
-            (buffer.synth_buffer_offset.wrapping_sub(64)) & (2 * MP3_DEWINDOW_SIZE - 1);
-
-        // 合成フィルタを畳み込み・バッファに書き込み
-        for i in 0..64 {
-            let mut sum = 0.0f32;
-            for j in 0..MP3_MAX_NUM_SUBBAND {
-                sum += filter_in[j] * SYNTHESIS_FILTER_COEFFICIENTS[i][j];
-            }
-            buffer.synth_buffer[buffer.synth_buffer_offset + i] = sum;
-        }
-
-        // 合成窓適用
-        for sb in 0..MP3_MAX_NUM_SUBBAND {
-            let mut sum = 0.0f32;
-            for j in 0..16 {
-                let k = sb + (j << 5);
-                let index = (buffer.synth_buffer_offset + (k + (((j + 1) >> 1) << 6)))
-                    & (2 * MP3_DEWINDOW_SIZE - 1);
-                sum += MP3_DEWINDOW_TABLE[k] * buffer.synth_buffer[index];
-            }
-            output[ssb * MP3_MAX_NUM_SUBBAND + sb] = sum;
-        }
+        // 1タイムスロット分の合成
+        mp3_subband_synthesis(
+            buffer,
+            &filter_in,
+            <&mut [f32; MP3_MAX_NUM_SUBBAND]>::try_from(
+                &mut output[ssb * MP3_MAX_NUM_SUBBAND..(ssb + 1) * MP3_MAX_NUM_SUBBAND],
+            )
+            .unwrap(),
+        );
     }
 }
 
@@ -3918,6 +4042,7 @@ fn subband_synthesis(
 pub fn mp3_hybrid_synthesis(
     header: &MP3FrameHeader,
     side_info: &MP3SideInformation,
+    is_pos: &[[u8; MP3_NUM_SAMPLES_PER_GRANULE]; MP3_NUM_GRANLES_PER_FRAME],
     synth_buffer: &mut [MP3SynthesisBuffer],
     buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
 ) {
@@ -3928,10 +4053,10 @@ pub fn mp3_hybrid_synthesis(
     };
 
     // ステレオ信号処理
-    stereo_processing(header, buffer);
+    stereo_processing(header, side_info, is_pos, buffer);
 
     for ch in 0..num_channels {
-        for gr in 0..MP3_NUM_GRANLES_PER_FRAME {
+        for gr in 0..get_num_granules!(header) {
             let granule = &side_info.ch[ch].gr[gr];
             let buffer_ref = <&mut [f32; MP3_NUM_SAMPLES_PER_GRANULE]>::try_from(
                 &mut buffer[ch]
@@ -3945,6 +4070,9 @@ pub fn mp3_hybrid_synthesis(
             // エイリアス削減バタフライ演算
             antialias_butterfly(granule, buffer_ref);
 
+            // 隠蔽処理のためにスペクトルを記録
+            synth_buffer[ch].spectrum.copy_from_slice(buffer_ref);
+
             // IMDCT
             imdct(granule, &mut synth_buffer[ch].imdct_buffer, buffer_ref);
 
@@ -3957,3 +4085,55 @@ pub fn mp3_hybrid_synthesis(
         }
     }
 }
+
+/// 直前のグラニュールのスペクトルにgainを乗じて1グラニュール分を合成（Layer3の隠蔽処理）
+/// ロングブロックとして合成し、IMDCTのオーバーラップとサブバンド合成の状態を連続させる
+pub fn mp3_conceal_granule(
+    synth_buffer: &mut MP3SynthesisBuffer,
+    gain: f32,
+    output: &mut [f32; MP3_NUM_SAMPLES_PER_GRANULE],
+) {
+    // 次の隠蔽処理では減衰したスペクトルを使う
+    for spec in synth_buffer.spectrum.iter_mut() {
+        *spec *= gain;
+    }
+    output.copy_from_slice(&synth_buffer.spectrum);
+
+    imdct(
+        &MP3GranuleInformation::default(),
+        &mut synth_buffer.imdct_buffer,
+        output,
+    );
+    phase_inversion(output);
+    let interm_buffer = *output;
+    subband_synthesis(synth_buffer, &interm_buffer, output);
+}
+
+/// 直前のサブバンド合成入力にgainを乗じて繰り返し合成（Layer1/Layer2の隠蔽処理）
+/// gainは直前の入力の長さ(MP3_DCT_SIZEスロット)ごとに乗じる
+pub fn mp3_conceal_subband(
+    synth_buffer: &mut MP3SynthesisBuffer,
+    gain: f32,
+    num_slots: usize,
+    output: &mut [f32],
+) {
+    for block_start in (0..num_slots).step_by(MP3_DCT_SIZE) {
+        // 合成すると入力が記録されるため、ブロックの先頭で複製
+        let history = synth_buffer.subband_history;
+        let history_pos = synth_buffer.subband_history_pos;
+        for slot in block_start..num_slots.min(block_start + MP3_DCT_SIZE) {
+            let mut input = history[(history_pos + slot - block_start) % MP3_DCT_SIZE];
+            for sample in input.iter_mut() {
+                *sample *= gain;
+            }
+            mp3_subband_synthesis(
+                synth_buffer,
+                &input,
+                <&mut [f32; MP3_MAX_NUM_SUBBAND]>::try_from(
+                    &mut output[slot * MP3_MAX_NUM_SUBBAND..(slot + 1) * MP3_MAX_NUM_SUBBAND],
+                )
+                .unwrap(),
+            );
+        }
+    }
+}
@@ -0,0 +1,314 @@
+//! ID3v1/ID3v1.1タグと拡張タグ(TAG+)のパーサ
+//! ID3v1タグはファイル末尾128byte、拡張タグはその直前の227byteに配置される
+
+/// ID3v1タグのサイズ
+pub const ID3V1_TAG_SIZE: usize = 128;
+/// 拡張タグ(TAG+)のサイズ
+pub const ID3V1_ENHANCED_TAG_SIZE: usize = 227;
+
+/// ジャンル名の一覧（0-79: ID3v1標準, 80-191: Winamp拡張）
+pub const ID3V1_GENRE_TABLE: [&str; 192] = [
+    "Blues",
+    "Classic Rock",
+    "Country",
+    "Dance",
+    "Disco",
+    "Funk",
+    "Grunge",
+    "Hip-Hop",
+    "Jazz",
+    "Metal",
+    "New Age",
+    "Oldies",
+    "Other",
+    "Pop",
+    "R&B",
+    "Rap",
+    "Reggae",
+    "Rock",
+    "Techno",
+    "Industrial",
+    "Alternative",
+    "Ska",
+    "Death Metal",
+    "Pranks",
+    "Soundtrack",
+    "Euro-Techno",
+    "Ambient",
+    "Trip-Hop",
+    "Vocal",
+    "Jazz+Funk",
+    "Fusion",
+    "Trance",
+    "Classical",
+    "Instrumental",
+    "Acid",
+    "House",
+    "Game",
+    "Sound Clip",
+    "Gospel",
+    "Noise",
+    "AlternRock",
+    "Bass",
+    "Soul",
+    "Punk",
+    "Space",
+    "Meditative",
+    "Instrumental Pop",
+    "Instrumental Rock",
+    "Ethnic",
+    "Gothic",
+    "Darkwave",
+    "Techno-Industrial",
+    "Electronic",
+    "Pop-Folk",
+    "Eurodance",
+    "Dream",
+    "Southern Rock",
+    "Comedy",
+    "Cult",
+    "Gangsta",
+    "Top 40",
+    "Christian Rap",
+    "Pop/Funk",
+    "Jungle",
+    "Native American",
+    "Cabaret",
+    "New Wave",
+    "Psychadelic",
+    "Rave",
+    "Showtunes",
+    "Trailer",
+    "Lo-Fi",
+    "Tribal",
+    "Acid Punk",
+    "Acid Jazz",
+    "Polka",
+    "Retro",
+    "Musical",
+    "Rock & Roll",
+    "Hard Rock",
+    "Folk",
+    "Folk-Rock",
+    "National Folk",
+    "Swing",
+    "Fast Fusion",
+    "Bebob",
+    "Latin",
+    "Revival",
+    "Celtic",
+    "Bluegrass",
+    "Avantgarde",
+    "Gothic Rock",
+    "Progressive Rock",
+    "Psychedelic Rock",
+    "Symphonic Rock",
+    "Slow Rock",
+    "Big Band",
+    "Chorus",
+    "Easy Listening",
+    "Acoustic",
+    "Humour",
+    "Speech",
+    "Chanson",
+    "Opera",
+    "Chamber Music",
+    "Sonata",
+    "Symphony",
+    "Booty Bass",
+    "Primus",
+    "Porn Groove",
+    "Satire",
+    "Slow Jam",
+    "Club",
+    "Tango",
+    "Samba",
+    "Folklore",
+    "Ballad",
+    "Power Ballad",
+    "Rhythmic Soul",
+    "Freestyle",
+    "Duet",
+    "Punk Rock",
+    "Drum Solo",
+    "A capella",
+    "Euro-House",
+    "Dance Hall",
+    "Goa",
+    "Drum & Bass",
+    "Club-House",
+    "Hardcore",
+    "Terror",
+    "Indie",
+    "BritPop",
+    "Negerpunk",
+    "Polsk Punk",
+    "Beat",
+    "Christian Gangsta Rap",
+    "Heavy Metal",
+    "Black Metal",
+    "Crossover",
+    "Contemporary Christian",
+    "Christian Rock",
+    "Merengue",
+    "Salsa",
+    "Thrash Metal",
+    "Anime",
+    "JPop",
+    "Synthpop",
+    "Abstract",
+    "Art Rock",
+    "Baroque",
+    "Bhangra",
+    "Big Beat",
+    "Breakbeat",
+    "Chillout",
+    "Downtempo",
+    "Dub",
+    "EBM",
+    "Eclectic",
+    "Electro",
+    "Electroclash",
+    "Emo",
+    "Experimental",
+    "Garage",
+    "Global",
+    "IDM",
+    "Illbient",
+    "Industro-Goth",
+    "Jam Band",
+    "Krautrock",
+    "Leftfield",
+    "Lounge",
+    "Math Rock",
+    "New Romantic",
+    "Nu-Breakz",
+    "Post-Punk",
+    "Post-Rock",
+    "Psytrance",
+    "Shoegaze",
+    "Space Rock",
+    "Trop Rock",
+    "World Music",
+    "Neoclassical",
+    "Audiobook",
+    "Audio Theatre",
+    "Neue Deutsche Welle",
+    "Podcast",
+    "Indie Rock",
+    "G-Funk",
+    "Dubstep",
+    "Garage Rock",
+    "Psybient",
+];
+
+/// 拡張タグ(TAG+)情報 各フィールドはISO-8859-1
+#[derive(PartialEq, Copy, Clone)]
+pub struct ID3v1EnhancedTag<'a> {
+    /// タイトルの続き（ID3v1タグのタイトルに連結する）
+    pub title: &'a [u8],
+    /// アーティストの続き
+    pub artist: &'a [u8],
+    /// アルバムの続き
+    pub album: &'a [u8],
+    /// 速さ(0:未設定, 1:遅い, 2:普通, 3:速い, 4:ハードコア)
+    pub speed: u8,
+    /// ジャンル（自由記述）
+    pub genre: &'a [u8],
+    /// 開始時刻("mmm:ss")
+    pub start_time: &'a [u8],
+    /// 終了時刻("mmm:ss")
+    pub end_time: &'a [u8],
+}
+
+/// ID3v1タグ情報 各フィールドはISO-8859-1で、末尾の0と空白は除く
+#[derive(PartialEq, Copy, Clone)]
+pub struct ID3v1Tag<'a> {
+    /// タイトル
+    pub title: &'a [u8],
+    /// アーティスト
+    pub artist: &'a [u8],
+    /// アルバム
+    pub album: &'a [u8],
+    /// 年
+    pub year: &'a [u8],
+    /// コメント
+    pub comment: &'a [u8],
+    /// トラック番号(ID3v1.1のみ)
+    pub track: Option<u8>,
+    /// ジャンル番号(255:未設定)
+    pub genre: u8,
+    /// 拡張タグ情報
+    pub enhanced: Option<ID3v1EnhancedTag<'a>>,
+}
+
+impl ID3v1Tag<'_> {
+    /// ジャンル名を取得
+    pub fn genre_name(&self) -> Option<&'static str> {
+        ID3V1_GENRE_TABLE.get(self.genre as usize).copied()
+    }
+}
+
+/// 末尾の0と空白を除いたフィールドを取得
+fn get_field(data: &[u8]) -> &[u8] {
+    let end = data
+        .iter()
+        .rposition(|&c| c != 0 && c != b' ')
+        .map_or(0, |pos| pos + 1);
+    &data[..end]
+}
+
+/// ファイル末尾のID3v1タグと拡張タグの合計サイズを取得（タグがなければ0）
+pub fn get_id3v1_tag_size(data: &[u8]) -> usize {
+    if data.len() < ID3V1_TAG_SIZE || !data[data.len() - ID3V1_TAG_SIZE..].starts_with(b"TAG") {
+        return 0;
+    }
+    let size = ID3V1_TAG_SIZE + ID3V1_ENHANCED_TAG_SIZE;
+    if data.len() >= size && data[data.len() - size..].starts_with(b"TAG+") {
+        size
+    } else {
+        ID3V1_TAG_SIZE
+    }
+}
+
+/// ファイル末尾のID3v1タグのデコード
+pub fn decode_id3v1_tag(data: &[u8]) -> Option<ID3v1Tag<'_>> {
+    let size = get_id3v1_tag_size(data);
+    if size == 0 {
+        return None;
+    }
+    let tag = &data[data.len() - ID3V1_TAG_SIZE..];
+
+    // コメントの29byte目が0で30byte目が0でなければID3v1.1のトラック番号
+    let (comment, track) = if tag[125] == 0 && tag[126] != 0 {
+        (&tag[97..125], Some(tag[126]))
+    } else {
+        (&tag[97..127], None)
+    };
+
+    let enhanced = if size > ID3V1_TAG_SIZE {
+        let ext = &data[data.len() - size..data.len() - ID3V1_TAG_SIZE];
+        Some(ID3v1EnhancedTag {
+            title: get_field(&ext[4..64]),
+            artist: get_field(&ext[64..124]),
+            album: get_field(&ext[124..184]),
+            speed: ext[184],
+            genre: get_field(&ext[185..215]),
+            start_time: get_field(&ext[215..221]),
+            end_time: get_field(&ext[221..227]),
+        })
+    } else {
+        None
+    };
+
+    Some(ID3v1Tag {
+        title: get_field(&tag[3..33]),
+        artist: get_field(&tag[33..63]),
+        album: get_field(&tag[63..93]),
+        year: get_field(&tag[93..97]),
+        comment: get_field(comment),
+        track,
+        genre: tag[127],
+        enhanced,
+    })
+}
@@ -0,0 +1,819 @@
+//! ID3v2タグ(v2.2/v2.3/v2.4)のパーサ
+//! https://id3.org/id3v2.4.0-structure を参照
+use crate::decoder::MP3DecodeError;
+use miniz_oxide::inflate::TINFLStatus;
+use miniz_oxide::inflate::core::{DecompressorOxide, decompress, inflate_flags};
+
+/// ID3v2ヘッダ（フッタ）のサイズ
+pub const ID3V2_HEADER_SIZE: usize = 10;
+
+/// v2.2のフレームIDとv2.3以降のフレームIDの対応
+const ID3V2_2_FRAME_ID_TABLE: [(&[u8; 3], &[u8; 4]); 11] = [
+    (b"TT2", b"TIT2"),
+    (b"TP1", b"TPE1"),
+    (b"TAL", b"TALB"),
+    (b"TRK", b"TRCK"),
+    (b"TYE", b"TYER"),
+    (b"TXX", b"TXXX"),
+    (b"COM", b"COMM"),
+    (b"ULT", b"USLT"),
+    (b"PIC", b"APIC"),
+    (b"TCO", b"TCON"),
+    (b"TSS", b"TSSE"),
+];
+
+/// ID3v2ヘッダ情報
+#[derive(PartialEq, Copy, Clone)]
+pub struct ID3v2Header {
+    /// メジャーバージョン(2, 3, 4)
+    pub major_version: u8,
+    /// リビジョン
+    pub revision: u8,
+    /// タグ全体に非同期化が適用されているか
+    pub unsynchronisation: bool,
+    /// 拡張ヘッダがあるか（v2.2では圧縮フラグ）
+    pub extended_header: bool,
+    /// 実験的なタグか
+    pub experimental: bool,
+    /// フッタがあるか(v2.4のみ)
+    pub footer: bool,
+    /// ヘッダ・フッタを除いたタグのサイズ
+    pub size: usize,
+}
+
+impl ID3v2Header {
+    /// ヘッダ・フッタを含むタグ全体のサイズ
+    pub fn tag_size(&self) -> usize {
+        ID3V2_HEADER_SIZE + self.size + if self.footer { ID3V2_HEADER_SIZE } else { 0 }
+    }
+}
+
+/// ID3v2のテキストエンコーディング
+#[derive(PartialEq, Copy, Clone)]
+pub enum ID3v2TextEncoding {
+    /// ISO-8859-1
+    Latin1 = 0,
+    /// BOM付きUTF-16
+    Utf16 = 1,
+    /// BOMなしUTF-16BE(v2.4のみ)
+    Utf16BE = 2,
+    /// UTF-8(v2.4のみ)
+    Utf8 = 3,
+}
+
+/// ID3v2のテキスト（エンコードされたままのバイト列を保持）
+#[derive(PartialEq, Copy, Clone)]
+pub struct ID3v2Text<'a> {
+    /// エンコーディング
+    pub encoding: ID3v2TextEncoding,
+    /// 終端文字を除いたバイト列（UTF-16ではBOMを含む）
+    pub data: &'a [u8],
+}
+
+/// ID3v2のテキストを1文字ずつデコードするイテレータ
+pub struct ID3v2Chars<'a> {
+    /// エンコーディング
+    encoding: ID3v2TextEncoding,
+    /// UTF-16のバイトオーダー
+    big_endian: bool,
+    /// 未デコードのバイト列
+    data: &'a [u8],
+}
+
+impl<'a> ID3v2Text<'a> {
+    /// 1文字ずつデコードするイテレータを取得（不正なバイト列はU+FFFDに置換）
+    pub fn chars(&self) -> ID3v2Chars<'a> {
+        let mut data = self.data;
+        let mut big_endian = true;
+        // UTF-16はBOMでバイトオーダーを判定（BOMがなければビッグエンディアンとみなす）
+        if self.encoding == ID3v2TextEncoding::Utf16 && data.len() >= 2 {
+            match (data[0], data[1]) {
+                (0xFF, 0xFE) => {
+                    big_endian = false;
+                    data = &data[2..];
+                }
+                (0xFE, 0xFF) => data = &data[2..],
+                _ => {}
+            }
+        }
+        ID3v2Chars {
+            encoding: self.encoding,
+            big_endian,
+            data,
+        }
+    }
+
+    /// 文字列スライスとして取得（UTF-8またはASCIIのみのISO-8859-1の場合）
+    pub fn as_str(&self) -> Option<&'a str> {
+        match self.encoding {
+            ID3v2TextEncoding::Utf8 => core::str::from_utf8(self.data).ok(),
+            ID3v2TextEncoding::Latin1 if self.data.is_ascii() => {
+                core::str::from_utf8(self.data).ok()
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Iterator for ID3v2Chars<'_> {
+    type Item = char;
+
+    fn next(&mut self) -> Option<char> {
+        match self.encoding {
+            ID3v2TextEncoding::Latin1 => {
+                let (&byte, rest) = self.data.split_first()?;
+                self.data = rest;
+                Some(byte as char)
+            }
+            ID3v2TextEncoding::Utf8 => {
+                let lead = *self.data.first()?;
+                let len = match lead {
+                    0x00..=0x7F => 1,
+                    0xC0..=0xDF => 2,
+                    0xE0..=0xEF => 3,
+                    _ => 4,
+                };
+                if let Some(bytes) = self.data.get(..len)
+                    && let Ok(s) = core::str::from_utf8(bytes)
+                {
+                    self.data = &self.data[len..];
+                    return s.chars().next();
+                }
+                self.data = &self.data[1..];
+                Some(char::REPLACEMENT_CHARACTER)
+            }
+            ID3v2TextEncoding::Utf16 | ID3v2TextEncoding::Utf16BE => {
+                let read_unit = |bytes: &[u8]| -> Option<u16> {
+                    let unit = [*bytes.first()?, *bytes.get(1)?];
+                    Some(if self.big_endian {
+                        u16::from_be_bytes(unit)
+                    } else {
+                        u16::from_le_bytes(unit)
+                    })
+                };
+                let unit = read_unit(self.data)?;
+                self.data = &self.data[2..];
+                // サロゲートペア
+                if (0xD800..0xDC00).contains(&unit)
+                    && let Some(low) = read_unit(self.data)
+                    && (0xDC00..0xE000).contains(&low)
+                {
+                    self.data = &self.data[2..];
+                    let code = 0x10000 + (((unit as u32) - 0xD800) << 10) + ((low as u32) - 0xDC00);
+                    return char::from_u32(code);
+                }
+                Some(char::from_u32(unit as u32).unwrap_or(char::REPLACEMENT_CHARACTER))
+            }
+        }
+    }
+}
+
+/// ID3v2フレームのフラグ
+#[derive(PartialEq, Copy, Clone)]
+pub struct ID3v2FrameFlags {
+    /// タグ変更時にフレームを破棄すべきか
+    pub tag_alter_preservation: bool,
+    /// ファイル変更時にフレームを破棄すべきか
+    pub file_alter_preservation: bool,
+    /// 読み取り専用か
+    pub read_only: bool,
+    /// グループID
+    pub grouping: Option<u8>,
+    /// zlib圧縮されているか
+    pub compression: bool,
+    /// 暗号化方式
+    pub encryption: Option<u8>,
+    /// 非同期化されているか
+    pub unsynchronisation: bool,
+    /// 圧縮・非同期化前のデータサイズ
+    pub data_length: Option<u32>,
+}
+
+/// ID3v2のフレーム（内容は未デコード）
+#[derive(PartialEq, Copy, Clone)]
+pub struct ID3v2RawFrame<'a> {
+    /// フレームID（v2.2のIDはv2.3以降のIDに変換、対応がなければ末尾を0で埋める）
+    pub id: [u8; 4],
+    /// タグのメジャーバージョン
+    pub major_version: u8,
+    /// フラグ
+    pub flags: ID3v2FrameFlags,
+    /// フラグに付随するバイトを除いたフレームの内容（非同期化・圧縮されたまま）
+    pub data: &'a [u8],
+}
+
+/// ID3v2タグのフレームを順に取り出すイテレータ
+#[derive(PartialEq, Clone)]
+pub struct ID3v2FrameIterator<'a> {
+    /// タグのメジャーバージョン
+    major_version: u8,
+    /// タグ全体が非同期化されているか(v2.4)
+    unsynchronisation: bool,
+    /// 未読のフレーム領域
+    data: &'a [u8],
+}
+
+/// ID3v2のフレーム内容
+#[derive(PartialEq, Clone)]
+pub enum ID3v2Frame<'a> {
+    /// テキスト情報(TIT2, TPE1, TALB, TRCK, TYER, TDRCなど"T"で始まるフレーム)
+    Text {
+        /// フレームID
+        id: [u8; 4],
+        /// テキスト（v2.4では複数の値が終端文字で区切られうる）
+        text: ID3v2Text<'a>,
+    },
+    /// ユーザ定義テキスト(TXXX)
+    UserText {
+        /// 説明
+        description: ID3v2Text<'a>,
+        /// 値
+        value: ID3v2Text<'a>,
+    },
+    /// コメント(COMM)
+    Comment {
+        /// 言語コード(ISO-639-2)
+        language: [u8; 3],
+        /// 説明
+        description: ID3v2Text<'a>,
+        /// 本文
+        text: ID3v2Text<'a>,
+    },
+    /// 非同期歌詞(USLT)
+    Lyrics {
+        /// 言語コード(ISO-639-2)
+        language: [u8; 3],
+        /// 説明
+        description: ID3v2Text<'a>,
+        /// 歌詞
+        text: ID3v2Text<'a>,
+    },
+    /// 添付画像(APIC)
+    Picture {
+        /// MIMEタイプ（v2.2では"JPG"などの3文字の形式名）
+        mime_type: &'a [u8],
+        /// 画像の種類(0x03: 表紙など)
+        picture_type: u8,
+        /// 説明
+        description: ID3v2Text<'a>,
+        /// 画像データ
+        data: &'a [u8],
+    },
+    /// プライベートデータ(PRIV)
+    Private {
+        /// 所有者の識別子
+        owner: &'a [u8],
+        /// データ
+        data: &'a [u8],
+    },
+    /// チャプター(CHAP)
+    Chapter {
+        /// 要素ID
+        element_id: &'a [u8],
+        /// 開始時刻(ms)
+        start_time: u32,
+        /// 終了時刻(ms)
+        end_time: u32,
+        /// 開始位置(byte, 未使用ならば0xFFFFFFFF)
+        start_offset: u32,
+        /// 終了位置(byte, 未使用ならば0xFFFFFFFF)
+        end_offset: u32,
+        /// 埋め込まれたフレーム
+        sub_frames: ID3v2FrameIterator<'a>,
+    },
+    /// 目次(CTOC)
+    TableOfContents {
+        /// 要素ID
+        element_id: &'a [u8],
+        /// 最上位の目次か
+        top_level: bool,
+        /// 子要素が順序付けられているか
+        ordered: bool,
+        /// 子要素の数
+        num_children: u8,
+        /// 終端文字で区切られた子要素のID
+        child_element_ids: &'a [u8],
+        /// 埋め込まれたフレーム
+        sub_frames: ID3v2FrameIterator<'a>,
+    },
+    /// 次のタグの位置(SEEK)
+    Seek {
+        /// このタグの終端から次のタグまでの最小のバイト数
+        offset: u32,
+    },
+    /// 上記以外のフレーム
+    Unknown {
+        /// フレームID
+        id: [u8; 4],
+        /// フレームの内容
+        data: &'a [u8],
+    },
+}
+
+/// ID3v2タグ
+#[derive(Clone)]
+pub struct ID3v2Tag<'a> {
+    /// ヘッダ情報
+    pub header: ID3v2Header,
+    /// 拡張ヘッダとパディングを含むフレーム領域（v2.4以前の非同期化は解除済み）
+    frames: &'a [u8],
+}
+
+impl<'a> ID3v2Tag<'a> {
+    /// フレームを順に取り出すイテレータを取得
+    pub fn frames(&self) -> ID3v2FrameIterator<'a> {
+        ID3v2FrameIterator {
+            major_version: self.header.major_version,
+            unsynchronisation: self.header.major_version >= 4 && self.header.unsynchronisation,
+            data: self.frames,
+        }
+    }
+
+    /// 指定したIDのフレームのうち最初のものを取得
+    pub fn find_frame(&self, id: &[u8; 4]) -> Option<ID3v2RawFrame<'a>> {
+        self.frames().find(|frame| &frame.id == id)
+    }
+
+    /// SEEKフレームが示す、このタグの終端から次のタグまでのバイト数を取得
+    pub fn seek_offset(&self) -> Option<u32> {
+        match self.find_frame(b"SEEK")?.decode(&mut []).ok()? {
+            ID3v2Frame::Seek { offset } => Some(offset),
+            _ => None,
+        }
+    }
+}
+
+/// 28bitの同期安全整数の読み出し
+fn read_syncsafe_u32(data: &[u8]) -> Option<u32> {
+    let bytes = data.get(..4)?;
+    if bytes.iter().any(|&b| b >= 0x80) {
+        return None;
+    }
+    Some(bytes.iter().fold(0u32, |acc, &b| (acc << 7) | b as u32))
+}
+
+/// ビッグエンディアンの整数の読み出し
+fn read_uint_be(data: &[u8], nbytes: usize) -> Option<u32> {
+    let bytes = data.get(..nbytes)?;
+    Some(bytes.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32))
+}
+
+/// 非同期化の解除（0xFF直後の0x00を取り除く）出力サイズを返す
+fn decode_unsynchronisation(input: &[u8], output: &mut [u8]) -> Result<usize, MP3DecodeError> {
+    let mut size = 0;
+    let mut prev = 0u8;
+    for &byte in input {
+        if !(prev == 0xFF && byte == 0x00) {
+            *output
+                .get_mut(size)
+                .ok_or(MP3DecodeError::InsufficientBuffer)? = byte;
+            size += 1;
+        }
+        prev = byte;
+    }
+    Ok(size)
+}
+
+/// zlib圧縮されたデータの展開 出力サイズを返す
+fn decode_compression(input: &[u8], output: &mut [u8]) -> Result<usize, MP3DecodeError> {
+    let mut decompressor = DecompressorOxide::new();
+    let (status, _, size) = decompress(
+        &mut decompressor,
+        input,
+        output,
+        0,
+        inflate_flags::TINFL_FLAG_PARSE_ZLIB_HEADER
+            | inflate_flags::TINFL_FLAG_USING_NON_WRAPPING_OUTPUT_BUF,
+    );
+    match status {
+        TINFLStatus::Done => Ok(size),
+        TINFLStatus::HasMoreOutput => Err(MP3DecodeError::InsufficientBuffer),
+        _ => Err(MP3DecodeError::InvalidFormat),
+    }
+}
+
+/// 終端文字までのテキストを切り出し 残りのデータを返す
+fn split_terminated_text(encoding: ID3v2TextEncoding, data: &[u8]) -> (ID3v2Text<'_>, &[u8]) {
+    let end = match encoding {
+        // UTF-16は2byte境界の0x0000が終端
+        ID3v2TextEncoding::Utf16 | ID3v2TextEncoding::Utf16BE => data
+            .chunks_exact(2)
+            .position(|unit| unit == [0, 0])
+            .map(|pos| (2 * pos, 2 * pos + 2)),
+        _ => data.iter().position(|&b| b == 0).map(|pos| (pos, pos + 1)),
+    };
+    let (text, rest) = match end {
+        Some((text_end, next)) => (&data[..text_end], &data[next..]),
+        None => (data, &data[data.len()..]),
+    };
+    (
+        ID3v2Text {
+            encoding,
+            data: text,
+        },
+        rest,
+    )
+}
+
+/// 末尾の終端文字までのテキストを切り出し
+fn get_text(encoding: ID3v2TextEncoding, data: &[u8]) -> ID3v2Text<'_> {
+    let mut end = data.len();
+    match encoding {
+        ID3v2TextEncoding::Utf16 | ID3v2TextEncoding::Utf16BE => {
+            end -= end % 2;
+            while end >= 2 && data[end - 2..end] == [0, 0] {
+                end -= 2;
+            }
+        }
+        _ => {
+            while end >= 1 && data[end - 1] == 0 {
+                end -= 1;
+            }
+        }
+    }
+    ID3v2Text {
+        encoding,
+        data: &data[..end],
+    }
+}
+
+/// Latin-1の終端文字までのバイト列を切り出し 残りのデータを返す
+fn split_terminated_bytes(data: &[u8]) -> (&[u8], &[u8]) {
+    match data.iter().position(|&b| b == 0) {
+        Some(pos) => (&data[..pos], &data[pos + 1..]),
+        None => (data, &data[data.len()..]),
+    }
+}
+
+/// テキストエンコーディングの読み出し
+fn read_text_encoding(data: &[u8]) -> Result<(ID3v2TextEncoding, &[u8]), MP3DecodeError> {
+    let (&encoding, rest) = data.split_first().ok_or(MP3DecodeError::InsufficientData)?;
+    let encoding = match encoding {
+        0 => ID3v2TextEncoding::Latin1,
+        1 => ID3v2TextEncoding::Utf16,
+        2 => ID3v2TextEncoding::Utf16BE,
+        3 => ID3v2TextEncoding::Utf8,
+        _ => return Err(MP3DecodeError::InvalidFormat),
+    };
+    Ok((encoding, rest))
+}
+
+/// フレーム内容のパース
+fn parse_frame_content(
+    id: [u8; 4],
+    major_version: u8,
+    data: &[u8],
+) -> Result<ID3v2Frame<'_>, MP3DecodeError> {
+    match &id {
+        b"TXXX" => {
+            let (encoding, data) = read_text_encoding(data)?;
+            let (description, data) = split_terminated_text(encoding, data);
+            Ok(ID3v2Frame::UserText {
+                description,
+                value: get_text(encoding, data),
+            })
+        }
+        [b'T', ..] => {
+            let (encoding, data) = read_text_encoding(data)?;
+            Ok(ID3v2Frame::Text {
+                id,
+                text: get_text(encoding, data),
+            })
+        }
+        b"COMM" | b"USLT" => {
+            let (encoding, data) = read_text_encoding(data)?;
+            let language: [u8; 3] = data
+                .get(..3)
+                .and_then(|lang| lang.try_into().ok())
+                .ok_or(MP3DecodeError::InsufficientData)?;
+            let (description, data) = split_terminated_text(encoding, &data[3..]);
+            let text = get_text(encoding, data);
+            Ok(if &id == b"COMM" {
+                ID3v2Frame::Comment {
+                    language,
+                    description,
+                    text,
+                }
+            } else {
+                ID3v2Frame::Lyrics {
+                    language,
+                    description,
+                    text,
+                }
+            })
+        }
+        b"APIC" => {
+            let (encoding, data) = read_text_encoding(data)?;
+            // v2.2は3文字の画像形式、v2.3以降は終端付きのMIMEタイプ
+            let (mime_type, data) = if major_version == 2 {
+                if data.len() < 3 {
+                    return Err(MP3DecodeError::InsufficientData);
+                }
+                data.split_at(3)
+            } else {
+                split_terminated_bytes(data)
+            };
+            let (&picture_type, data) =
+                data.split_first().ok_or(MP3DecodeError::InsufficientData)?;
+            let (description, data) = split_terminated_text(encoding, data);
+            Ok(ID3v2Frame::Picture {
+                mime_type,
+                picture_type,
+                description,
+                data,
+            })
+        }
+        b"PRIV" => {
+            let (owner, data) = split_terminated_bytes(data);
+            Ok(ID3v2Frame::Private { owner, data })
+        }
+        b"CHAP" => {
+            let (element_id, data) = split_terminated_bytes(data);
+            let read = |index: usize| {
+                read_uint_be(data.get(4 * index..).unwrap_or(&[]), 4)
+                    .ok_or(MP3DecodeError::InsufficientData)
+            };
+            Ok(ID3v2Frame::Chapter {
+                element_id,
+                start_time: read(0)?,
+                end_time: read(1)?,
+                start_offset: read(2)?,
+                end_offset: read(3)?,
+                sub_frames: ID3v2FrameIterator {
+                    major_version,
+                    unsynchronisation: false,
+                    data: &data[16..],
+                },
+            })
+        }
+        b"CTOC" => {
+            let (element_id, data) = split_terminated_bytes(data);
+            let (flags, num_children) = match data.get(..2) {
+                Some(bytes) => (bytes[0], bytes[1]),
+                None => return Err(MP3DecodeError::InsufficientData),
+            };
+            // 子要素のIDは終端付きで子要素の数だけ並ぶ
+            let mut rest = &data[2..];
+            for _ in 0..num_children {
+                rest = split_terminated_bytes(rest).1;
+            }
+            let ids_size = data.len() - 2 - rest.len();
+            Ok(ID3v2Frame::TableOfContents {
+                element_id,
+                top_level: (flags & 0x2) != 0,
+                ordered: (flags & 0x1) != 0,
+                num_children,
+                child_element_ids: &data[2..2 + ids_size],
+                sub_frames: ID3v2FrameIterator {
+                    major_version,
+                    unsynchronisation: false,
+                    data: rest,
+                },
+            })
+        }
+        b"SEEK" => Ok(ID3v2Frame::Seek {
+            offset: read_uint_be(data, 4).ok_or(MP3DecodeError::InsufficientData)?,
+        }),
+        _ => Ok(ID3v2Frame::Unknown { id, data }),
+    }
+}
+
+impl<'a> ID3v2RawFrame<'a> {
+    /// フレーム内容のデコード
+    /// 非同期化・圧縮されたフレームはbufferに展開する（不要な場合は空のバッファでよい）
+    pub fn decode<'b>(&self, buffer: &'b mut [u8]) -> Result<ID3v2Frame<'b>, MP3DecodeError>
+    where
+        'a: 'b,
+    {
+        // 暗号化には非対応
+        if self.flags.encryption.is_some() {
+            return Err(MP3DecodeError::InvalidFormat);
+        }
+
+        let data: &'b [u8] = match (self.flags.unsynchronisation, self.flags.compression) {
+            (false, false) => self.data,
+            (true, false) => {
+                let size = decode_unsynchronisation(self.data, buffer)?;
+                &buffer[..size]
+            }
+            (false, true) => {
+                let size = decode_compression(self.data, buffer)?;
+                &buffer[..size]
+            }
+            (true, true) => {
+                // 非同期化を解除してから展開
+                let (unsync, output) = buffer.split_at_mut(self.data.len().min(buffer.len()));
+                let size = decode_unsynchronisation(self.data, unsync)?;
+                let size = decode_compression(&unsync[..size], output)?;
+                &output[..size]
+            }
+        };
+
+        parse_frame_content(self.id, self.major_version, data)
+    }
+}
+
+impl<'a> Iterator for ID3v2FrameIterator<'a> {
+    type Item = ID3v2RawFrame<'a>;
+
+    fn next(&mut self) -> Option<ID3v2RawFrame<'a>> {
+        // v2.2は6byte, v2.3以降は10byteのフレームヘッダ
+        let header_size = if self.major_version == 2 { 6 } else { 10 };
+        let header = self.data.get(..header_size)?;
+
+        // パディング（0埋め）に達したら終了
+        if header[0] == 0 {
+            self.data = &[];
+            return None;
+        }
+
+        let mut id = [0u8; 4];
+        let (size, flags) = match self.major_version {
+            2 => {
+                id = ID3V2_2_FRAME_ID_TABLE
+                    .iter()
+                    .find(|(v22, _)| &header[..3] == *v22)
+                    .map(|(_, v23)| **v23)
+                    .unwrap_or([header[0], header[1], header[2], 0]);
+                (read_uint_be(&header[3..], 3)?, [0u8; 2])
+            }
+            3 => {
+                id.copy_from_slice(&header[..4]);
+                (read_uint_be(&header[4..], 4)?, [header[8], header[9]])
+            }
+            _ => {
+                id.copy_from_slice(&header[..4]);
+                (read_syncsafe_u32(&header[4..])?, [header[8], header[9]])
+            }
+        };
+
+        // フレームIDは英大文字と数字のみ
+        if !id
+            .iter()
+            .take(if self.major_version == 2 { 3 } else { 4 })
+            .all(|&c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        {
+            self.data = &[];
+            return None;
+        }
+
+        let Some(body) = self.data.get(header_size..header_size + size as usize) else {
+            self.data = &[];
+            return None;
+        };
+        self.data = &self.data[header_size + size as usize..];
+
+        // フラグと付随するバイトの解釈
+        let mut frame_flags = ID3v2FrameFlags {
+            tag_alter_preservation: false,
+            file_alter_preservation: false,
+            read_only: false,
+            grouping: None,
+            compression: false,
+            encryption: None,
+            unsynchronisation: false,
+            data_length: None,
+        };
+        let mut read_pos = 0;
+        match self.major_version {
+            3 => {
+                frame_flags.tag_alter_preservation = (flags[0] & 0x80) != 0;
+                frame_flags.file_alter_preservation = (flags[0] & 0x40) != 0;
+                frame_flags.read_only = (flags[0] & 0x20) != 0;
+                // 展開後サイズ, 暗号化方式, グループIDの順
+                if (flags[1] & 0x80) != 0 {
+                    frame_flags.compression = true;
+                    frame_flags.data_length = Some(read_uint_be(body, 4)?);
+                    read_pos += 4;
+                }
+                if (flags[1] & 0x40) != 0 {
+                    frame_flags.encryption = Some(*body.get(read_pos)?);
+                    read_pos += 1;
+                }
+                if (flags[1] & 0x20) != 0 {
+                    frame_flags.grouping = Some(*body.get(read_pos)?);
+                    read_pos += 1;
+                }
+            }
+            4 => {
+                frame_flags.tag_alter_preservation = (flags[0] & 0x40) != 0;
+                frame_flags.file_alter_preservation = (flags[0] & 0x20) != 0;
+                frame_flags.read_only = (flags[0] & 0x10) != 0;
+                frame_flags.compression = (flags[1] & 0x08) != 0;
+                frame_flags.unsynchronisation = self.unsynchronisation || (flags[1] & 0x02) != 0;
+                // グループID, 暗号化方式, データ長の順
+                if (flags[1] & 0x40) != 0 {
+                    frame_flags.grouping = Some(*body.get(read_pos)?);
+                    read_pos += 1;
+                }
+                if (flags[1] & 0x04) != 0 {
+                    frame_flags.encryption = Some(*body.get(read_pos)?);
+                    read_pos += 1;
+                }
+                if (flags[1] & 0x01) != 0 {
+                    frame_flags.data_length = Some(read_syncsafe_u32(body.get(read_pos..)?)?);
+                    read_pos += 4;
+                }
+            }
+            _ => {}
+        }
+
+        Some(ID3v2RawFrame {
+            id,
+            major_version: self.major_version,
+            flags: frame_flags,
+            data: body.get(read_pos..)?,
+        })
+    }
+}
+
+/// ID3v2ヘッダのデコード
+pub fn decode_id3v2_header(data: &[u8]) -> Result<ID3v2Header, MP3DecodeError> {
+    // サイズ不足
+    if data.len() < ID3V2_HEADER_SIZE {
+        return Err(MP3DecodeError::InsufficientData);
+    }
+
+    // タグが不正
+    if &data[..3] != b"ID3" || data[3] == 0xFF || data[4] == 0xFF {
+        return Err(MP3DecodeError::InvalidFormat);
+    }
+
+    let flags = data[5];
+    let major_version = data[3];
+    Ok(ID3v2Header {
+        major_version,
+        revision: data[4],
+        unsynchronisation: (flags & 0x80) != 0,
+        extended_header: (flags & 0x40) != 0,
+        experimental: (flags & 0x20) != 0,
+        footer: major_version >= 4 && (flags & 0x10) != 0,
+        size: read_syncsafe_u32(&data[6..]).ok_or(MP3DecodeError::InvalidFormat)? as usize,
+    })
+}
+
+/// データ末尾に追加されたフッタ付きのID3v2タグのサイズを取得（タグがなければ0）
+pub fn get_appended_id3v2_tag_size(data: &[u8]) -> usize {
+    let Some(footer_start) = data.len().checked_sub(ID3V2_HEADER_SIZE) else {
+        return 0;
+    };
+    // フッタはヘッダの識別子を逆にした"3DI"で始まる
+    let footer = &data[footer_start..];
+    if &footer[..3] != b"3DI" {
+        return 0;
+    }
+    let Some(size) = read_syncsafe_u32(&footer[6..]) else {
+        return 0;
+    };
+    let tag_size = size as usize + 2 * ID3V2_HEADER_SIZE;
+    match data.len().checked_sub(tag_size) {
+        Some(start) if data[start..].starts_with(b"ID3") => tag_size,
+        _ => 0,
+    }
+}
+
+/// ID3v2タグのデコード
+/// v2.4より前のタグ全体の非同期化には対応しない（InsufficientBufferを返す）
+pub fn decode_id3v2_tag(data: &[u8]) -> Result<ID3v2Tag<'_>, MP3DecodeError> {
+    decode_id3v2_tag_with_buffer(data, &mut [])
+}
+
+/// ID3v2タグのデコード
+/// v2.4より前のタグ全体の非同期化はbufferに解除して参照する
+pub fn decode_id3v2_tag_with_buffer<'a>(
+    data: &'a [u8],
+    buffer: &'a mut [u8],
+) -> Result<ID3v2Tag<'a>, MP3DecodeError> {
+    let header = decode_id3v2_header(data)?;
+    let body = data
+        .get(ID3V2_HEADER_SIZE..ID3V2_HEADER_SIZE + header.size)
+        .ok_or(MP3DecodeError::InsufficientData)?;
+
+    // v2.2のタグ全体の圧縮は仕様が定まっていないため非対応
+    if header.major_version == 2 && header.extended_header {
+        return Err(MP3DecodeError::InvalidFormat);
+    }
+
+    // v2.4より前はタグ全体に非同期化が適用される
+    let mut frames: &'a [u8] = if header.major_version < 4 && header.unsynchronisation {
+        let size = decode_unsynchronisation(body, buffer)?;
+        &buffer[..size]
+    } else {
+        body
+    };
+
+    // 拡張ヘッダをスキップ
+    if header.extended_header {
+        let extended_header_size = match header.major_version {
+            // v2.3はサイズフィールド自身を含まない
+            3 => read_uint_be(frames, 4).map(|size| size as usize + 4),
+            _ => read_syncsafe_u32(frames).map(|size| size as usize),
+        }
+        .ok_or(MP3DecodeError::InvalidFormat)?;
+        frames = frames
+            .get(extended_header_size..)
+            .ok_or(MP3DecodeError::InvalidFormat)?;
+    }
+
+    Ok(ID3v2Tag { header, frames })
+}
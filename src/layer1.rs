@@ -0,0 +1,115 @@
+use crate::decoder::MP3DecodeError;
+use crate::hybrid_synthesis::*;
+use crate::layer2::*;
+use crate::types::*;
+
+use bitreader::BitReader;
+
+/// 1フレームあたりのタイムスロット数
+const LAYER1_NUM_SLOTS_PER_FRAME: usize = MP3_NUM_SAMPLES_PER_FRAME_LAYER1 / MP3_MAX_NUM_SUBBAND;
+/// 禁止されているビット割り当て
+const LAYER1_FORBIDDEN_ALLOCATION: u8 = 15;
+
+/// CRCで保護されるビット数（ビット割り当て）を取得
+pub fn get_layer1_crc_protected_bits(header: &MP3FrameHeader) -> usize {
+    match header.channel_mode {
+        MP3ChannelMode::Monoral => 4 * MP3_MAX_NUM_SUBBAND,
+        _ => {
+            // bound以上のサブバンドは全チャンネルで共通
+            let bound = get_joint_stereo_bound(header);
+            4 * (2 * bound + (MP3_MAX_NUM_SUBBAND - bound))
+        }
+    }
+}
+
+/// Layer1のフレームデコード
+pub fn mp3_layer1_decode(
+    header: &MP3FrameHeader,
+    data: &[u8],
+    synth_buffer: &mut [MP3SynthesisBuffer],
+    output: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+) -> Result<(), MP3DecodeError> {
+    let num_channels = match header.channel_mode {
+        MP3ChannelMode::Monoral => 1,
+        _ => 2,
+    };
+
+    let bound = if num_channels == 1 {
+        MP3_MAX_NUM_SUBBAND
+    } else {
+        get_joint_stereo_bound(header)
+    };
+
+    let mut breader = BitReader::new(data);
+
+    // ビット割り当てのデコード
+    let mut allocation = [[0u8; MP3_MAX_NUM_SUBBAND]; MP3_MAX_NUM_CHANNELS];
+    for sb in 0..MP3_MAX_NUM_SUBBAND {
+        if sb < bound {
+            for ch_allocation in allocation.iter_mut().take(num_channels) {
+                ch_allocation[sb] = read_bits!(breader, 4) as u8;
+            }
+        } else {
+            // bound以上のサブバンドは全チャンネルで共通
+            let alloc = read_bits!(breader, 4) as u8;
+            for ch_allocation in allocation.iter_mut().take(num_channels) {
+                ch_allocation[sb] = alloc;
+            }
+        }
+    }
+    if allocation
+        .iter()
+        .any(|ch_allocation| ch_allocation.contains(&LAYER1_FORBIDDEN_ALLOCATION))
+    {
+        return Err(MP3DecodeError::InvalidFormat);
+    }
+
+    // スケールファクタのデコード
+    let mut scale_factor = [[0.0f32; MP3_MAX_NUM_SUBBAND]; MP3_MAX_NUM_CHANNELS];
+    for sb in 0..MP3_MAX_NUM_SUBBAND {
+        for ch in 0..num_channels {
+            if allocation[ch][sb] != 0 {
+                scale_factor[ch][sb] = get_scale_factor(read_bits!(breader, 6) as u8);
+            }
+        }
+    }
+
+    // サブバンドサンプルのデコード・合成
+    for slot in 0..LAYER1_NUM_SLOTS_PER_FRAME {
+        let mut sbsample = [[0.0f32; MP3_MAX_NUM_SUBBAND]; MP3_MAX_NUM_CHANNELS];
+        for sb in 0..MP3_MAX_NUM_SUBBAND {
+            let nch = if sb < bound { num_channels } else { 1 };
+            for ch in 0..nch {
+                let alloc = allocation[ch][sb];
+                if alloc == 0 {
+                    continue;
+                }
+                // ビット割り当て+1のビット幅で(2^nb - 1)レベルに量子化されている
+                let nbits = alloc + 1;
+                let sample = requantize(read_bits!(breader, nbits), (1 << nbits) - 1);
+                if sb < bound {
+                    sbsample[ch][sb] = sample * scale_factor[ch][sb];
+                } else {
+                    // 共通のサンプルに各チャンネルのスケールファクタを適用
+                    for c in 0..num_channels {
+                        sbsample[c][sb] = sample * scale_factor[c][sb];
+                    }
+                }
+            }
+        }
+
+        // サブバンド合成
+        for ch in 0..num_channels {
+            mp3_subband_synthesis(
+                &mut synth_buffer[ch],
+                &sbsample[ch],
+                <&mut [f32; MP3_MAX_NUM_SUBBAND]>::try_from(
+                    &mut output[ch][slot * MP3_MAX_NUM_SUBBAND..(slot + 1) * MP3_MAX_NUM_SUBBAND],
+                )
+                .unwrap(),
+            );
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,457 @@
+use crate::decoder::MP3DecodeError;
+use crate::hybrid_synthesis::*;
+use crate::types::*;
+
+use bitreader::BitReader;
+
+/// 1フレームあたりのタイムスロット数
+const LAYER2_NUM_SLOTS_PER_FRAME: usize = 36;
+/// スケールファクタの数（3つのパートで別々のスケールファクタ）
+const LAYER2_NUM_SCALEFACTOR_PARTS: usize = 3;
+
+/// サブバンド毎の量子化テーブル
+struct SubbandQuantization {
+    /// 符号化されている最大サブバンド数
+    sblimit: usize,
+    /// サブバンド毎のビット割り当てテーブルインデックス
+    offsets: [u8; 30],
+}
+
+/// サブバンド毎の量子化テーブル
+const SUBBAND_QUANTIZATION_TABLE: [SubbandQuantization; 5] = [
+    // ISO/IEC 11172-3 Table B.2a
+    SubbandQuantization {
+        sblimit: 27,
+        offsets: [
+            7, 7, 7, 6, 6, 6, 6, 6, 6, 6, 6, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 0, 0, 0, 0, 0, 0,
+            0,
+        ],
+    },
+    // ISO/IEC 11172-3 Table B.2b
+    SubbandQuantization {
+        sblimit: 30,
+        offsets: [
+            7, 7, 7, 6, 6, 6, 6, 6, 6, 6, 6, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 0, 0, 0, 0, 0, 0,
+            0,
+        ],
+    },
+    // ISO/IEC 11172-3 Table B.2c
+    SubbandQuantization {
+        sblimit: 8,
+        offsets: [
+            5, 5, 2, 2, 2, 2, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0,
+        ],
+    },
+    // ISO/IEC 11172-3 Table B.2d
+    SubbandQuantization {
+        sblimit: 12,
+        offsets: [
+            5, 5, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0,
+            0,
+        ],
+    },
+    // ISO/IEC 13818-3 Table B.1
+    SubbandQuantization {
+        sblimit: 30,
+        offsets: [
+            4, 4, 4, 4, 2, 2, 2, 2, 2, 2, 2, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1,
+            1,
+        ],
+    },
+];
+
+/// ビット割り当てテーブル (ビット割り当てのビット幅, 量子化クラスオフセットテーブルインデックス)
+const BIT_ALLOCATION_TABLE: [(u8, usize); 8] = [
+    (2, 0),
+    (2, 3),
+    (3, 3),
+    (3, 1),
+    (4, 2),
+    (4, 3),
+    (4, 4),
+    (4, 5),
+];
+
+/// ビット割り当てから量子化クラスへのオフセットテーブル
+const QUANTIZATION_CLASS_OFFSET_TABLE: [[u8; 15]; 6] = [
+    [0, 1, 16, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 2, 3, 4, 5, 16, 0, 0, 0, 0, 0, 0, 0, 0],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14],
+    [0, 1, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15],
+    [0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 16],
+    [0, 2, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15, 16],
+];
+
+/// 量子化クラス
+struct QuantizationClass {
+    /// 量子化レベル数
+    nlevels: u32,
+    /// 3サンプルをまとめて符号化しているか
+    grouping: bool,
+    /// 符号のビット幅（グルーピング時は3サンプル分）
+    bits: u8,
+}
+
+/// 量子化クラステーブル
+const QUANTIZATION_CLASS_TABLE: [QuantizationClass; 17] = [
+    QuantizationClass {
+        nlevels: 3,
+        grouping: true,
+        bits: 5,
+    },
+    QuantizationClass {
+        nlevels: 5,
+        grouping: true,
+        bits: 7,
+    },
+    QuantizationClass {
+        nlevels: 7,
+        grouping: false,
+        bits: 3,
+    },
+    QuantizationClass {
+        nlevels: 9,
+        grouping: true,
+        bits: 10,
+    },
+    QuantizationClass {
+        nlevels: 15,
+        grouping: false,
+        bits: 4,
+    },
+    QuantizationClass {
+        nlevels: 31,
+        grouping: false,
+        bits: 5,
+    },
+    QuantizationClass {
+        nlevels: 63,
+        grouping: false,
+        bits: 6,
+    },
+    QuantizationClass {
+        nlevels: 127,
+        grouping: false,
+        bits: 7,
+    },
+    QuantizationClass {
+        nlevels: 255,
+        grouping: false,
+        bits: 8,
+    },
+    QuantizationClass {
+        nlevels: 511,
+        grouping: false,
+        bits: 9,
+    },
+    QuantizationClass {
+        nlevels: 1023,
+        grouping: false,
+        bits: 10,
+    },
+    QuantizationClass {
+        nlevels: 2047,
+        grouping: false,
+        bits: 11,
+    },
+    QuantizationClass {
+        nlevels: 4095,
+        grouping: false,
+        bits: 12,
+    },
+    QuantizationClass {
+        nlevels: 8191,
+        grouping: false,
+        bits: 13,
+    },
+    QuantizationClass {
+        nlevels: 16383,
+        grouping: false,
+        bits: 14,
+    },
+    QuantizationClass {
+        nlevels: 32767,
+        grouping: false,
+        bits: 15,
+    },
+    QuantizationClass {
+        nlevels: 65535,
+        grouping: false,
+        bits: 16,
+    },
+];
+
+/// ビット読み出し（読み出せなければデータ不足としてエラー）
+macro_rules! read_bits {
+    ($breader:expr,$nbits:expr) => {{
+        $breader
+            .read_u32($nbits)
+            .map_err(|_| MP3DecodeError::InsufficientData)?
+    }};
+}
+pub(crate) use read_bits;
+
+/// スケールファクタインデックスをスケールファクタに変換
+pub fn get_scale_factor(index: u8) -> f32 {
+    2.0f32.powf(1.0 - index as f32 / 3.0)
+}
+
+/// ジョイントステレオで個別に符号化される最大サブバンド数(bound)を取得
+pub fn get_joint_stereo_bound(header: &MP3FrameHeader) -> usize {
+    match header.channel_mode {
+        MP3ChannelMode::JointStereo => match header.ext_channel_mode {
+            MP3ExtChannelMode::NONE => 4,
+            MP3ExtChannelMode::IntensityStereo => 8,
+            MP3ExtChannelMode::MSStereo => 12,
+            MP3ExtChannelMode::MSIntensityStereo => 16,
+        },
+        _ => MP3_MAX_NUM_SUBBAND,
+    }
+}
+
+/// 量子化テーブルの選択
+fn get_subband_quantization(header: &MP3FrameHeader) -> &'static SubbandQuantization {
+    let num_channels = match header.channel_mode {
+        MP3ChannelMode::Monoral => 1,
+        _ => 2,
+    };
+    let index = match header.version {
+        MPEGVersion::MPEGVersion1 => {
+            let bit_rate_per_channel = header.bit_rate as u32 / num_channels;
+            if header.bit_rate == MP3BitRate::Kbps0 {
+                // フリーフォーマット
+                if header.sampling_rate == MP3SamplingRate::Hz48000 {
+                    0
+                } else {
+                    1
+                }
+            } else if bit_rate_per_channel <= 48_000 {
+                if header.sampling_rate == MP3SamplingRate::Hz32000 {
+                    3
+                } else {
+                    2
+                }
+            } else if bit_rate_per_channel <= 80_000
+                || header.sampling_rate == MP3SamplingRate::Hz48000
+            {
+                0
+            } else {
+                1
+            }
+        }
+        _ => 4,
+    };
+    &SUBBAND_QUANTIZATION_TABLE[index]
+}
+
+/// 量子化値の逆量子化 (2 * s - (nlevels - 1)) / nlevels で[-1,1]の範囲に復元
+pub fn requantize(sample: u32, nlevels: u32) -> f32 {
+    (2.0 * sample as f32 - (nlevels - 1) as f32) / nlevels as f32
+}
+
+/// 3サンプル分の量子化値の読み出し・逆量子化
+fn decode_samples(
+    breader: &mut BitReader,
+    qclass: &QuantizationClass,
+    output: &mut [f32; 3],
+) -> Result<(), MP3DecodeError> {
+    let mut samples = [0u32; 3];
+    if qclass.grouping {
+        // 3サンプルをまとめた符号を分解
+        let mut code = read_bits!(breader, qclass.bits);
+        for sample in samples.iter_mut() {
+            *sample = code % qclass.nlevels;
+            code /= qclass.nlevels;
+        }
+    } else {
+        for sample in samples.iter_mut() {
+            *sample = read_bits!(breader, qclass.bits);
+        }
+    }
+
+    for (out, &sample) in output.iter_mut().zip(samples.iter()) {
+        *out = requantize(sample, qclass.nlevels);
+    }
+
+    Ok(())
+}
+
+/// 処理チャンネル数、符号化されている最大サブバンド数、個別に符号化される最大サブバンド数を取得
+fn get_subband_limits(header: &MP3FrameHeader) -> (usize, usize, usize) {
+    let num_channels = match header.channel_mode {
+        MP3ChannelMode::Monoral => 1,
+        _ => 2,
+    };
+
+    let sblimit = get_subband_quantization(header).sblimit;
+    let bound = if num_channels == 1 {
+        sblimit
+    } else {
+        get_joint_stereo_bound(header).min(sblimit)
+    };
+
+    (num_channels, sblimit, bound)
+}
+
+/// ビット割り当てのデコード
+fn decode_bit_allocation(
+    header: &MP3FrameHeader,
+    breader: &mut BitReader,
+) -> Result<[[u8; MP3_MAX_NUM_SUBBAND]; MP3_MAX_NUM_CHANNELS], MP3DecodeError> {
+    let quantization = get_subband_quantization(header);
+    let (num_channels, sblimit, bound) = get_subband_limits(header);
+
+    let mut allocation = [[0u8; MP3_MAX_NUM_SUBBAND]; MP3_MAX_NUM_CHANNELS];
+    for sb in 0..sblimit {
+        let nbal = BIT_ALLOCATION_TABLE[quantization.offsets[sb] as usize].0;
+        if sb < bound {
+            for ch_allocation in allocation.iter_mut().take(num_channels) {
+                ch_allocation[sb] = read_bits!(breader, nbal) as u8;
+            }
+        } else {
+            // bound以上のサブバンドは全チャンネルで共通
+            let alloc = read_bits!(breader, nbal) as u8;
+            for ch_allocation in allocation.iter_mut().take(num_channels) {
+                ch_allocation[sb] = alloc;
+            }
+        }
+    }
+
+    Ok(allocation)
+}
+
+/// CRCで保護されるビット数（ビット割り当て・スケールファクタ選択情報）を取得
+pub fn get_layer2_crc_protected_bits(
+    header: &MP3FrameHeader,
+    data: &[u8],
+) -> Result<usize, MP3DecodeError> {
+    let mut breader = BitReader::new(data);
+    let allocation = decode_bit_allocation(header, &mut breader)?;
+
+    // 割り当てのあるサブバンドは2bitのスケールファクタ選択情報を持つ
+    let num_scfsi = allocation
+        .iter()
+        .flat_map(|ch_allocation| ch_allocation.iter())
+        .filter(|&&alloc| alloc != 0)
+        .count();
+
+    Ok(breader.position() as usize + 2 * num_scfsi)
+}
+
+/// Layer2のフレームデコード
+pub fn mp3_layer2_decode(
+    header: &MP3FrameHeader,
+    data: &[u8],
+    synth_buffer: &mut [MP3SynthesisBuffer],
+    output: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+) -> Result<(), MP3DecodeError> {
+    let quantization = get_subband_quantization(header);
+    let (num_channels, sblimit, bound) = get_subband_limits(header);
+
+    let mut breader = BitReader::new(data);
+
+    // ビット割り当てのデコード
+    let allocation = decode_bit_allocation(header, &mut breader)?;
+
+    // スケールファクタ選択情報のデコード
+    let mut scfsi = [[0u8; MP3_MAX_NUM_SUBBAND]; MP3_MAX_NUM_CHANNELS];
+    for sb in 0..sblimit {
+        for ch in 0..num_channels {
+            if allocation[ch][sb] != 0 {
+                scfsi[ch][sb] = read_bits!(breader, 2) as u8;
+            }
+        }
+    }
+
+    // スケールファクタのデコード
+    let mut scale_factor =
+        [[[0.0f32; LAYER2_NUM_SCALEFACTOR_PARTS]; MP3_MAX_NUM_SUBBAND]; MP3_MAX_NUM_CHANNELS];
+    for sb in 0..sblimit {
+        for ch in 0..num_channels {
+            if allocation[ch][sb] == 0 {
+                continue;
+            }
+            let mut index = [0u8; LAYER2_NUM_SCALEFACTOR_PARTS];
+            match scfsi[ch][sb] {
+                // 3パートそれぞれ個別
+                0 => {
+                    for idx in index.iter_mut() {
+                        *idx = read_bits!(breader, 6) as u8;
+                    }
+                }
+                // 1,2パート目で共有
+                1 => {
+                    index[0] = read_bits!(breader, 6) as u8;
+                    index[1] = index[0];
+                    index[2] = read_bits!(breader, 6) as u8;
+                }
+                // 3パートで共有
+                2 => {
+                    index[0] = read_bits!(breader, 6) as u8;
+                    index[1] = index[0];
+                    index[2] = index[0];
+                }
+                // 2,3パート目で共有
+                _ => {
+                    index[0] = read_bits!(breader, 6) as u8;
+                    index[1] = read_bits!(breader, 6) as u8;
+                    index[2] = index[1];
+                }
+            }
+            for part in 0..3 {
+                scale_factor[ch][sb][part] = get_scale_factor(index[part]);
+            }
+        }
+    }
+
+    // サブバンドサンプルのデコード
+    let mut sbsample =
+        [[[0.0f32; MP3_MAX_NUM_SUBBAND]; LAYER2_NUM_SLOTS_PER_FRAME]; MP3_MAX_NUM_CHANNELS];
+    for gr in 0..(LAYER2_NUM_SLOTS_PER_FRAME / 3) {
+        // 4グラニュール(12サンプル)ごとにスケールファクタが切り替わる
+        let part = gr / 4;
+        for sb in 0..sblimit {
+            let nch = if sb < bound { num_channels } else { 1 };
+            for ch in 0..nch {
+                let alloc = allocation[ch][sb] as usize;
+                if alloc == 0 {
+                    continue;
+                }
+                let offset = BIT_ALLOCATION_TABLE[quantization.offsets[sb] as usize].1;
+                let qclass = &QUANTIZATION_CLASS_TABLE
+                    [QUANTIZATION_CLASS_OFFSET_TABLE[offset][alloc - 1] as usize];
+                let mut samples = [0.0f32; 3];
+                decode_samples(&mut breader, qclass, &mut samples)?;
+                if sb < bound {
+                    for s in 0..3 {
+                        sbsample[ch][3 * gr + s][sb] = samples[s] * scale_factor[ch][sb][part];
+                    }
+                } else {
+                    // 共通のサンプルに各チャンネルのスケールファクタを適用
+                    for c in 0..num_channels {
+                        for s in 0..3 {
+                            sbsample[c][3 * gr + s][sb] = samples[s] * scale_factor[c][sb][part];
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // サブバンド合成
+    for ch in 0..num_channels {
+        for slot in 0..LAYER2_NUM_SLOTS_PER_FRAME {
+            mp3_subband_synthesis(
+                &mut synth_buffer[ch],
+                &sbsample[ch][slot],
+                <&mut [f32; MP3_MAX_NUM_SUBBAND]>::try_from(
+                    &mut output[ch][slot * MP3_MAX_NUM_SUBBAND..(slot + 1) * MP3_MAX_NUM_SUBBAND],
+                )
+                .unwrap(),
+            );
+        }
+    }
+
+    Ok(())
+}
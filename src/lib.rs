@@ -1,7 +1,23 @@
-// test時はno_stdを無効に設定
-#![cfg_attr(not(test), no_std)]
+// stdフィーチャ有効時はno_stdを無効に設定
+#![cfg_attr(not(feature = "std"), no_std)]
 pub mod types;
 pub mod decoder;
+pub mod ape;
+pub mod id3v1;
+pub mod id3v2;
+pub mod lyrics3;
+pub mod tag;
+pub mod stream;
+pub mod frame;
+pub mod seek_index;
+#[cfg(feature = "std")]
+pub mod reader;
 mod maindata_buffer;
 mod huffman;
 mod hybrid_synthesis;
+mod crc;
+mod deemphasis;
+mod layer1;
+mod layer2;
+mod replaygain;
+mod xing;
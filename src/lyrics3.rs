@@ -0,0 +1,103 @@
+//! Lyrics3v2タグのパーサ
+//! "LYRICSBEGIN"から始まるフィールドの列の後ろに、10進6桁のサイズと"LYRICS200"が続く
+
+/// 開始の識別子
+const LYRICS3_BEGIN: &[u8; 11] = b"LYRICSBEGIN";
+/// 終端の識別子
+const LYRICS3_V2_END: &[u8; 9] = b"LYRICS200";
+/// 10進6桁のサイズフィールドと終端の識別子のサイズ
+const LYRICS3_V2_TRAILER_SIZE: usize = 6 + 9;
+
+/// Lyrics3v2のフィールド 値はISO-8859-1
+#[derive(PartialEq, Copy, Clone)]
+pub struct Lyrics3Field<'a> {
+    /// フィールドID(IND, LYR, INF, AUT, EAL, EAR, ETT, IMG)
+    pub id: [u8; 3],
+    /// 値
+    pub value: &'a [u8],
+}
+
+/// Lyrics3v2のフィールドを順に取り出すイテレータ
+#[derive(PartialEq, Clone)]
+pub struct Lyrics3FieldIterator<'a> {
+    /// 未読のフィールド領域
+    data: &'a [u8],
+}
+
+/// Lyrics3v2タグ
+#[derive(PartialEq, Copy, Clone)]
+pub struct Lyrics3Tag<'a> {
+    /// フィールド領域
+    fields: &'a [u8],
+}
+
+impl<'a> Lyrics3Tag<'a> {
+    /// フィールドを順に取り出すイテレータを取得
+    pub fn fields(&self) -> Lyrics3FieldIterator<'a> {
+        Lyrics3FieldIterator { data: self.fields }
+    }
+
+    /// IDに一致するフィールドを取得
+    pub fn find_field(&self, id: &[u8; 3]) -> Option<Lyrics3Field<'a>> {
+        self.fields().find(|field| &field.id == id)
+    }
+}
+
+/// 10進数の読み出し
+fn read_decimal(data: &[u8]) -> Option<usize> {
+    if data.is_empty() || !data.iter().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Some(
+        data.iter()
+            .fold(0, |acc, &c| acc * 10 + (c - b'0') as usize),
+    )
+}
+
+impl<'a> Iterator for Lyrics3FieldIterator<'a> {
+    type Item = Lyrics3Field<'a>;
+
+    fn next(&mut self) -> Option<Lyrics3Field<'a>> {
+        // ID(3byte), 10進5桁のサイズ, 値の順
+        let header = self.data.get(..8)?;
+        let Some(value) = read_decimal(&header[3..]).and_then(|size| self.data.get(8..8 + size))
+        else {
+            self.data = &[];
+            return None;
+        };
+        self.data = &self.data[8 + value.len()..];
+        Some(Lyrics3Field {
+            id: [header[0], header[1], header[2]],
+            value,
+        })
+    }
+}
+
+/// データ末尾のLyrics3v2タグのサイズを取得（タグがなければ0）
+pub fn get_lyrics3_tag_size(data: &[u8]) -> usize {
+    if data.len() < LYRICS3_V2_TRAILER_SIZE || !data.ends_with(LYRICS3_V2_END) {
+        return 0;
+    }
+    let trailer_start = data.len() - LYRICS3_V2_TRAILER_SIZE;
+    // サイズは"LYRICSBEGIN"からサイズフィールドの直前まで
+    match read_decimal(&data[trailer_start..trailer_start + 6]) {
+        Some(size)
+            if size <= trailer_start && data[trailer_start - size..].starts_with(LYRICS3_BEGIN) =>
+        {
+            size + LYRICS3_V2_TRAILER_SIZE
+        }
+        _ => 0,
+    }
+}
+
+/// データ末尾のLyrics3v2タグのデコード
+pub fn decode_lyrics3_tag(data: &[u8]) -> Option<Lyrics3Tag<'_>> {
+    let size = get_lyrics3_tag_size(data);
+    if size == 0 {
+        return None;
+    }
+    let start = data.len() - size + LYRICS3_BEGIN.len();
+    Some(Lyrics3Tag {
+        fields: &data[start..data.len() - LYRICS3_V2_TRAILER_SIZE],
+    })
+}
@@ -1,4 +1,4 @@
-use bitreader::BitReader;
+use core::cmp::min;
 
 /// メインデータのバッファサイズ(byte)
 pub const MP3_MAINDATA_BUFFER_SIZE: usize = 4096;
@@ -55,28 +55,22 @@ impl MP3MainDataBuffer {
         }
     }
 
-    /// データ読み出し
+    /// データ読み出し（nbitsは32以下）
+    /// 読み込み位置は常にバッファ内にあり、リングバッファとして読み出すため失敗しない
     pub fn get_bits(&mut self, nbits: u8) -> u32 {
-        if nbits == 0 {
-            return 0;
-        }
-        // self.read_pos_bitsから読みだすビットリーダを生成
-        let mut breader = BitReader::new(&self.buffer);
-        breader.skip(self.read_pos_bits).unwrap();
-        if self.read_pos_bits + nbits as u64 >= MP3_MAINDATA_BUFFER_SIZE_BITS {
-            // バッファから飛び出る場合は、末尾まで読んだ後に再度先頭から読みだす
-            let tail_bits = MP3_MAINDATA_BUFFER_SIZE_BITS - self.read_pos_bits;
-            let tail = breader.read_u32(tail_bits as u8).unwrap();
-            let remain_bits = nbits as u64 - tail_bits;
-            let mut head_reader = BitReader::new(&self.buffer);
-            let head = head_reader.read_u32(remain_bits as u8).unwrap();
-            self.read_pos_bits = remain_bits;
-            (tail << remain_bits) | head
-        } else {
-            let ret = breader.read_u32(nbits).unwrap();
-            self.read_pos_bits += nbits as u64;
-            ret
+        let mut ret = 0;
+        let mut remain_bits = nbits as u64;
+        while remain_bits > 0 {
+            // 読み込み位置のバイトから読める分だけ読み出す
+            let byte = self.buffer[(self.read_pos_bits / 8) as usize] as u32;
+            let offset = self.read_pos_bits % 8;
+            let num_read = min(8 - offset, remain_bits);
+            ret = (ret << num_read) | ((byte >> (8 - offset - num_read)) & ((1 << num_read) - 1));
+            remain_bits -= num_read;
+            // バッファから飛び出る場合は先頭から読み出す
+            self.read_pos_bits = (self.read_pos_bits + num_read) % MP3_MAINDATA_BUFFER_SIZE_BITS;
         }
+        ret
     }
 
     /// 次のバイト境界に合わせる
@@ -94,6 +88,6 @@ impl MP3MainDataBuffer {
 
     /// ビット単位でのシーク
     pub fn seek(&mut self, position: u64) {
-        self.read_pos_bits = position;
+        self.read_pos_bits = position % MP3_MAINDATA_BUFFER_SIZE_BITS;
     }
 }
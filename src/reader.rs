@@ -0,0 +1,204 @@
+//! std::io::Read/Seekからフレームを順次読み出してデコードするリーダ（stdフィーチャ）
+use crate::decoder::*;
+use crate::stream::*;
+use crate::types::*;
+
+use core::cmp::min;
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// 入力から一度に読み出すサイズ(byte)
+const MP3_READER_CHUNK_SIZE: usize = 4096;
+
+/// デコードしたフレーム
+pub struct MP3ReaderFrame {
+    /// フレームヘッダ
+    pub header: MP3FrameHeader,
+    /// チャンネル数
+    pub num_channels: usize,
+    /// チャンネルあたりのサンプル数
+    pub num_samples: usize,
+    /// デコードしたサンプル
+    pub samples: [[f32; MP3_NUM_SAMPLES_PER_FRAME]; MP3_MAX_NUM_CHANNELS],
+    /// 破損したフレームを隠蔽したか
+    pub concealed: bool,
+}
+
+/// 入力から必要な分だけ読み出してデコードするリーダ
+/// 使用するメモリは入力の長さによらず一定
+pub struct MP3Reader<R: Read + Seek> {
+    /// 入力
+    reader: R,
+    /// 入力の先頭位置
+    start_position: u64,
+    /// ストリーミングデコーダ
+    decoder: MP3StreamDecoder,
+    /// 入力から読み出したデータ
+    chunk: [u8; MP3_READER_CHUNK_SIZE],
+    /// 読み出したデータのうちデコーダに与えた位置
+    chunk_pos: usize,
+    /// 読み出したデータのサイズ
+    chunk_size: usize,
+    /// 入力の終端に達したか
+    end_of_input: bool,
+    /// 直近にデコードしたフレーム
+    frame: Option<MP3ReaderFrame>,
+    /// 直近のフレームのうち読み出し済みのサンプル数
+    frame_pos: usize,
+}
+
+/// デコードエラーを入出力エラーに変換
+fn to_io_error(error: MP3DecodeError) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+impl<R: Read + Seek> MP3Reader<R> {
+    /// リーダ生成 入力の現在位置をストリームの先頭とする
+    pub fn new(mut reader: R) -> io::Result<Self> {
+        let start_position = reader.stream_position()?;
+        Ok(Self {
+            reader,
+            start_position,
+            decoder: MP3StreamDecoder::new(),
+            chunk: [0; MP3_READER_CHUNK_SIZE],
+            chunk_pos: 0,
+            chunk_size: 0,
+            end_of_input: false,
+            frame: None,
+            frame_pos: 0,
+        })
+    }
+
+    /// フレームデコーダの参照（デコードオプションの設定に使用）
+    pub fn decoder_mut(&mut self) -> &mut MP3Decoder {
+        self.decoder.decoder_mut()
+    }
+
+    /// 入力を取り出す
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// ストリームの先頭に戻る（デコードオプションは保持）
+    pub fn rewind(&mut self) -> io::Result<()> {
+        self.reader.seek(SeekFrom::Start(self.start_position))?;
+        self.decoder.reset();
+        self.chunk_pos = 0;
+        self.chunk_size = 0;
+        self.end_of_input = false;
+        self.frame = None;
+        self.frame_pos = 0;
+        Ok(())
+    }
+
+    /// 次のフレームをデコード（終端に達していればfalse）
+    fn decode_next_frame(&mut self) -> io::Result<bool> {
+        let mut samples = [[0.0f32; MP3_NUM_SAMPLES_PER_FRAME]; MP3_MAX_NUM_CHANNELS];
+        loop {
+            match self.decoder.decode(&mut samples) {
+                Ok((header, concealed)) => {
+                    let num_channels = match header.channel_mode {
+                        MP3ChannelMode::Monoral => 1,
+                        _ => 2,
+                    };
+                    self.frame = Some(MP3ReaderFrame {
+                        num_channels,
+                        num_samples: get_num_samples_per_frame(&header),
+                        header,
+                        samples,
+                        concealed,
+                    });
+                    self.frame_pos = 0;
+                    return Ok(true);
+                }
+                Err(MP3DecodeError::NeedMoreData) if !self.end_of_input => {
+                    // 与え切ったら入力から読み出す
+                    if self.chunk_pos == self.chunk_size {
+                        self.chunk_size = self.reader.read(&mut self.chunk)?;
+                        self.chunk_pos = 0;
+                        if self.chunk_size == 0 {
+                            self.end_of_input = true;
+                            self.decoder.flush();
+                            continue;
+                        }
+                    }
+                    self.chunk_pos += self
+                        .decoder
+                        .feed(&self.chunk[self.chunk_pos..self.chunk_size]);
+                }
+                Err(MP3DecodeError::NeedMoreData | MP3DecodeError::EndOfStream) => {
+                    self.frame = None;
+                    return Ok(false);
+                }
+                Err(e) => return Err(to_io_error(e)),
+            }
+        }
+    }
+
+    /// インターリーブしたサンプルを読み出し、書き込んだサンプル数（全チャンネルの合計）を返す
+    /// 出力はフレーム単位ではなくチャンネル数単位で埋める 0を返したら終端に達している
+    pub fn read_samples(&mut self, output: &mut [f32]) -> io::Result<usize> {
+        self.read_interleaved(output, |x| x)
+    }
+
+    /// インターリーブした16bit整数のサンプルを読み出し、書き込んだサンプル数（全チャンネルの合計）を返す
+    /// 0を返したら終端に達している
+    pub fn read_interleaved_i16(&mut self, output: &mut [i16]) -> io::Result<usize> {
+        const AMPLITUDE: f32 = i16::MAX as f32;
+        self.read_interleaved(output, |x| (x * AMPLITUDE).round() as i16)
+    }
+
+    /// 変換しながらインターリーブしたサンプルを読み出し
+    fn read_interleaved<T>(
+        &mut self,
+        output: &mut [T],
+        convert: impl Fn(f32) -> T,
+    ) -> io::Result<usize> {
+        let mut num_written = 0;
+        loop {
+            // 読み出し済みであれば次のフレームをデコード
+            if self
+                .frame
+                .as_ref()
+                .is_none_or(|frame| self.frame_pos >= frame.num_samples)
+                && !self.decode_next_frame()?
+            {
+                return Ok(num_written);
+            }
+            let Some(frame) = &self.frame else {
+                return Ok(num_written);
+            };
+
+            // 出力に収まる分だけ書き込む
+            let num_channels = frame.num_channels;
+            let num_copy = min(
+                frame.num_samples - self.frame_pos,
+                (output.len() - num_written) / num_channels,
+            );
+            if num_copy == 0 {
+                return Ok(num_written);
+            }
+            for (smpl, out) in output[num_written..num_written + num_copy * num_channels]
+                .chunks_exact_mut(num_channels)
+                .enumerate()
+            {
+                for (ch, out) in out.iter_mut().enumerate() {
+                    *out = convert(frame.samples[ch][self.frame_pos + smpl]);
+                }
+            }
+            self.frame_pos += num_copy;
+            num_written += num_copy * num_channels;
+        }
+    }
+}
+
+impl<R: Read + Seek> Iterator for MP3Reader<R> {
+    type Item = io::Result<MP3ReaderFrame>;
+
+    /// 次のフレームをデコード（read_samplesで読み残したサンプルは読み捨てる）
+    fn next(&mut self) -> Option<io::Result<MP3ReaderFrame>> {
+        match self.decode_next_frame() {
+            Ok(_) => self.frame.take().map(Ok),
+            Err(e) => Some(Err(e)),
+        }
+    }
+}
@@ -0,0 +1,46 @@
+use crate::types::*;
+
+/// ReplayGainの出力倍率を計算
+/// クリッピング防止が有効な場合はピーク振幅が1.0を超えないように倍率を抑える
+pub fn get_replay_gain_scale(
+    replay_gain: &MP3ReplayGain,
+    mode: MP3ReplayGainMode,
+    prevent_clipping: bool,
+) -> f32 {
+    let track = (replay_gain.track_gain, replay_gain.track_peak);
+    let album = (replay_gain.album_gain, replay_gain.album_peak);
+    let (gain, peak) = match mode {
+        MP3ReplayGainMode::Disabled => return 1.0,
+        MP3ReplayGainMode::Track if track.0.is_some() => track,
+        MP3ReplayGainMode::Track => album,
+        MP3ReplayGainMode::Album if album.0.is_some() => album,
+        MP3ReplayGainMode::Album => track,
+    };
+    let Some(gain) = gain else {
+        return 1.0;
+    };
+
+    let mut scale = 10.0f32.powf(gain / 20.0);
+    if prevent_clipping
+        && let Some(peak) = peak
+        && peak > 0.0
+        && scale * peak > 1.0
+    {
+        scale = 1.0 / peak;
+    }
+    scale
+}
+
+/// ゲインの適用
+pub fn mp3_apply_gain(
+    buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    num_channels: usize,
+    num_samples: usize,
+    scale: f32,
+) {
+    for ch_buffer in buffer.iter_mut().take(num_channels) {
+        for sample in ch_buffer[..num_samples].iter_mut() {
+            *sample *= scale;
+        }
+    }
+}
@@ -0,0 +1,188 @@
+//! シークインデックスの作成・読み込み
+//! インデックスは以下のバイナリ形式（数値はリトルエンディアン）で、読み込み時もそのまま参照する
+//! ヘッダ(32byte): 識別子"MP3S", バージョン(u16), エントリ間隔(u16, フレーム数), データサイズ(u64),
+//!                 先頭フレームのヘッダ(4byte), 総サンプル数(u64), エントリ数(u32)
+//! エントリ(18byte): フレーム位置(u64), サンプル位置(u64), maindata_begin(u16)
+use crate::decoder::*;
+use crate::frame::MP3FrameIterator;
+
+/// 識別子
+const MP3_SEEK_INDEX_SIGNATURE: &[u8; 4] = b"MP3S";
+/// フォーマットのバージョン
+const MP3_SEEK_INDEX_VERSION: u16 = 1;
+/// ヘッダのサイズ(byte)
+pub const MP3_SEEK_INDEX_HEADER_SIZE: usize = 32;
+/// エントリのサイズ(byte)
+pub const MP3_SEEK_INDEX_ENTRY_SIZE: usize = 18;
+
+/// シークインデックスのエントリ
+#[derive(PartialEq, Copy, Clone)]
+pub struct MP3SeekPoint {
+    /// データ先頭からのフレーム位置(byte)
+    pub offset: u64,
+    /// フレーム先頭のサンプル位置（エンコーダ遅延・デコーダ遅延を含む）
+    pub sample: u64,
+    /// フレームのmaindata_begin
+    pub maindata_begin: u16,
+}
+
+/// シークインデックス
+#[derive(PartialEq, Copy, Clone)]
+pub struct MP3SeekIndex<'a> {
+    /// エントリ間隔（フレーム数）
+    interval: usize,
+    /// 総サンプル数
+    num_samples: u64,
+    /// エントリ領域
+    entries: &'a [u8],
+}
+
+/// リトルエンディアンで16bit読み出し
+fn read_u16_le(data: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([data[pos], data[pos + 1]])
+}
+
+/// リトルエンディアンで32bit読み出し
+fn read_u32_le(data: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([data[pos], data[pos + 1], data[pos + 2], data[pos + 3]])
+}
+
+/// リトルエンディアンで64bit読み出し
+fn read_u64_le(data: &[u8], pos: usize) -> u64 {
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&data[pos..pos + 8]);
+    u64::from_le_bytes(bytes)
+}
+
+/// フレーム数とエントリ間隔からシークインデックスのサイズ(byte)を計算
+pub fn get_seek_index_size(num_frames: usize, interval: usize) -> usize {
+    MP3_SEEK_INDEX_HEADER_SIZE + num_frames.div_ceil(interval.max(1)) * MP3_SEEK_INDEX_ENTRY_SIZE
+}
+
+/// intervalフレームごとにエントリを記録したシークインデックスを作成し、書き込んだサイズを返す
+pub fn build_seek_index(
+    data: &[u8],
+    interval: usize,
+    output: &mut [u8],
+) -> Result<usize, MP3DecodeError> {
+    if interval == 0 || interval > u16::MAX as usize {
+        return Err(MP3DecodeError::InvalidFormat);
+    }
+    if output.len() < MP3_SEEK_INDEX_HEADER_SIZE {
+        return Err(MP3DecodeError::InsufficientBuffer);
+    }
+
+    // フレームを走査してエントリを記録
+    let mut first_offset = None;
+    let mut num_entries = 0;
+    let mut num_samples = 0;
+    for (count, frame) in MP3FrameIterator::new(data).enumerate() {
+        if count % interval == 0 {
+            let pos = MP3_SEEK_INDEX_HEADER_SIZE + num_entries * MP3_SEEK_INDEX_ENTRY_SIZE;
+            let Some(entry) = output.get_mut(pos..pos + MP3_SEEK_INDEX_ENTRY_SIZE) else {
+                return Err(MP3DecodeError::InsufficientBuffer);
+            };
+            entry[0..8].copy_from_slice(&(frame.offset as u64).to_le_bytes());
+            entry[8..16].copy_from_slice(&(num_samples as u64).to_le_bytes());
+            entry[16..18].copy_from_slice(&frame.side_info.maindata_begin.to_le_bytes());
+            num_entries += 1;
+        }
+        first_offset.get_or_insert(frame.offset);
+        num_samples += get_num_samples_per_frame(&frame.header);
+    }
+    let Some(first_offset) = first_offset else {
+        return Err(MP3DecodeError::EndOfStream);
+    };
+
+    // ヘッダ
+    let header = &mut output[..MP3_SEEK_INDEX_HEADER_SIZE];
+    header[0..4].copy_from_slice(MP3_SEEK_INDEX_SIGNATURE);
+    header[4..6].copy_from_slice(&MP3_SEEK_INDEX_VERSION.to_le_bytes());
+    header[6..8].copy_from_slice(&(interval as u16).to_le_bytes());
+    header[8..16].copy_from_slice(&(data.len() as u64).to_le_bytes());
+    header[16..20].copy_from_slice(&data[first_offset..first_offset + 4]);
+    header[20..28].copy_from_slice(&(num_samples as u64).to_le_bytes());
+    header[28..32].copy_from_slice(&(num_entries as u32).to_le_bytes());
+
+    Ok(MP3_SEEK_INDEX_HEADER_SIZE + num_entries * MP3_SEEK_INDEX_ENTRY_SIZE)
+}
+
+impl<'a> MP3SeekIndex<'a> {
+    /// シークインデックスの読み込み
+    /// データサイズと先頭フレームのヘッダが一致しなければ不正とする
+    pub fn load(index: &'a [u8], data: &[u8]) -> Result<Self, MP3DecodeError> {
+        if index.len() < MP3_SEEK_INDEX_HEADER_SIZE {
+            return Err(MP3DecodeError::InsufficientData);
+        }
+        if &index[0..4] != MP3_SEEK_INDEX_SIGNATURE
+            || read_u16_le(index, 4) != MP3_SEEK_INDEX_VERSION
+        {
+            return Err(MP3DecodeError::InvalidFormat);
+        }
+        let interval = read_u16_le(index, 6) as usize;
+        let num_entries = read_u32_le(index, 28) as usize;
+        let Some(entries) = num_entries
+            .checked_mul(MP3_SEEK_INDEX_ENTRY_SIZE)
+            .and_then(|size| index[MP3_SEEK_INDEX_HEADER_SIZE..].get(..size))
+        else {
+            return Err(MP3DecodeError::InsufficientData);
+        };
+        let seek_index = Self {
+            interval,
+            num_samples: read_u64_le(index, 20),
+            entries,
+        };
+
+        // 作成元のデータか検証
+        let first_offset = seek_index.entry(0).map(|entry| entry.offset as usize);
+        if interval == 0
+            || read_u64_le(index, 8) != data.len() as u64
+            || first_offset.and_then(|offset| data.get(offset..)?.get(..4)) != Some(&index[16..20])
+        {
+            return Err(MP3DecodeError::InvalidFormat);
+        }
+
+        Ok(seek_index)
+    }
+
+    /// エントリ間隔（フレーム数）
+    pub fn interval(&self) -> usize {
+        self.interval
+    }
+
+    /// 総サンプル数（エンコーダ遅延・デコーダ遅延を含む）
+    pub fn num_samples(&self) -> u64 {
+        self.num_samples
+    }
+
+    /// エントリ数
+    pub fn num_entries(&self) -> usize {
+        self.entries.len() / MP3_SEEK_INDEX_ENTRY_SIZE
+    }
+
+    /// エントリの取得
+    pub fn entry(&self, index: usize) -> Option<MP3SeekPoint> {
+        let entry = self
+            .entries
+            .get(index * MP3_SEEK_INDEX_ENTRY_SIZE..(index + 1) * MP3_SEEK_INDEX_ENTRY_SIZE)?;
+        Some(MP3SeekPoint {
+            offset: read_u64_le(entry, 0),
+            sample: read_u64_le(entry, 8),
+            maindata_begin: read_u16_le(entry, 16),
+        })
+    }
+
+    /// サンプル位置を含むフレーム以前で最も近いエントリの番号を取得
+    pub fn find_entry(&self, sample: usize) -> Option<usize> {
+        // サンプル位置が単調増加であることを利用して二分探索
+        let (mut low, mut high) = (0, self.num_entries());
+        while low < high {
+            let mid = (low + high) / 2;
+            match self.entry(mid) {
+                Some(entry) if entry.sample <= sample as u64 => low = mid + 1,
+                _ => high = mid,
+            }
+        }
+        low.checked_sub(1)
+    }
+}
@@ -0,0 +1,169 @@
+//! 任意の長さのバイト列を順次与えてデコードするストリーミングデコーダ
+use crate::decoder::*;
+use crate::id3v2::decode_id3v2_header;
+use crate::tag::get_trailing_tags_size;
+use crate::types::*;
+
+/// 内部バッファのサイズ(byte) フリーフォーマットの最大フレームと次のヘッダが収まる
+pub const MP3_STREAM_BUFFER_SIZE: usize = 8192;
+
+/// ストリーミングデコーダ
+pub struct MP3StreamDecoder {
+    /// フレームデコーダ
+    decoder: MP3Decoder,
+    /// 未デコードのデータを保持するバッファ
+    buffer: [u8; MP3_STREAM_BUFFER_SIZE],
+    /// バッファ内のデータサイズ
+    buffer_size: usize,
+    /// 読み捨てる残りのバイト数（バッファに収まらないID3v2タグ）
+    skip_size: usize,
+    /// ストリーム終端に達したか
+    end_of_stream: bool,
+}
+
+impl Default for MP3StreamDecoder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MP3StreamDecoder {
+    /// ストリーミングデコーダ生成
+    pub fn new() -> Self {
+        Self {
+            decoder: MP3Decoder::new(),
+            buffer: [0; MP3_STREAM_BUFFER_SIZE],
+            buffer_size: 0,
+            skip_size: 0,
+            end_of_stream: false,
+        }
+    }
+
+    /// フレームデコーダの参照（デコードオプションの設定に使用）
+    pub fn decoder_mut(&mut self) -> &mut MP3Decoder {
+        &mut self.decoder
+    }
+
+    /// デコーダ内部状態リセット（デコードオプションは保持）
+    pub fn reset(&mut self) {
+        self.decoder.reset();
+        self.buffer_size = 0;
+        self.skip_size = 0;
+        self.end_of_stream = false;
+    }
+
+    /// データを与える 内部バッファに取り込んだバイト数を返す
+    /// 返り値がdataの長さ未満であれば、decodeでフレームを取り出してから残りを与える
+    pub fn feed(&mut self, data: &[u8]) -> usize {
+        // 読み捨て中のタグは取り込まずに消費
+        let num_skip = self.skip_size.min(data.len());
+        self.skip_size -= num_skip;
+        let data = &data[num_skip..];
+
+        let num_copy = data.len().min(MP3_STREAM_BUFFER_SIZE - self.buffer_size);
+        self.buffer[self.buffer_size..self.buffer_size + num_copy]
+            .copy_from_slice(&data[..num_copy]);
+        self.buffer_size += num_copy;
+        num_skip + num_copy
+    }
+
+    /// ストリーム終端を通知
+    /// 以降は末尾のフレームを取り出せるようになり、全て取り出すとEndOfStreamを返す
+    pub fn flush(&mut self) {
+        self.end_of_stream = true;
+        // 末尾のタグを除く
+        self.buffer_size -= get_trailing_tags_size(&self.buffer[..self.buffer_size]);
+    }
+
+    /// バッファ先頭からバイトを取り除く
+    fn consume(&mut self, size: usize) {
+        self.buffer.copy_within(size..self.buffer_size, 0);
+        self.buffer_size -= size;
+    }
+
+    /// 1フレームデコードし、(フレームヘッダ, 破損したフレームを隠蔽したか)を返す
+    /// フレームが揃っていなければNeedMoreDataを返す
+    pub fn decode(
+        &mut self,
+        buffer: &mut [[f32; MP3_NUM_SAMPLES_PER_FRAME]],
+    ) -> Result<(MP3FrameHeader, bool), MP3DecodeError> {
+        // データが不足している時のエラー
+        let need_more_data = |end_of_stream: bool| {
+            if end_of_stream {
+                MP3DecodeError::EndOfStream
+            } else {
+                MP3DecodeError::NeedMoreData
+            }
+        };
+
+        loop {
+            let data = &self.buffer[..self.buffer_size];
+            let buffer_full = self.buffer_size == MP3_STREAM_BUFFER_SIZE;
+
+            // ID3v2タグを読み飛ばす
+            match decode_id3v2_header(data) {
+                Ok(header) => {
+                    let tag_size = header.tag_size();
+                    let num_consume = tag_size.min(self.buffer_size);
+                    self.skip_size = tag_size - num_consume;
+                    self.consume(num_consume);
+                    continue;
+                }
+                Err(MP3DecodeError::InsufficientData)
+                    if data.starts_with(&b"ID3"[..data.len().min(3)]) && !self.end_of_stream =>
+                {
+                    return Err(MP3DecodeError::NeedMoreData);
+                }
+                _ => {}
+            }
+
+            // 末尾のタグの可能性があれば終端の通知を待つ
+            if !self.end_of_stream
+                && !buffer_full
+                && [&b"TAG"[..], b"APETAGEX", b"LYRICSBEGIN"]
+                    .iter()
+                    .any(|signature| data.starts_with(signature))
+            {
+                return Err(MP3DecodeError::NeedMoreData);
+            }
+
+            // 同期コードまで読み捨てる（見つからなければ同期コードの一部になりうる末尾2byteを残す）
+            let Some(sync_pos) = find_sync_code(data) else {
+                self.decoder.invalidate_maindata();
+                self.consume(self.buffer_size.saturating_sub(2));
+                return Err(need_more_data(self.end_of_stream));
+            };
+            if sync_pos > 0 {
+                // 読み捨てたデータ以前のメインデータは参照できない
+                self.decoder.invalidate_maindata();
+                self.consume(sync_pos);
+                continue;
+            }
+
+            // フレームのサイズを確定
+            let (frame_size, is_tag) = match self
+                .decoder
+                .measure_stream_frame(data, self.end_of_stream || buffer_full)
+            {
+                Ok(Some(result)) => result,
+                Ok(None) => return Err(MP3DecodeError::NeedMoreData),
+                Err(_) => {
+                    // 同期コードの誤検出とみなして次の同期コードを探す
+                    self.decoder.invalidate_maindata();
+                    self.consume(1);
+                    continue;
+                }
+            };
+
+            // VBRタグのフレームは音声としてデコードしない
+            if is_tag {
+                self.consume(frame_size);
+                continue;
+            }
+
+            let result = self.decoder.decode_frame_at(&data[..frame_size], buffer);
+            self.consume(frame_size);
+            return result.map(|(_, header, _, concealed)| (header, concealed));
+        }
+    }
+}
@@ -0,0 +1,351 @@
+//! ID3v2, ID3v1, APE, Lyrics3v2タグの統合的な取得
+use crate::ape::*;
+use crate::id3v1::*;
+use crate::id3v2::*;
+use crate::lyrics3::*;
+use crate::types::MP3ReplayGain;
+use core::cmp::{max, min};
+
+/// 統合するID3v2タグの最大数
+pub const MP3_MAX_NUM_ID3V2_TAGS: usize = 4;
+
+/// タグのテキスト（ID3v2以外のタグもエンコーディング付きのバイト列で表す）
+pub type MP3TagText<'a> = ID3v2Text<'a>;
+
+/// タグ共通の項目
+#[derive(PartialEq, Copy, Clone)]
+pub enum MP3TagField {
+    /// タイトル
+    Title,
+    /// アーティスト
+    Artist,
+    /// アルバム
+    Album,
+    /// 年
+    Year,
+    /// コメント
+    Comment,
+    /// トラック番号
+    Track,
+    /// ジャンル
+    Genre,
+}
+
+/// ファイルに含まれるタグ
+#[derive(Clone)]
+pub struct MP3Tags<'a> {
+    /// ID3v2タグ（先頭の連続したタグ, SEEKフレームが指すタグ, 末尾に追加されたタグの順）
+    pub id3v2: [Option<ID3v2Tag<'a>>; MP3_MAX_NUM_ID3V2_TAGS],
+    /// 末尾のAPEタグ
+    pub ape: Option<APETag<'a>>,
+    /// 末尾のLyrics3v2タグ
+    pub lyrics3: Option<Lyrics3Tag<'a>>,
+    /// 末尾のID3v1タグ
+    pub id3v1: Option<ID3v1Tag<'a>>,
+}
+
+/// 空でなければテキストを返す
+fn non_empty(text: MP3TagText<'_>) -> Option<MP3TagText<'_>> {
+    if text.data.is_empty() {
+        None
+    } else {
+        Some(text)
+    }
+}
+
+/// ISO-8859-1のテキストを作成
+fn latin1_text(data: &[u8]) -> Option<MP3TagText<'_>> {
+    non_empty(MP3TagText {
+        encoding: ID3v2TextEncoding::Latin1,
+        data,
+    })
+}
+
+/// 大文字小文字を区別せずにテキストを比較
+fn text_eq_ignore_case(text: &MP3TagText<'_>, s: &str) -> bool {
+    let mut chars = text.chars();
+    s.chars()
+        .all(|c| chars.next().is_some_and(|t| t.eq_ignore_ascii_case(&c)))
+        && chars.next().is_none()
+}
+
+/// "-6.20 dB"や"0.988"のような数値のテキストを読み出し
+fn parse_number_text(text: &MP3TagText<'_>) -> Option<f32> {
+    // 数値部分のみを固定長バッファに集める
+    let mut buffer = [0u8; 32];
+    let mut size = 0;
+    for c in text.chars().skip_while(|c| c.is_whitespace()) {
+        if !(c.is_ascii_digit() || c == '+' || c == '-' || c == '.') || size >= buffer.len() {
+            break;
+        }
+        buffer[size] = c as u8;
+        size += 1;
+    }
+    core::str::from_utf8(&buffer[..size]).ok()?.parse().ok()
+}
+
+/// ReplayGainのキーに対応する値を設定
+fn set_replay_gain_value(
+    replay_gain: &mut MP3ReplayGain,
+    key: &MP3TagText<'_>,
+    value: &MP3TagText<'_>,
+) {
+    let field = if text_eq_ignore_case(key, "REPLAYGAIN_TRACK_GAIN") {
+        &mut replay_gain.track_gain
+    } else if text_eq_ignore_case(key, "REPLAYGAIN_TRACK_PEAK") {
+        &mut replay_gain.track_peak
+    } else if text_eq_ignore_case(key, "REPLAYGAIN_ALBUM_GAIN") {
+        &mut replay_gain.album_gain
+    } else if text_eq_ignore_case(key, "REPLAYGAIN_ALBUM_PEAK") {
+        &mut replay_gain.album_peak
+    } else {
+        return;
+    };
+    if let Some(number) = parse_number_text(value) {
+        *field = Some(number);
+    }
+}
+
+/// RVA2フレームのマスターボリュームの調整量(dB)とピーク振幅を設定
+fn set_rva2_value(replay_gain: &mut MP3ReplayGain, data: &[u8]) {
+    // 識別子("track"または"album"), チャンネルごとの調整量の順
+    let Some(end) = data.iter().position(|&c| c == 0) else {
+        return;
+    };
+    let identification = latin1_text(&data[..end]);
+    let (gain, peak) = match identification {
+        Some(text) if text_eq_ignore_case(&text, "track") => {
+            (&mut replay_gain.track_gain, &mut replay_gain.track_peak)
+        }
+        Some(text) if text_eq_ignore_case(&text, "album") => {
+            (&mut replay_gain.album_gain, &mut replay_gain.album_peak)
+        }
+        _ => return,
+    };
+
+    // チャンネル種別(1byte), 調整量(16bit, 1/512dB単位), ピークのビット数(1byte), ピーク
+    let mut pos = end + 1;
+    while let Some(&[channel_type, adjust_hi, adjust_lo, peak_bits]) = data.get(pos..pos + 4) {
+        let peak_size = (peak_bits as usize).div_ceil(8);
+        let Some(peak_bytes) = data.get(pos + 4..pos + 4 + peak_size) else {
+            return;
+        };
+        // マスターボリュームのみ使用
+        if channel_type == 1 {
+            *gain = Some(i16::from_be_bytes([adjust_hi, adjust_lo]) as f32 / 512.0);
+            if (1..=32).contains(&peak_bits) {
+                let value = peak_bytes
+                    .iter()
+                    .fold(0u64, |acc, &b| (acc << 8) | b as u64);
+                *peak = Some(value as f32 / (1u64 << (peak_bits - 1)) as f32);
+            }
+            return;
+        }
+        pos += 4 + peak_size;
+    }
+}
+
+impl<'a> MP3Tags<'a> {
+    /// ReplayGain情報を取得（ID3v2のTXXX/RVA2フレーム, APEタグの順に優先）
+    pub fn replay_gain(&self) -> MP3ReplayGain {
+        let mut replay_gain = MP3ReplayGain {
+            track_gain: None,
+            track_peak: None,
+            album_gain: None,
+            album_peak: None,
+        };
+
+        // 優先度の低い順に上書き
+        if let Some(ape) = self.ape {
+            for item in ape
+                .items()
+                .filter(|item| item.item_type == APEItemType::Text)
+            {
+                let text = |data| MP3TagText {
+                    encoding: ID3v2TextEncoding::Utf8,
+                    data,
+                };
+                set_replay_gain_value(&mut replay_gain, &text(item.key), &text(item.value));
+            }
+        }
+        for tag in self.id3v2.iter().flatten() {
+            for raw in tag.frames() {
+                match raw.decode(&mut []) {
+                    Ok(ID3v2Frame::UserText { description, value }) => {
+                        set_replay_gain_value(&mut replay_gain, &description, &value)
+                    }
+                    Ok(ID3v2Frame::Unknown { id, data }) if &id == b"RVA2" => {
+                        set_rva2_value(&mut replay_gain, data)
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        replay_gain
+    }
+
+    /// 項目の値を取得（ID3v2, APE, Lyrics3v2, ID3v1の順で最初に見つかったもの）
+    pub fn get(&self, field: MP3TagField) -> Option<MP3TagText<'a>> {
+        self.get_id3v2(field)
+            .or_else(|| self.get_ape(field))
+            .or_else(|| self.get_lyrics3(field))
+            .or_else(|| self.get_id3v1(field))
+    }
+
+    /// ID3v2タグから項目の値を取得（展開が必要なフレームは対象外）
+    fn get_id3v2(&self, field: MP3TagField) -> Option<MP3TagText<'a>> {
+        let ids: &[&[u8; 4]] = match field {
+            MP3TagField::Title => &[b"TIT2"],
+            MP3TagField::Artist => &[b"TPE1"],
+            MP3TagField::Album => &[b"TALB"],
+            MP3TagField::Year => &[b"TDRC", b"TYER"],
+            MP3TagField::Comment => &[b"COMM"],
+            MP3TagField::Track => &[b"TRCK"],
+            MP3TagField::Genre => &[b"TCON"],
+        };
+        ids.iter().find_map(|id| {
+            let frame = self.find_id3v2_frame(id)?.decode(&mut []).ok()?;
+            match frame {
+                ID3v2Frame::Text { text, .. } | ID3v2Frame::Comment { text, .. } => non_empty(text),
+                _ => None,
+            }
+        })
+    }
+
+    /// 全てのID3v2タグから指定したIDのフレームを取得（後ろのタグの内容で上書きする）
+    pub fn find_id3v2_frame(&self, id: &[u8; 4]) -> Option<ID3v2RawFrame<'a>> {
+        self.id3v2
+            .iter()
+            .rev()
+            .flatten()
+            .find_map(|tag| tag.find_frame(id))
+    }
+
+    /// APEタグから項目の値を取得
+    fn get_ape(&self, field: MP3TagField) -> Option<MP3TagText<'a>> {
+        let key = match field {
+            MP3TagField::Title => "Title",
+            MP3TagField::Artist => "Artist",
+            MP3TagField::Album => "Album",
+            MP3TagField::Year => "Year",
+            MP3TagField::Comment => "Comment",
+            MP3TagField::Track => "Track",
+            MP3TagField::Genre => "Genre",
+        };
+        let item = self.ape?.find_item(key)?;
+        if item.item_type != APEItemType::Text {
+            return None;
+        }
+        non_empty(MP3TagText {
+            encoding: ID3v2TextEncoding::Utf8,
+            data: item.value,
+        })
+    }
+
+    /// Lyrics3v2タグから項目の値を取得
+    fn get_lyrics3(&self, field: MP3TagField) -> Option<MP3TagText<'a>> {
+        let id = match field {
+            MP3TagField::Title => b"ETT",
+            MP3TagField::Artist => b"EAR",
+            MP3TagField::Album => b"EAL",
+            _ => return None,
+        };
+        latin1_text(self.lyrics3?.find_field(id)?.value)
+    }
+
+    /// ID3v1タグから項目の値を取得
+    fn get_id3v1(&self, field: MP3TagField) -> Option<MP3TagText<'a>> {
+        let tag = self.id3v1?;
+        match field {
+            MP3TagField::Title => latin1_text(tag.title),
+            MP3TagField::Artist => latin1_text(tag.artist),
+            MP3TagField::Album => latin1_text(tag.album),
+            MP3TagField::Year => latin1_text(tag.year),
+            MP3TagField::Comment => latin1_text(tag.comment),
+            // トラック番号は数値のためテキストとしては返さない
+            MP3TagField::Track => None,
+            MP3TagField::Genre => latin1_text(tag.genre_name()?.as_bytes()),
+        }
+    }
+}
+
+/// 先頭の連続したID3v2タグの合計サイズを取得
+pub fn get_leading_tags_size(data: &[u8]) -> usize {
+    let mut pos = 0;
+    while let Ok(header) = decode_id3v2_header(&data[pos..]) {
+        pos = min(pos + header.tag_size(), data.len());
+    }
+    pos
+}
+
+/// 末尾のタグ(ID3v2, APE, Lyrics3v2, TAG+, ID3v1)の合計サイズを取得
+pub fn get_trailing_tags_size(data: &[u8]) -> usize {
+    let mut end = data.len() - get_id3v1_tag_size(data);
+    // ID3v2タグ, APEタグ, Lyrics3v2タグはどの順に置かれてもよい
+    loop {
+        let size = max(
+            get_appended_id3v2_tag_size(&data[..end]),
+            max(
+                get_ape_tag_size(&data[..end]),
+                get_lyrics3_tag_size(&data[..end]),
+            ),
+        );
+        if size == 0 {
+            break;
+        }
+        end -= size;
+    }
+    data.len() - end
+}
+
+/// ファイルに含まれるタグをデコード
+pub fn decode_tags(data: &[u8]) -> MP3Tags<'_> {
+    let mut tags = MP3Tags {
+        id3v2: [const { None }; MP3_MAX_NUM_ID3V2_TAGS],
+        ape: None,
+        lyrics3: None,
+        id3v1: decode_id3v1_tag(data),
+    };
+
+    // 先頭の連続したタグと、SEEKフレームが指すタグを探索
+    let mut num_id3v2_tags = 0;
+    let mut pos = 0;
+    while num_id3v2_tags < MP3_MAX_NUM_ID3V2_TAGS
+        && let Some(Ok(tag)) = data.get(pos..).map(decode_id3v2_tag)
+    {
+        pos += tag.header.tag_size() + tag.seek_offset().unwrap_or(0) as usize;
+        tags.id3v2[num_id3v2_tags] = Some(tag);
+        num_id3v2_tags += 1;
+    }
+
+    // 末尾から順にタグを探索
+    let mut end = data.len() - get_id3v1_tag_size(data);
+    loop {
+        let data = &data[..end];
+        let size = get_appended_id3v2_tag_size(data);
+        if size > 0 {
+            if num_id3v2_tags < MP3_MAX_NUM_ID3V2_TAGS {
+                tags.id3v2[num_id3v2_tags] = decode_id3v2_tag(&data[end - size..]).ok();
+                num_id3v2_tags += 1;
+            }
+            end -= size;
+            continue;
+        }
+        let size = get_ape_tag_size(data);
+        if size > 0 {
+            tags.ape = tags.ape.or(decode_ape_tag(data));
+            end -= size;
+            continue;
+        }
+        let size = get_lyrics3_tag_size(data);
+        if size > 0 {
+            tags.lyrics3 = tags.lyrics3.or(decode_lyrics3_tag(data));
+            end -= size;
+            continue;
+        }
+        break;
+    }
+
+    tags
+}
@@ -2,8 +2,12 @@
 pub const MP3_MAX_NUM_CHANNELS: usize = 2;
 /// フレーム当たりサンプル数
 pub const MP3_NUM_SAMPLES_PER_FRAME: usize = 1152;
+/// Layer1のフレーム当たりサンプル数
+pub const MP3_NUM_SAMPLES_PER_FRAME_LAYER1: usize = 384;
 /// フレームあたりグラニュール数
 pub const MP3_NUM_GRANLES_PER_FRAME: usize = 2;
+/// MPEG2(LSF)のフレームあたりグラニュール数
+pub const MP3_NUM_GRANLES_PER_FRAME_LSF: usize = 1;
 /// グラニュール当たりサンプル数
 pub const MP3_NUM_SAMPLES_PER_GRANULE: usize =
     MP3_NUM_SAMPLES_PER_FRAME / MP3_NUM_GRANLES_PER_FRAME;
@@ -13,11 +17,14 @@ pub const MP3_NUM_CRITICAL_BANDS_LONG: usize = 23;
 pub const MP3_NUM_CRITICAL_BANDS_SHORT: usize = 13;
 
 /// MPEGバージョン
+#[derive(PartialEq, Copy, Clone)]
 pub enum MPEGVersion {
     /// MPEG1
-    MPEGVersion1 = 1,
+    MPEGVersion1 = 3,
     /// MPEG2(LSF, Low Sampling Frequency)
-    MPEGVersion2 = 0,
+    MPEGVersion2 = 2,
+    /// MPEG2.5(非公式のLSF拡張)
+    MPEGVersion25 = 0,
 }
 
 /// ブロックタイプ
@@ -52,9 +59,12 @@ pub enum MP3ExtChannelMode {
     MSStereo = 1,
     /// なにもしない
     NONE = 2,
+    /// MSステレオとインテンシティステレオの併用
+    MSIntensityStereo = 3,
 }
 
 /// レイヤー
+#[derive(PartialEq, Copy, Clone)]
 pub enum MP3Layer {
     /// Layer1
     Layer1 = 1,
@@ -70,6 +80,12 @@ pub enum MP3Layer {
 pub enum MP3BitRate {
     /// 0kbps
     Kbps0 = 0,
+    /// 8kbps
+    Kbps8 = 8_000,
+    /// 16kbps
+    Kbps16 = 16_000,
+    /// 24kbps
+    Kbps24 = 24_000,
     /// 32kbps
     Kbps32 = 32_000,
     /// 40kbps
@@ -88,16 +104,30 @@ pub enum MP3BitRate {
     Kbps112 = 112_000,
     /// 128kbps
     Kbps128 = 128_000,
+    /// 144kbps
+    Kbps144 = 144_000,
     /// 160kbps
     Kbps160 = 160_000,
+    /// 176kbps
+    Kbps176 = 176_000,
     /// 192kbps
     Kbps192 = 192_000,
     /// 224kbps
     Kbps224 = 224_000,
     /// 256kbps
     Kbps256 = 256_000,
+    /// 288kbps
+    Kbps288 = 288_000,
     /// 320kbps
     Kbps320 = 320_000,
+    /// 352kbps
+    Kbps352 = 352_000,
+    /// 384kbps
+    Kbps384 = 384_000,
+    /// 416kbps
+    Kbps416 = 416_000,
+    /// 448kbps
+    Kbps448 = 448_000,
 }
 
 /// サンプリングレート
@@ -110,6 +140,18 @@ pub enum MP3SamplingRate {
     Hz48000 = 48000,
     /// 32.0kHz
     Hz32000 = 32000,
+    /// 22.05kHz
+    Hz22050 = 22050,
+    /// 24.0kHz
+    Hz24000 = 24000,
+    /// 16.0kHz
+    Hz16000 = 16000,
+    /// 11.025kHz
+    Hz11025 = 11025,
+    /// 12.0kHz
+    Hz12000 = 12000,
+    /// 8.0kHz
+    Hz8000 = 8000,
 }
 
 /// エンファシスモード
@@ -124,6 +166,65 @@ pub enum MP3EmphasisMode {
     CCITTJ17 = 3,
 }
 
+/// CRC不一致フレームの扱い
+#[derive(PartialEq, Copy, Clone)]
+pub enum MP3CrcPolicy {
+    /// 検査しない
+    Ignore,
+    /// エラーを返す
+    Reject,
+    /// 隠蔽した信号（MP3ConcealmentModeで指定）を出力してデコードを継続
+    Conceal,
+}
+
+/// 破損・欠落フレームの隠蔽方法
+#[derive(PartialEq, Copy, Clone)]
+pub enum MP3ConcealmentMode {
+    /// 無音を挿入
+    Silence,
+    /// 直前のフレームを繰り返す
+    Repeat,
+    /// 直前のフレームを減衰させながら繰り返す
+    FadeOut,
+}
+
+/// デュアルチャンネルの出力チャンネル選択
+#[derive(PartialEq, Copy, Clone)]
+pub enum MP3DualChannelOutput {
+    /// 両チャンネルをそのまま出力
+    Both,
+    /// チャンネルAのみ出力（2チャンネル目は無音）
+    ChannelA,
+    /// チャンネルBのみ出力（2チャンネル目は無音）
+    ChannelB,
+    /// チャンネルAを両チャンネルに出力
+    MirrorA,
+}
+
+/// ReplayGainの適用モード
+#[derive(PartialEq, Copy, Clone)]
+pub enum MP3ReplayGainMode {
+    /// 適用しない
+    Disabled,
+    /// トラックゲインを適用（なければアルバムゲイン）
+    Track,
+    /// アルバムゲインを適用（なければトラックゲイン）
+    Album,
+}
+
+/// ReplayGain情報
+#[derive(PartialEq, Copy, Clone)]
+pub struct MP3ReplayGain {
+    /// トラックゲイン(dB)
+    pub track_gain: Option<f32>,
+    /// トラックのピーク振幅（1.0で最大振幅）
+    pub track_peak: Option<f32>,
+    /// アルバムゲイン(dB)
+    pub album_gain: Option<f32>,
+    /// アルバムのピーク振幅（1.0で最大振幅）
+    pub album_peak: Option<f32>,
+}
+
 /// フレームヘッダ情報
 pub struct MP3FrameHeader {
     /// バージョン
@@ -160,8 +261,8 @@ pub struct MP3GranuleInformation {
     pub big_values: u16,
     /// 量子化ステップを表すパラメータ(8bit)
     pub global_gain: u8,
-    /// スケールファクタのビット幅のテーブルインデックス(4bit)
-    pub scalefac_compress: u8,
+    /// スケールファクタのビット幅のテーブルインデックス(MPEG1: 4bit, MPEG2: 9bit)
+    pub scalefac_compress: u16,
     /// 1bit normalなら0, normalでないなら1
     pub window_switching_flag: bool,
     /// 窓関数タイプ
@@ -188,20 +289,94 @@ pub struct MP3GranuleInformation {
 pub struct MP3ChannelSideInformation {
     /// ScaleFector Selection Information 4グループ(0-5,6-10,11-15,16-20)で、2グラニュールで同一のスケールファクタを使用しているか？ 1bit x4
     pub scfsi: [bool; 4],
-    /// グラニュール情報
+    /// グラニュール情報（MPEG2では先頭の1グラニュールのみ使用）
     pub gr: [MP3GranuleInformation; MP3_NUM_GRANLES_PER_FRAME],
 }
 
 /// サイドインフォメーション
 pub struct MP3SideInformation {
-    /// メインデータが始まるまでの負のオフセットバイト 9bit
+    /// メインデータが始まるまでの負のオフセットバイト 9bit(MPEG2: 8bit)
     pub maindata_begin: u16,
-    /// ステレオであれば3bit, モノラルであれば5bit ユーザ向けのビット(ISOは未使用)
+    /// ステレオであれば3bit, モノラルであれば5bit(MPEG2: ステレオ2bit, モノラル1bit) ユーザ向けのビット(ISOは未使用)
     pub private_bits: u8,
     /// チャンネルあたりのサイドインフォメーション
     pub ch: [MP3ChannelSideInformation; MP3_MAX_NUM_CHANNELS],
 }
 
+/// LAMEタグ情報
+#[derive(PartialEq, Copy, Clone)]
+pub struct MP3LameInformation {
+    /// エンコーダのバージョン文字列(例: "LAME3.100")
+    pub encoder: [u8; 9],
+    /// タグのリビジョン
+    pub revision: u8,
+    /// VBRの方式
+    pub vbr_method: u8,
+    /// ローパスフィルタの周波数(Hz)
+    pub lowpass: u32,
+    /// ピーク振幅（1.0で最大振幅）
+    pub peak: f32,
+    /// トラックゲイン(dB)（Radio ReplayGain）
+    pub track_gain: Option<f32>,
+    /// アルバムゲイン(dB)（Audiophile ReplayGain）
+    pub album_gain: Option<f32>,
+    /// エンコーダ遅延サンプル数
+    pub encoder_delay: u16,
+    /// 末尾のパディングサンプル数
+    pub padding: u16,
+    /// タグのフレームを含む音楽データのバイト数
+    pub music_length: u32,
+    /// 音楽データのCRC-16
+    pub music_crc: u16,
+    /// タグのCRC-16が一致したか
+    pub crc_valid: bool,
+}
+
+/// Xing/Infoタグ情報
+#[derive(PartialEq, Copy, Clone)]
+pub struct MP3XingInformation {
+    /// VBRか（"Xing"ならばVBR, "Info"ならばCBR）
+    pub vbr: bool,
+    /// フレーム数（タグのフレームは含まない）
+    pub num_frames: Option<u32>,
+    /// バイト数（タグのフレームを含む）
+    pub num_bytes: Option<u32>,
+    /// シーク用の目次(TOC) 再生位置の百分率ごとのファイル位置(/256)
+    pub toc: Option<[u8; 100]>,
+    /// 品質指標（0:最良 - 100:最悪）
+    pub quality: Option<u32>,
+    /// LAMEタグ情報
+    pub lame: Option<MP3LameInformation>,
+}
+
+/// VBRIタグ情報（Fraunhofer社エンコーダが出力）
+#[derive(PartialEq, Copy, Clone)]
+pub struct MP3VbriInformation {
+    /// タグのバージョン
+    pub version: u16,
+    /// エンコーダ遅延
+    pub delay: u16,
+    /// 品質指標
+    pub quality: u16,
+    /// バイト数（タグのフレームを含む）
+    pub num_bytes: u32,
+    /// フレーム数（タグのフレームは含まない）
+    pub num_frames: u32,
+    /// TOCエントリのスケールファクタ
+    pub toc_scale: u16,
+    /// TOCエントリあたりのバイト数(1-4)
+    pub toc_entry_size: u16,
+    /// TOCエントリあたりのフレーム数
+    pub frames_per_toc_entry: u16,
+    /// TOCのエントリ数
+    pub num_toc_entries: usize,
+    /// シーク用の目次(TOC)のフレームヘッダ先頭からの位置(byte)
+    /// TOCはエントリごとの区間のバイト数（スケールファクタ適用前）で、mp3_read_vbri_toc_entryで読み出す
+    pub toc_offset: usize,
+    /// TOCのサイズ(byte)
+    pub toc_size: usize,
+}
+
 /// フォーマット情報
 #[derive(PartialEq)]
 pub struct MP3FormatInformation {
@@ -209,8 +384,20 @@ pub struct MP3FormatInformation {
     pub num_channels: usize,
     /// サンプル数
     pub num_samples: usize,
+    /// フレームあたりサンプル数
+    pub num_samples_per_frame: usize,
+    /// デュアルチャンネル（2つの独立した番組）か
+    pub dual_channel: bool,
     /// サンプリングレート
     pub sampling_rate: MP3SamplingRate,
     /// ビットレート
     pub bit_rate: MP3BitRate,
+    /// 平均ビットレート(bps)
+    pub average_bit_rate: usize,
+    /// VBRか
+    pub vbr: bool,
+    /// Xing/Infoタグ情報
+    pub xing: Option<MP3XingInformation>,
+    /// VBRIタグ情報
+    pub vbri: Option<MP3VbriInformation>,
 }
@@ -0,0 +1,176 @@
+use crate::crc::*;
+use crate::types::*;
+
+/// Xing/Infoタグのフレーム数フィールドの有無を示すフラグ
+const XING_FLAG_FRAMES: u32 = 0x1;
+/// Xing/Infoタグのバイト数フィールドの有無を示すフラグ
+const XING_FLAG_BYTES: u32 = 0x2;
+/// Xing/InfoタグのTOCフィールドの有無を示すフラグ
+const XING_FLAG_TOC: u32 = 0x4;
+/// Xing/Infoタグの品質指標フィールドの有無を示すフラグ
+const XING_FLAG_QUALITY: u32 = 0x8;
+/// TOCのサイズ(byte)
+const XING_TOC_SIZE: usize = 100;
+/// LAMEタグのサイズ(byte)
+const LAME_TAG_SIZE: usize = 36;
+/// LAMEタグのCRC計算範囲（フレーム先頭からのバイト数）
+const LAME_TAG_CRC_RANGE: usize = 190;
+
+/// VBRIタグの位置（フレームヘッダ先頭からのバイト数）
+const VBRI_TAG_OFFSET: usize = 36;
+/// VBRIタグのTOCを除いたサイズ(byte)
+const VBRI_HEADER_SIZE: usize = 26;
+
+/// ビッグエンディアンで16bit読み出し
+fn read_u16_be(data: &[u8], pos: usize) -> Option<u16> {
+    let bytes = data.get(pos..pos + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+/// ビッグエンディアンで32bit読み出し
+fn read_u32_be(data: &[u8], pos: usize) -> Option<u32> {
+    let bytes = data.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// ReplayGainフィールドのデコード 名前(3bit) 設定者(3bit) 符号(1bit) 値(9bit, 0.1dB単位)
+fn decode_replay_gain(field: u16) -> Option<f32> {
+    // 名前が未設定ならば無効
+    if (field >> 13) == 0 {
+        return None;
+    }
+    let gain = (field & 0x1FF) as f32 / 10.0;
+    Some(if (field & 0x200) != 0 { -gain } else { gain })
+}
+
+/// LAMEタグのデコード（frameはフレームヘッダ先頭から, offsetはLAMEタグの位置）
+fn decode_lame_information(frame: &[u8], offset: usize) -> Option<MP3LameInformation> {
+    let tag = frame.get(offset..offset + LAME_TAG_SIZE)?;
+
+    // エンコーダのバージョン文字列が英数字で始まらなければLAMEタグではない
+    if !tag[0].is_ascii_alphanumeric() {
+        return None;
+    }
+    let mut encoder = [0u8; 9];
+    encoder.copy_from_slice(&tag[..9]);
+
+    let delay_padding = ((tag[21] as u32) << 16) | ((tag[22] as u32) << 8) | tag[23] as u32;
+    let tag_crc = u16::from_be_bytes([tag[34], tag[35]]);
+    let crc_valid = frame.len() >= LAME_TAG_CRC_RANGE
+        && mp3_crc16_lame(&frame[..LAME_TAG_CRC_RANGE]) == tag_crc;
+
+    Some(MP3LameInformation {
+        encoder,
+        revision: tag[9] >> 4,
+        vbr_method: tag[9] & 0xF,
+        lowpass: tag[10] as u32 * 100,
+        // 2^23を1.0とする固定小数点
+        peak: read_u32_be(tag, 11)? as f32 / (1 << 23) as f32,
+        track_gain: decode_replay_gain(u16::from_be_bytes([tag[15], tag[16]])),
+        album_gain: decode_replay_gain(u16::from_be_bytes([tag[17], tag[18]])),
+        encoder_delay: (delay_padding >> 12) as u16,
+        padding: (delay_padding & 0xFFF) as u16,
+        music_length: read_u32_be(tag, 28)?,
+        music_crc: u16::from_be_bytes([tag[32], tag[33]]),
+        crc_valid,
+    })
+}
+
+/// Xing/Infoタグのデコード（frameはフレームヘッダ先頭から, offsetはサイドインフォメーション直後の位置）
+pub fn mp3_decode_xing_information(frame: &[u8], offset: usize) -> Option<MP3XingInformation> {
+    let data = frame.get(offset..)?;
+
+    // タグの識別子
+    let vbr = match data.get(..4)? {
+        b"Xing" => true,
+        b"Info" => false,
+        _ => return None,
+    };
+    let flags = read_u32_be(data, 4)?;
+    let mut read_pos = 8;
+
+    // フラグが立っているフィールドのみ順に格納されている
+    let mut info = MP3XingInformation {
+        vbr,
+        num_frames: None,
+        num_bytes: None,
+        toc: None,
+        quality: None,
+        lame: None,
+    };
+    if flags & XING_FLAG_FRAMES != 0 {
+        info.num_frames = Some(read_u32_be(data, read_pos)?);
+        read_pos += 4;
+    }
+    if flags & XING_FLAG_BYTES != 0 {
+        info.num_bytes = Some(read_u32_be(data, read_pos)?);
+        read_pos += 4;
+    }
+    if flags & XING_FLAG_TOC != 0 {
+        let mut toc = [0u8; XING_TOC_SIZE];
+        toc.copy_from_slice(data.get(read_pos..read_pos + XING_TOC_SIZE)?);
+        info.toc = Some(toc);
+        read_pos += XING_TOC_SIZE;
+    }
+    if flags & XING_FLAG_QUALITY != 0 {
+        info.quality = Some(read_u32_be(data, read_pos)?);
+        read_pos += 4;
+    }
+
+    // 続けてLAMEタグがあれば読み出し
+    info.lame = decode_lame_information(frame, offset + read_pos);
+
+    Some(info)
+}
+
+/// VBRIタグのデコード（frameはフレームヘッダ先頭から）
+pub fn mp3_decode_vbri_information(frame: &[u8]) -> Option<MP3VbriInformation> {
+    let data = frame.get(VBRI_TAG_OFFSET..)?;
+
+    // タグの識別子
+    if data.get(..4)? != b"VBRI" {
+        return None;
+    }
+
+    let mut info = MP3VbriInformation {
+        version: read_u16_be(data, 4)?,
+        delay: read_u16_be(data, 6)?,
+        quality: read_u16_be(data, 8)?,
+        num_bytes: read_u32_be(data, 10)?,
+        num_frames: read_u32_be(data, 14)?,
+        toc_scale: read_u16_be(data, 20)?,
+        toc_entry_size: read_u16_be(data, 22)?,
+        frames_per_toc_entry: read_u16_be(data, 24)?,
+        num_toc_entries: read_u16_be(data, 18)? as usize,
+        toc_offset: VBRI_TAG_OFFSET + VBRI_HEADER_SIZE,
+        toc_size: 0,
+    };
+
+    // エントリサイズは1-4byte
+    let entry_size = info.toc_entry_size as usize;
+    if !(1..=4).contains(&entry_size) {
+        return None;
+    }
+
+    // TOCがフレームに収まっているか
+    info.toc_size = info.num_toc_entries * entry_size;
+    frame.get(info.toc_offset..info.toc_offset + info.toc_size)?;
+
+    Some(info)
+}
+
+/// VBRIタグのTOCのエントリ（区間のバイト数, スケールファクタ適用済み）を読み出し（frameはフレームヘッダ先頭から）
+pub fn mp3_read_vbri_toc_entry(
+    frame: &[u8],
+    info: &MP3VbriInformation,
+    index: usize,
+) -> Option<u32> {
+    if index >= info.num_toc_entries {
+        return None;
+    }
+    let entry_size = info.toc_entry_size as usize;
+    let pos = info.toc_offset + index * entry_size;
+    let bytes = frame.get(pos..pos + entry_size)?;
+    let value = bytes.iter().fold(0u32, |acc, &b| (acc << 8) | b as u32);
+    Some(value.saturating_mul(info.toc_scale as u32))
+}
@@ -1,123 +1,216 @@
+use baremp3::ape::*;
 use baremp3::decoder::*;
+use baremp3::frame::*;
+use baremp3::id3v1::*;
+use baremp3::id3v2::*;
+use baremp3::lyrics3::*;
+use baremp3::seek_index::*;
+use baremp3::stream::*;
+use baremp3::tag::*;
 use baremp3::types::*;
 use hound;
-use std::cmp::max;
+use std::cmp::{max, min};
 
 #[test]
 fn get_format_test() -> Result<(), Box<dyn std::error::Error>> {
     // テストケース
     struct FormatTestCase<'a> {
         path: &'a str,                // mp3ファイルパス
-        format: MP3FormatInformation, // 正解フォーマット（サンプル数はdist10デコーダの結果）
+        format: MP3FormatInformation, // 正解フォーマット（サンプル数はdist10デコーダの結果からタグのフレームを除いたもの）
+        xing_num_frames: Option<u32>, // Xing/Infoタグのフレーム数
     }
     let testcases = [
         FormatTestCase {
             path: "./tests/data/alphabet02all_01_32_encffmpeg.mp3",
             format: MP3FormatInformation {
                 num_channels: 1,
-                num_samples: 609408,
+                num_samples: 608256,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps32,
+                average_bit_rate: 32_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: Some(528),
         },
         FormatTestCase {
             path: "./tests/data/alphabet02all_01_128_encffmpeg.mp3",
             format: MP3FormatInformation {
                 num_channels: 1,
-                num_samples: 609408,
+                num_samples: 608256,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps128,
+                average_bit_rate: 128_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: Some(528),
         },
         FormatTestCase {
             path: "./tests/data/alphabet02all_01_320_encffmpeg.mp3",
             format: MP3FormatInformation {
                 num_channels: 1,
-                num_samples: 609408,
+                num_samples: 608256,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps320,
+                average_bit_rate: 320_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: Some(528),
         },
         FormatTestCase {
             path: "./tests/data/y004_32_encdist10.mpg",
             format: MP3FormatInformation {
                 num_channels: 2,
                 num_samples: 1323200,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps32,
+                average_bit_rate: 32_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: None,
         },
         FormatTestCase {
             path: "./tests/data/y004_128_encdist10.mpg",
             format: MP3FormatInformation {
                 num_channels: 2,
                 num_samples: 1323200,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps128,
+                average_bit_rate: 128_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: None,
         },
         FormatTestCase {
             path: "./tests/data/y004_320_encdist10.mpg",
             format: MP3FormatInformation {
                 num_channels: 2,
                 num_samples: 1323200,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps320,
+                average_bit_rate: 320_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: None,
         },
         FormatTestCase {
             path: "./tests/data/y004_64_encgogo.mp3",
             format: MP3FormatInformation {
                 num_channels: 2,
                 num_samples: 1324800,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps64,
+                average_bit_rate: 64_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: None,
         },
         FormatTestCase {
             path: "./tests/data/y004_128_encgogo.mp3",
             format: MP3FormatInformation {
                 num_channels: 2,
                 num_samples: 1324800,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps128,
+                average_bit_rate: 128_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: None,
         },
         FormatTestCase {
             path: "./tests/data/y004_320_encgogo.mp3",
             format: MP3FormatInformation {
                 num_channels: 2,
                 num_samples: 1324800,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps320,
+                average_bit_rate: 320_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: None,
         },
         FormatTestCase {
             path: "./tests/data/y004_32_encffmpeg.mp3",
             format: MP3FormatInformation {
                 num_channels: 2,
-                num_samples: 1325952,
+                num_samples: 1324800,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps32,
+                average_bit_rate: 32_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: Some(1150),
         },
         FormatTestCase {
             path: "./tests/data/y004_128_encffmpeg.mp3",
             format: MP3FormatInformation {
                 num_channels: 2,
-                num_samples: 1325952,
+                num_samples: 1324800,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps128,
+                average_bit_rate: 128_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: Some(1150),
         },
         FormatTestCase {
             path: "./tests/data/y004_320_encffmpeg.mp3",
             format: MP3FormatInformation {
                 num_channels: 2,
-                num_samples: 1325952,
+                num_samples: 1324800,
+                num_samples_per_frame: 1152,
+                dual_channel: false,
                 sampling_rate: MP3SamplingRate::Hz44100,
                 bit_rate: MP3BitRate::Kbps320,
+                average_bit_rate: 320_000,
+                vbr: false,
+                xing: None,
+                vbri: None,
             },
+            xing_num_frames: Some(1150),
         },
     ];
 
@@ -125,10 +218,17 @@ fn get_format_test() -> Result<(), Box<dyn std::error::Error>> {
         let data = std::fs::read(case.path)?;
         let format = get_format_information(&data)?;
         assert_eq!(case.format.num_channels, format.num_channels);
+        assert_eq!(case.format.dual_channel, format.dual_channel);
         // サンプル数が減っていなければよいとする
         assert!(case.format.num_samples <= format.num_samples);
         assert!(case.format.sampling_rate == format.sampling_rate);
         assert!(case.format.bit_rate == format.bit_rate);
+        assert_eq!(case.format.vbr, format.vbr);
+        // 平均ビットレートは1%以内の誤差を許容
+        let expected = case.format.average_bit_rate;
+        assert!(100 * expected.abs_diff(format.average_bit_rate) <= expected);
+        let xing_num_frames = format.xing.and_then(|xing| xing.num_frames);
+        assert_eq!(case.xing_num_frames, xing_num_frames);
     }
 
     Ok(())
@@ -199,20 +299,30 @@ fn decode_test() -> Result<(), Box<dyn std::error::Error>> {
         let data = std::fs::read(case.mp3_path)?;
         let format = get_format_information(&data)?;
 
+        // dist10はXing/Infoタグのフレームも無音としてデコードしているため、その分ずらして比較
+        let ref_offset = if format.xing.is_some() {
+            format.num_samples_per_frame
+        } else {
+            0
+        };
+
         // サンプル数のチェック
         // dist10と一致させるのは困難なので、減っていなければよいとする
         let num_total_samples = format.num_channels * format.num_samples;
+        let num_ref_samples = reader.duration() as usize;
         assert!(
-            num_total_samples >= spec.channels as usize * reader.duration() as usize,
-            "failed to check samples for mp3:{} wav:{}", case.mp3_path, case.ref_wav_path
+            format.num_samples + ref_offset >= num_ref_samples,
+            "failed to check samples for mp3:{} wav:{}",
+            case.mp3_path,
+            case.ref_wav_path
         );
 
         // リファレンス波形のPCM読み込み
-        let mut ref_pcm = vec![0i16; num_total_samples];
-        for smpl in 0..reader.duration() as usize {
+        let mut ref_pcm = vec![0i16; spec.channels as usize * num_ref_samples];
+        for smpl in 0..num_ref_samples {
             // インターリーブで読み出されるので、サンプル数だけ離して配置
             for ch in 0..spec.channels as usize {
-                ref_pcm[smpl + ch * format.num_samples] =
+                ref_pcm[smpl + ch * num_ref_samples] =
                     reader.samples::<i16>().next().unwrap().unwrap();
             }
         }
@@ -227,12 +337,12 @@ fn decode_test() -> Result<(), Box<dyn std::error::Error>> {
         // 末尾の遅延サンプル分(=1057)除いて比較
         // （モノラルデータでは、dist10のデコード結果末尾で成分が発生...）
         let mut max_abs_error = 0;
-        for smpl in 0..(format.num_samples - 1057) {
+        for smpl in 0..(num_ref_samples - ref_offset - 1057) {
             for ch in 0..format.num_channels {
                 const AMPLITUDE: f32 = i16::MAX as f32;
-                let index = smpl + ch * format.num_channels;
-                let out = (output[index] * AMPLITUDE).round() as i16;
-                max_abs_error = max(max_abs_error, (ref_pcm[index] - out).abs());
+                let out = (output[smpl + ch * format.num_samples] * AMPLITUDE).round() as i16;
+                let reference = ref_pcm[smpl + ref_offset + ch * num_ref_samples];
+                max_abs_error = max(max_abs_error, (reference - out).abs());
             }
         }
         assert!(max_abs_error <= 1);
@@ -240,3 +350,2021 @@ fn decode_test() -> Result<(), Box<dyn std::error::Error>> {
 
     Ok(())
 }
+
+// 全サンプルをデコードし、リファレンスデコーダ（minimp3）の出力と比較
+// minimp3はフレーム先頭からそのまま出力するため、遅延を考慮せずに全区間を比較する
+fn compare_with_reference_decoder(
+    mp3_path: &str,
+    ref_wav_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let mut reader = hound::WavReader::open(ref_wav_path)?;
+    let spec = reader.spec();
+    let data = std::fs::read(mp3_path)?;
+    let format = get_format_information(&data)?;
+    assert_eq!(format.num_channels, spec.channels as usize);
+    assert_eq!(format.sampling_rate as u32, spec.sample_rate);
+
+    // リファレンス波形のPCM読み込み
+    let num_ref_samples = reader.duration() as usize;
+    assert_eq!(format.num_samples, num_ref_samples);
+    let ref_pcm: Vec<i16> = reader.samples::<i16>().collect::<Result<_, _>>()?;
+
+    // デコード
+    let mut output = vec![0.0f32; format.num_channels * format.num_samples];
+    let (left, right) = output.split_at_mut(format.num_samples);
+    let mut decoder = MP3Decoder::new();
+    let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [left, right])?;
+    assert_eq!(num_decoded_samples, format.num_samples);
+
+    let mut max_abs_error = 0;
+    for smpl in 0..format.num_samples {
+        for ch in 0..format.num_channels {
+            const AMPLITUDE: f32 = i16::MAX as f32;
+            let out = (output[smpl + ch * format.num_samples] * AMPLITUDE).round() as i16;
+            let reference = ref_pcm[smpl * format.num_channels + ch];
+            max_abs_error = max(max_abs_error, (reference - out).abs());
+        }
+    }
+    assert!(
+        max_abs_error <= 1,
+        "failed to compare mp3:{} wav:{} max_abs_error:{}",
+        mp3_path,
+        ref_wav_path,
+        max_abs_error
+    );
+
+    Ok(())
+}
+
+#[test]
+fn intensity_stereo_decode_test() -> Result<(), Box<dyn std::error::Error>> {
+    // デコードテストケース
+    struct DecodeTestCase<'a> {
+        mp3_path: &'a str,     // mp3ファイルパス
+        ref_wav_path: &'a str, // 正解デコードデータ
+    }
+
+    let testcases = [
+        // MPEG1 インテンシティステレオ ロングブロック
+        DecodeTestCase {
+            mp3_path: "./tests/data/intensity_stereo_long_encsynth.mp3",
+            ref_wav_path: "./tests/data/intensity_stereo_long_encsynth_decminimp3.wav",
+        },
+        // MPEG1 インテンシティステレオ ショートブロック（ウィンドウ毎に異なる境界）
+        DecodeTestCase {
+            mp3_path: "./tests/data/intensity_stereo_short_encsynth.mp3",
+            ref_wav_path: "./tests/data/intensity_stereo_short_encsynth_decminimp3.wav",
+        },
+        // MPEG1 インテンシティステレオ ミックスドブロック
+        DecodeTestCase {
+            mp3_path: "./tests/data/intensity_stereo_mixed_encsynth.mp3",
+            ref_wav_path: "./tests/data/intensity_stereo_mixed_encsynth_decminimp3.wav",
+        },
+        // MPEG1 MS+インテンシティステレオ
+        DecodeTestCase {
+            mp3_path: "./tests/data/ms_intensity_stereo_encsynth.mp3",
+            ref_wav_path: "./tests/data/ms_intensity_stereo_encsynth_decminimp3.wav",
+        },
+        // MPEG2(LSF) インテンシティステレオ・MS+インテンシティステレオ
+        DecodeTestCase {
+            mp3_path: "./tests/data/intensity_stereo_lsf_encsynth.mp3",
+            ref_wav_path: "./tests/data/intensity_stereo_lsf_encsynth_decminimp3.wav",
+        },
+        // MPEG2(LSF) インテンシティステレオ ミックスドブロック
+        DecodeTestCase {
+            mp3_path: "./tests/data/intensity_stereo_lsf_mixed_encsynth.mp3",
+            ref_wav_path: "./tests/data/intensity_stereo_lsf_mixed_encsynth_decminimp3.wav",
+        },
+    ];
+
+    for case in &testcases {
+        compare_with_reference_decoder(case.mp3_path, case.ref_wav_path)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn lsf_decode_test() -> Result<(), Box<dyn std::error::Error>> {
+    // テストケース
+    struct LSFTestCase {
+        header: [u8; 4],                // フレームヘッダ
+        frame_size: usize,              // フレームサイズ
+        sampling_rate: MP3SamplingRate, // サンプリングレート
+    }
+    let testcases = [
+        // MPEG2 Layer3 22.05kHz 64kbps モノラル
+        LSFTestCase {
+            header: [0xFF, 0xF3, 0x80, 0xC0],
+            frame_size: 208,
+            sampling_rate: MP3SamplingRate::Hz22050,
+        },
+        // MPEG2.5 Layer3 8kHz 8kbps モノラル
+        LSFTestCase {
+            header: [0xFF, 0xE3, 0x18, 0xC0],
+            frame_size: 72,
+            sampling_rate: MP3SamplingRate::Hz8000,
+        },
+    ];
+
+    const NUM_FRAMES: usize = 10;
+    for case in &testcases {
+        // メインデータが空（無音）のフレームを並べる
+        let mut data = vec![0u8; case.frame_size * NUM_FRAMES];
+        for frame in data.chunks_mut(case.frame_size) {
+            frame[..4].copy_from_slice(&case.header);
+        }
+
+        let format = get_format_information(&data)?;
+        assert_eq!(format.num_channels, 1);
+        assert_eq!(format.num_samples_per_frame, 576);
+        assert_eq!(format.num_samples, 576 * NUM_FRAMES);
+        assert!(format.sampling_rate == case.sampling_rate);
+
+        let mut output = vec![1.0f32; format.num_samples];
+        let mut decoder = MP3Decoder::new();
+        let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+        assert_eq!(num_decoded_samples, format.num_samples);
+        assert!(output.iter().all(|&x| x == 0.0));
+    }
+
+    Ok(())
+}
+
+// ビット単位の書き込み
+fn put_bits(data: &mut [u8], pos: &mut usize, value: u32, nbits: usize) {
+    for i in (0..nbits).rev() {
+        if (value >> i) & 1 != 0 {
+            data[*pos / 8] |= 0x80 >> (*pos % 8);
+        }
+        *pos += 1;
+    }
+}
+
+#[test]
+fn layer2_decode_test() -> Result<(), Box<dyn std::error::Error>> {
+    // テストケース
+    struct Layer2TestCase {
+        header: [u8; 4],   // フレームヘッダ
+        frame_size: usize, // フレームサイズ
+    }
+    let testcases = [
+        // MPEG1 Layer2 48kHz 64kbps モノラル
+        Layer2TestCase {
+            header: [0xFF, 0xFD, 0x44, 0xC0],
+            frame_size: 192,
+        },
+        // MPEG1 Layer2 48kHz 64kbps モノラル CRCあり
+        Layer2TestCase {
+            header: [0xFF, 0xFC, 0x44, 0xC0],
+            frame_size: 192,
+        },
+    ];
+
+    const NUM_FRAMES: usize = 10;
+    for case in &testcases {
+        // 第0サブバンドのみに一定値を持つフレームを並べる
+        let mut data = vec![0u8; case.frame_size * NUM_FRAMES];
+        for frame in data.chunks_mut(case.frame_size) {
+            frame[..4].copy_from_slice(&case.header);
+            let mut pos = if (case.header[1] & 1) == 0 { 48 } else { 32 };
+            // ビット割り当て(Table B.2a) 第0サブバンドのみ15レベル量子化
+            put_bits(frame, &mut pos, 3, 4);
+            pos += 2 * 4 + 8 * 4 + 12 * 3 + 4 * 2;
+            // スケールファクタ選択情報: 3パートで共有
+            put_bits(frame, &mut pos, 2, 2);
+            // スケールファクタ: 1.0
+            put_bits(frame, &mut pos, 3, 6);
+            // サンプル: 14/15
+            for _ in 0..36 {
+                put_bits(frame, &mut pos, 14, 4);
+            }
+        }
+
+        let format = get_format_information(&data)?;
+        assert_eq!(format.num_channels, 1);
+        assert_eq!(format.num_samples_per_frame, 1152);
+        assert_eq!(format.num_samples, 1152 * NUM_FRAMES);
+        assert!(format.sampling_rate == MP3SamplingRate::Hz48000);
+        assert!(format.bit_rate == MP3BitRate::Kbps64);
+
+        let mut output = vec![0.0f32; format.num_samples];
+        let mut decoder = MP3Decoder::new();
+        let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+        assert_eq!(num_decoded_samples, format.num_samples);
+
+        // フィルタの遅延を除いた区間は直流成分(14/15)が出力される
+        for &out in &output[1152..] {
+            assert!((out - 14.0 / 15.0).abs() < 1e-3);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn layer2_lsf_decode_test() -> Result<(), Box<dyn std::error::Error>> {
+    // デコードテストケース（全サブバンドにビットを割り当てたMPEG2 Layer2）
+    struct DecodeTestCase<'a> {
+        mp2_path: &'a str,     // mp2ファイルパス
+        ref_wav_path: &'a str, // 正解デコードデータ
+    }
+
+    let testcases = [
+        // MPEG2 Layer2 22.05kHz 160kbps ステレオ
+        DecodeTestCase {
+            mp2_path: "./tests/data/layer2_lsf_22050_stereo_encsynth.mp2",
+            ref_wav_path: "./tests/data/layer2_lsf_22050_stereo_encsynth_decminimp3.wav",
+        },
+        // MPEG2 Layer2 24kHz 160kbps ジョイントステレオ
+        DecodeTestCase {
+            mp2_path: "./tests/data/layer2_lsf_24000_jointstereo_encsynth.mp2",
+            ref_wav_path: "./tests/data/layer2_lsf_24000_jointstereo_encsynth_decminimp3.wav",
+        },
+    ];
+
+    for case in &testcases {
+        compare_with_reference_decoder(case.mp2_path, case.ref_wav_path)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn layer1_decode_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer1 48kHz 64kbps モノラル
+    const HEADER: [u8; 4] = [0xFF, 0xFF, 0x24, 0xC0];
+    const FRAME_SIZE: usize = 64;
+    const NUM_FRAMES: usize = 10;
+
+    // 第0サブバンドのみに一定値を持つフレームを並べる
+    let mut data = vec![0u8; FRAME_SIZE * NUM_FRAMES];
+    for frame in data.chunks_mut(FRAME_SIZE) {
+        frame[..4].copy_from_slice(&HEADER);
+        let mut pos = 32;
+        // ビット割り当て: 第0サブバンドのみ4bit(15レベル)量子化
+        put_bits(frame, &mut pos, 3, 4);
+        pos += 31 * 4;
+        // スケールファクタ: 1.0
+        put_bits(frame, &mut pos, 3, 6);
+        // サンプル: 14/15
+        for _ in 0..12 {
+            put_bits(frame, &mut pos, 14, 4);
+        }
+    }
+
+    let format = get_format_information(&data)?;
+    assert_eq!(format.num_channels, 1);
+    assert_eq!(format.num_samples_per_frame, 384);
+    assert_eq!(format.num_samples, 384 * NUM_FRAMES);
+    assert!(format.sampling_rate == MP3SamplingRate::Hz48000);
+    assert!(format.bit_rate == MP3BitRate::Kbps64);
+
+    let mut output = vec![0.0f32; format.num_samples];
+    let mut decoder = MP3Decoder::new();
+    let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(num_decoded_samples, format.num_samples);
+
+    // フィルタの遅延を除いた区間は直流成分(14/15)が出力される
+    for &out in &output[1152..] {
+        assert!((out - 14.0 / 15.0).abs() < 1e-3);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn free_format_decode_test() -> Result<(), Box<dyn std::error::Error>> {
+    // テストケース
+    struct FreeFormatTestCase {
+        header: [u8; 4],              // フレームヘッダ
+        frame_size: usize,            // フレームサイズ
+        num_samples_per_frame: usize, // フレームあたりサンプル数
+    }
+    let testcases = [
+        // MPEG1 Layer3 48kHz フリーフォーマット(500kbps) モノラル
+        FreeFormatTestCase {
+            header: [0xFF, 0xFB, 0x04, 0xC0],
+            frame_size: 1500,
+            num_samples_per_frame: 1152,
+        },
+        // MPEG1 Layer2 48kHz フリーフォーマット(100kbps) モノラル
+        FreeFormatTestCase {
+            header: [0xFF, 0xFD, 0x04, 0xC0],
+            frame_size: 300,
+            num_samples_per_frame: 1152,
+        },
+        // MPEG1 Layer1 48kHz フリーフォーマット(100kbps) モノラル
+        FreeFormatTestCase {
+            header: [0xFF, 0xFF, 0x04, 0xC0],
+            frame_size: 100,
+            num_samples_per_frame: 384,
+        },
+    ];
+
+    const NUM_FRAMES: usize = 10;
+    for case in &testcases {
+        // 無音のフレームを並べる
+        let mut data = vec![0u8; case.frame_size * NUM_FRAMES];
+        for frame in data.chunks_mut(case.frame_size) {
+            frame[..4].copy_from_slice(&case.header);
+        }
+
+        let format = get_format_information(&data)?;
+        assert_eq!(format.num_channels, 1);
+        assert_eq!(format.num_samples, case.num_samples_per_frame * NUM_FRAMES);
+        assert!(format.bit_rate == MP3BitRate::Kbps0);
+
+        let mut output = vec![1.0f32; format.num_samples];
+        let mut decoder = MP3Decoder::new();
+        let (read_size, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+        assert_eq!(read_size, data.len());
+        assert_eq!(num_decoded_samples, format.num_samples);
+        assert!(output.iter().all(|&x| x == 0.0));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn crc_check_test() -> Result<(), Box<dyn std::error::Error>> {
+    // CRC-16(x^16 + x^15 + x^2 + 1)の計算
+    fn crc16(crc: u16, data: &[u8], nbits: usize) -> u16 {
+        let mut crc = crc;
+        for i in 0..nbits {
+            let bit = ((data[i / 8] >> (7 - (i % 8))) & 1) as u16;
+            let msb = crc >> 15;
+            crc <<= 1;
+            if (msb ^ bit) != 0 {
+                crc ^= 0x8005;
+            }
+        }
+        crc
+    }
+
+    // MPEG1 Layer2 48kHz 64kbps モノラル CRCあり
+    const HEADER: [u8; 4] = [0xFF, 0xFC, 0x44, 0xC0];
+    const FRAME_SIZE: usize = 192;
+    const NUM_FRAMES: usize = 10;
+    // CRCを破損させるフレーム
+    const CORRUPTED_FRAME: usize = 5;
+
+    // 第0サブバンドのみに一定値を持つフレームを並べる
+    let mut data = vec![0u8; FRAME_SIZE * NUM_FRAMES];
+    for (i, frame) in data.chunks_mut(FRAME_SIZE).enumerate() {
+        frame[..4].copy_from_slice(&HEADER);
+        let mut pos = 48;
+        // ビット割り当て(Table B.2a) 第0サブバンドのみ15レベル量子化
+        put_bits(frame, &mut pos, 3, 4);
+        pos += 2 * 4 + 8 * 4 + 12 * 3 + 4 * 2;
+        // スケールファクタ選択情報: 3パートで共有
+        put_bits(frame, &mut pos, 2, 2);
+        // スケールファクタ: 1.0
+        put_bits(frame, &mut pos, 3, 6);
+        // サンプル: 14/15
+        for _ in 0..36 {
+            put_bits(frame, &mut pos, 14, 4);
+        }
+        // ヘッダ後半16bit, ビット割り当て, スケールファクタ選択情報を保護
+        let mut crc = crc16(0xFFFF, &frame[2..4], 16);
+        crc = crc16(crc, &frame[6..], 88 + 2);
+        if i == CORRUPTED_FRAME {
+            crc ^= 1;
+        }
+        frame[4..6].copy_from_slice(&crc.to_be_bytes());
+    }
+
+    let format = get_format_information(&data)?;
+    let mut decoder = MP3Decoder::new();
+
+    // 不一致フレームでエラー
+    let mut output = vec![0.0f32; format.num_samples];
+    decoder.set_crc_policy(MP3CrcPolicy::Reject);
+    assert!(matches!(
+        decoder.decode_whole(&data, &mut [&mut output]),
+        Err(MP3DecodeError::CrcMismatch)
+    ));
+    let (_, num_decoded_samples) =
+        decoder.decode_whole(&data[..FRAME_SIZE * CORRUPTED_FRAME], &mut [&mut output])?;
+    assert_eq!(num_decoded_samples, 1152 * CORRUPTED_FRAME);
+
+    // 不一致フレームは無音（合成フィルタの残響512サンプルの後）
+    let mut output = vec![0.0f32; format.num_samples];
+    decoder.set_crc_policy(MP3CrcPolicy::Conceal);
+    let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(num_decoded_samples, format.num_samples);
+    let corrupted = 1152 * CORRUPTED_FRAME..1152 * (CORRUPTED_FRAME + 1);
+    assert!(
+        output[corrupted.start + 512..corrupted.end]
+            .iter()
+            .all(|&x| x == 0.0)
+    );
+    assert!((output[1152 * CORRUPTED_FRAME - 1] - 14.0 / 15.0).abs() < 1e-3);
+
+    // 検査せずにデコード
+    let mut output = vec![0.0f32; format.num_samples];
+    decoder.set_crc_policy(MP3CrcPolicy::Ignore);
+    let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(num_decoded_samples, format.num_samples);
+    for &out in &output[corrupted] {
+        assert!((out - 14.0 / 15.0).abs() < 1e-3);
+    }
+
+    // MPEG1 Layer3 48kHz 64kbps モノラル CRCあり（サイドインフォメーションを保護）
+    const HEADER_LAYER3: [u8; 4] = [0xFF, 0xFA, 0x54, 0xC0];
+    let mut data = vec![0u8; FRAME_SIZE * NUM_FRAMES];
+    for frame in data.chunks_mut(FRAME_SIZE) {
+        frame[..4].copy_from_slice(&HEADER_LAYER3);
+        let mut crc = crc16(0xFFFF, &frame[2..4], 16);
+        crc = crc16(crc, &frame[6..], 17 * 8);
+        frame[4..6].copy_from_slice(&crc.to_be_bytes());
+    }
+    let mut output = vec![1.0f32; 1152 * NUM_FRAMES];
+    decoder.set_crc_policy(MP3CrcPolicy::Reject);
+    let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(num_decoded_samples, 1152 * NUM_FRAMES);
+    assert!(output.iter().all(|&x| x == 0.0));
+
+    Ok(())
+}
+
+#[test]
+fn deemphasis_test() -> Result<(), Box<dyn std::error::Error>> {
+    const FRAME_SIZE: usize = 192;
+    const NUM_FRAMES: usize = 10;
+
+    // MPEG1 Layer2 48kHz 64kbps モノラルで、指定サブバンドのみに一定値を持つフレームを作成
+    // (サブバンド, ビット割り当て, 量子化値のビット幅, 量子化値)
+    fn make_data(emphasis: u8, subband: (usize, u32, usize, u32)) -> Vec<u8> {
+        // Table B.2aのビット割り当てのビット幅
+        const NBAL: [usize; 27] = [
+            4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 4, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 3, 2, 2, 2, 2,
+        ];
+        let (sb, alloc, nbits, code) = subband;
+        let mut data = vec![0u8; FRAME_SIZE * NUM_FRAMES];
+        for frame in data.chunks_mut(FRAME_SIZE) {
+            frame[..4].copy_from_slice(&[0xFF, 0xFD, 0x44, 0xC0 | emphasis]);
+            let mut pos = 32 + NBAL[..sb].iter().sum::<usize>();
+            put_bits(frame, &mut pos, alloc, NBAL[sb]);
+            pos = 32 + NBAL.iter().sum::<usize>();
+            // スケールファクタ選択情報: 3パートで共有
+            put_bits(frame, &mut pos, 2, 2);
+            // スケールファクタ: 1.0
+            put_bits(frame, &mut pos, 3, 6);
+            for _ in 0..36 {
+                put_bits(frame, &mut pos, code, nbits);
+            }
+        }
+        data
+    }
+
+    // 1フレーム目を除いた区間のRMS
+    fn decode_rms(data: &[u8], deemphasis: bool) -> Result<f32, MP3DecodeError> {
+        let mut output = vec![0.0f32; 1152 * NUM_FRAMES];
+        let mut decoder = MP3Decoder::new();
+        decoder.set_deemphasis(deemphasis);
+        decoder.decode_whole(data, &mut [&mut output])?;
+        let stable = &output[1152..];
+        Ok((stable.iter().map(|x| x * x).sum::<f32>() / stable.len() as f32).sqrt())
+    }
+
+    // (エンファシスモード, 15.375kHzでのゲイン)
+    for (emphasis, gain) in [(1, 0.357), (3, 0.119)] {
+        // 直流成分(第0サブバンド, 14/15)は変化しない
+        let data = make_data(emphasis, (0, 3, 4, 14));
+        assert!((decode_rms(&data, true)? - 14.0 / 15.0).abs() < 1e-3);
+
+        // 高域成分(第20サブバンド, 6/7)はアナログフィルタのゲイン程度に減衰する
+        let data = make_data(emphasis, (20, 3, 3, 6));
+        let ratio = decode_rms(&data, true)? / decode_rms(&data, false)?;
+        assert!((ratio - gain).abs() < 0.15 * gain);
+    }
+
+    // デフォルトではエンファシスフラグがあってもデエンファシスしない
+    let data = make_data(1, (20, 3, 3, 6));
+    let mut output = vec![0.0f32; 1152 * NUM_FRAMES];
+    MP3Decoder::new().decode_whole(&data, &mut [&mut output])?;
+    let stable = &output[1152..];
+    let rms = (stable.iter().map(|x| x * x).sum::<f32>() / stable.len() as f32).sqrt();
+    assert_eq!(rms, decode_rms(&data, false)?);
+
+    Ok(())
+}
+
+#[test]
+fn dual_channel_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer2 48kHz 128kbps デュアルチャンネル
+    const HEADER: [u8; 4] = [0xFF, 0xFD, 0x84, 0x80];
+    const FRAME_SIZE: usize = 384;
+    const NUM_FRAMES: usize = 10;
+    // Table B.2aのビット割り当てのビット幅の合計
+    const NBAL_TOTAL: usize = 11 * 4 + 12 * 3 + 4 * 2;
+    // チャンネルA: 14/15, チャンネルB: -2/3
+    const CHANNEL_A: f32 = 14.0 / 15.0;
+    const CHANNEL_B: f32 = -2.0 / 3.0;
+
+    // 第0サブバンドのみに一定値を持つフレームを並べる
+    let mut data = vec![0u8; FRAME_SIZE * NUM_FRAMES];
+    for frame in data.chunks_mut(FRAME_SIZE) {
+        frame[..4].copy_from_slice(&HEADER);
+        let mut pos = 32;
+        // ビット割り当て: 第0サブバンドのみ両チャンネル15レベル量子化
+        put_bits(frame, &mut pos, 3, 4);
+        put_bits(frame, &mut pos, 3, 4);
+        pos = 32 + 2 * NBAL_TOTAL;
+        // スケールファクタ選択情報: 3パートで共有
+        put_bits(frame, &mut pos, 2, 2);
+        put_bits(frame, &mut pos, 2, 2);
+        // スケールファクタ: 1.0
+        put_bits(frame, &mut pos, 3, 6);
+        put_bits(frame, &mut pos, 3, 6);
+        // サンプル
+        for _ in 0..12 {
+            for code in [14, 2] {
+                for _ in 0..3 {
+                    put_bits(frame, &mut pos, code, 4);
+                }
+            }
+        }
+    }
+
+    let format = get_format_information(&data)?;
+    assert_eq!(format.num_channels, 2);
+    assert!(format.dual_channel);
+
+    // (出力チャンネル選択, 1チャンネル目, 2チャンネル目)
+    let testcases = [
+        (MP3DualChannelOutput::Both, CHANNEL_A, CHANNEL_B),
+        (MP3DualChannelOutput::ChannelA, CHANNEL_A, 0.0),
+        (MP3DualChannelOutput::ChannelB, CHANNEL_B, 0.0),
+        (MP3DualChannelOutput::MirrorA, CHANNEL_A, CHANNEL_A),
+    ];
+    for (output_mode, first, second) in testcases {
+        let mut output = vec![0.0f32; 2 * format.num_samples];
+        let (left, right) = output.split_at_mut(format.num_samples);
+        let mut decoder = MP3Decoder::new();
+        decoder.set_dual_channel_output(output_mode);
+        decoder.decode_whole(&data, &mut [left, right])?;
+        for smpl in 1152..format.num_samples {
+            assert!((left[smpl] - first).abs() < 1e-3);
+            assert!((right[smpl] - second).abs() < 1e-3);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn xing_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer3 48kHz モノラル 64kbps(192byte)と128kbps(384byte)の無音フレームを交互に並べる
+    const HEADER_64KBPS: [u8; 4] = [0xFF, 0xFB, 0x54, 0xC0];
+    const HEADER_128KBPS: [u8; 4] = [0xFF, 0xFB, 0x94, 0xC0];
+    const NUM_FRAMES: usize = 9;
+    let mut audio = Vec::new();
+    for i in 0..NUM_FRAMES {
+        let (header, size) = if i % 2 == 0 {
+            (HEADER_64KBPS, 192)
+        } else {
+            (HEADER_128KBPS, 384)
+        };
+        let mut frame = vec![0u8; size];
+        frame[..4].copy_from_slice(&header);
+        audio.extend_from_slice(&frame);
+    }
+
+    // 先頭にXingタグのフレーム(64kbps)を付与
+    let mut data = vec![0u8; 192];
+    data[..4].copy_from_slice(&HEADER_64KBPS);
+    let mut tag = Vec::new();
+    tag.extend_from_slice(b"Xing");
+    tag.extend_from_slice(&0xFu32.to_be_bytes());
+    tag.extend_from_slice(&(NUM_FRAMES as u32).to_be_bytes());
+    tag.extend_from_slice(&((192 + audio.len()) as u32).to_be_bytes());
+    tag.extend((0..100).map(|i| i as u8));
+    tag.extend_from_slice(&57u32.to_be_bytes());
+    // サイドインフォメーション(17byte)の直後に配置
+    data[21..21 + tag.len()].copy_from_slice(&tag);
+    data.extend_from_slice(&audio);
+
+    let format = get_format_information(&data)?;
+    let xing = format.xing.unwrap();
+    assert!(xing.vbr);
+    assert_eq!(xing.num_frames, Some(NUM_FRAMES as u32));
+    assert_eq!(xing.num_bytes, Some(data.len() as u32));
+    assert_eq!(xing.toc.unwrap()[99], 99);
+    assert_eq!(xing.quality, Some(57));
+    assert_eq!(get_seek_position(&data, &format, 0), Some(0));
+    assert!(format.vbr);
+    assert_eq!(format.num_samples, 1152 * NUM_FRAMES);
+    assert_eq!(
+        format.average_bit_rate,
+        8 * audio.len() * 48000 / format.num_samples
+    );
+
+    // タグのフレームは音声としてデコードされない
+    let mut output = vec![1.0f32; format.num_samples];
+    let mut decoder = MP3Decoder::new();
+    let (read_size, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(read_size, data.len());
+    assert_eq!(num_decoded_samples, format.num_samples);
+    assert!(output.iter().all(|&x| x == 0.0));
+
+    // タグのフレームの直後に不正なヘッダがあっても中断せず、同期を取り直してデコード
+    let mut damaged = data[..192].to_vec();
+    damaged.extend_from_slice(&[0xFF, 0xFB, 0xF4, 0xC0]);
+    damaged.extend_from_slice(&audio);
+    let mut decoder = MP3Decoder::new();
+    let (read_size, num_decoded_samples) = decoder.decode_whole(&damaged, &mut [&mut output])?;
+    assert_eq!(read_size, damaged.len());
+    assert_eq!(num_decoded_samples, format.num_samples);
+
+    // タグがなければ全フレームを走査してVBRを検知
+    let format = get_format_information(&audio)?;
+    assert!(format.xing.is_none());
+    assert!(format.vbr);
+    assert_eq!(format.num_samples, 1152 * NUM_FRAMES);
+
+    Ok(())
+}
+
+#[test]
+fn vbri_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer3 48kHz モノラル 64kbps(192byte)と128kbps(384byte)の無音フレームを交互に並べる
+    const HEADER_64KBPS: [u8; 4] = [0xFF, 0xFB, 0x54, 0xC0];
+    const HEADER_128KBPS: [u8; 4] = [0xFF, 0xFB, 0x94, 0xC0];
+    const NUM_FRAMES: usize = 10;
+    let mut audio = Vec::new();
+    for i in 0..NUM_FRAMES {
+        let (header, size) = if i % 2 == 0 {
+            (HEADER_64KBPS, 192)
+        } else {
+            (HEADER_128KBPS, 384)
+        };
+        let mut frame = vec![0u8; size];
+        frame[..4].copy_from_slice(&header);
+        audio.extend_from_slice(&frame);
+    }
+
+    // 先頭にVBRIタグのフレーム(128kbps)を付与 TOCは2フレームごとのバイト数/2
+    let mut data = vec![0u8; 384];
+    data[..4].copy_from_slice(&HEADER_128KBPS);
+    let mut tag = Vec::new();
+    tag.extend_from_slice(b"VBRI");
+    tag.extend_from_slice(&1u16.to_be_bytes());
+    tag.extend_from_slice(&576u16.to_be_bytes());
+    tag.extend_from_slice(&75u16.to_be_bytes());
+    tag.extend_from_slice(&((384 + audio.len()) as u32).to_be_bytes());
+    tag.extend_from_slice(&(NUM_FRAMES as u32).to_be_bytes());
+    tag.extend_from_slice(&((NUM_FRAMES / 2) as u16).to_be_bytes());
+    tag.extend_from_slice(&2u16.to_be_bytes());
+    tag.extend_from_slice(&2u16.to_be_bytes());
+    tag.extend_from_slice(&2u16.to_be_bytes());
+    for _ in 0..NUM_FRAMES / 2 {
+        tag.extend_from_slice(&(((192 + 384) / 2) as u16).to_be_bytes());
+    }
+    // フレームヘッダから32byte後に配置
+    data[36..36 + tag.len()].copy_from_slice(&tag);
+    data.extend_from_slice(&audio);
+
+    let format = get_format_information(&data)?;
+    assert!(format.xing.is_none());
+    let vbri = format.vbri.unwrap();
+    assert_eq!(vbri.version, 1);
+    assert_eq!(vbri.delay, 576);
+    assert_eq!(vbri.quality, 75);
+    assert_eq!(vbri.num_bytes, data.len() as u32);
+    assert_eq!(vbri.num_frames, NUM_FRAMES as u32);
+    assert_eq!(vbri.num_toc_entries, NUM_FRAMES / 2);
+    assert_eq!(vbri.toc_offset, 36 + 26);
+    assert_eq!(vbri.toc_size, NUM_FRAMES / 2 * 2);
+    assert!(format.vbr);
+    assert_eq!(format.num_samples, 1152 * NUM_FRAMES);
+    assert!(format.bit_rate == MP3BitRate::Kbps64);
+    assert_eq!(
+        format.average_bit_rate,
+        8 * audio.len() * 48000 / format.num_samples
+    );
+
+    // TOCによるシーク位置
+    assert_eq!(get_seek_position(&data, &format, 0), Some(384));
+    assert_eq!(
+        get_seek_position(&data, &format, 1152 * 4),
+        Some(384 + 2 * (192 + 384))
+    );
+    assert_eq!(
+        get_seek_position(&data, &format, format.num_samples),
+        Some(data.len())
+    );
+
+    // タグのフレームは音声としてデコードされない
+    let mut output = vec![1.0f32; format.num_samples];
+    let mut decoder = MP3Decoder::new();
+    let (read_size, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(read_size, data.len());
+    assert_eq!(num_decoded_samples, format.num_samples);
+    assert!(output.iter().all(|&x| x == 0.0));
+
+    // TOCから求めた位置からシークし、サンプル位置以降を出力
+    let mut buffer = [[1.0f32; 1152]; 2];
+    let (read_pos, num_frame_samples) =
+        decoder.seek_to_sample(&data, 1152 * 4 + 10, &mut buffer)?;
+    assert_eq!(read_pos, 384 + 2 * (192 + 384) + 192);
+    assert_eq!(num_frame_samples, 1152 - 10);
+    assert!(buffer[0][..num_frame_samples].iter().all(|&x| x == 0.0));
+
+    Ok(())
+}
+
+#[test]
+fn gapless_decode_test() -> Result<(), Box<dyn std::error::Error>> {
+    // 元のPCMの読み込み
+    let mut reader = hound::WavReader::open("./tests/data/alphabet02all_01.wav")?;
+    let original: Vec<f32> = reader
+        .samples::<i16>()
+        .map(|x| x.unwrap() as f32 / i16::MAX as f32)
+        .collect();
+
+    for path in [
+        "./tests/data/alphabet02all_01_32_encffmpeg.mp3",
+        "./tests/data/alphabet02all_01_128_encffmpeg.mp3",
+        "./tests/data/alphabet02all_01_320_encffmpeg.mp3",
+    ] {
+        let data = std::fs::read(path)?;
+        let format = get_format_information(&data)?;
+        let lame = format.xing.unwrap().lame.unwrap();
+        assert_eq!(&lame.encoder, b"Lavc58.13");
+        assert_eq!(lame.encoder_delay, 576);
+        assert_eq!(lame.padding, 916);
+
+        // 元のPCMとサンプル数が一致
+        let mut output = vec![0.0f32; format.num_samples];
+        let mut decoder = MP3Decoder::new();
+        decoder.set_gapless(true);
+        let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+        assert_eq!(num_decoded_samples, original.len());
+
+        // 時間位置が揃っていることをSNR(10dB以上)で確認
+        let signal: f32 = original.iter().map(|x| x * x).sum();
+        let noise: f32 = original
+            .iter()
+            .zip(output.iter())
+            .map(|(x, y)| (x - y) * (x - y))
+            .sum();
+        assert!(10.0 * (signal / noise).log10() > 10.0);
+    }
+
+    Ok(())
+}
+
+// ID3v2フレームの作成 v2.3は通常の整数, v2.4は同期安全整数でサイズを格納
+fn make_id3v2_frame(version: u8, id: &[u8; 4], flags: [u8; 2], body: &[u8]) -> Vec<u8> {
+    let size = body.len() as u32;
+    let size = if version == 4 {
+        ((size & 0x7F) | ((size & 0x3F80) << 1) | ((size & 0x1FC000) << 2)).to_be_bytes()
+    } else {
+        size.to_be_bytes()
+    };
+    let mut frame = Vec::new();
+    frame.extend_from_slice(id);
+    frame.extend_from_slice(&size);
+    frame.extend_from_slice(&flags);
+    frame.extend_from_slice(body);
+    frame
+}
+// ID3v2タグの作成
+fn make_id3v2_tag(version: u8, flags: u8, body: &[u8]) -> Vec<u8> {
+    let size = body.len();
+    let mut tag = vec![b'I', b'D', b'3', version, 0, flags];
+    tag.extend((0..4).map(|i| ((size >> (21 - 7 * i)) & 0x7F) as u8));
+    tag.extend_from_slice(body);
+    tag
+}
+
+#[test]
+fn id3v2_test() -> Result<(), Box<dyn std::error::Error>> {
+    // 無圧縮ブロックのみのzlibストリームを作成
+    fn zlib_stored(data: &[u8]) -> Vec<u8> {
+        let (mut a, mut b) = (1u32, 0u32);
+        for &x in data {
+            a = (a + x as u32) % 65521;
+            b = (b + a) % 65521;
+        }
+        let len = data.len() as u16;
+        let mut out = vec![0x78, 0x01, 0x01];
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(data);
+        out.extend_from_slice(&((b << 16) | a).to_be_bytes());
+        out
+    }
+    let to_string = |text: &ID3v2Text| text.chars().collect::<String>();
+
+    // ffmpegが出力したv2.4タグ
+    let data = std::fs::read("./tests/data/alphabet02all_01_32_encffmpeg.mp3")?;
+    let tag = decode_id3v2_tag(&data)?;
+    assert_eq!(tag.header.major_version, 4);
+    assert_eq!(tag.header.tag_size(), get_id3v2tag_size(&data)?);
+    match tag.find_frame(b"TSSE").unwrap().decode(&mut [])? {
+        ID3v2Frame::Text { id, text } => {
+            assert_eq!(&id, b"TSSE");
+            assert_eq!(text.as_str(), Some("Lavf58.76.100"));
+        }
+        _ => panic!(),
+    }
+
+    // v2.4: UTF-16, UTF-8, COMM, APIC, PRIV, CHAP/CTOC, 圧縮と非同期化
+    let mut body = Vec::new();
+    let title: Vec<u8> = [1u8, 0xFF, 0xFE]
+        .into_iter()
+        .chain("タイトル".encode_utf16().flat_map(|c| c.to_le_bytes()))
+        .chain([0, 0])
+        .collect();
+    body.extend(make_id3v2_frame(4, b"TIT2", [0, 0], &title));
+    body.extend(make_id3v2_frame(4, b"TPE1", [0, 0], b"\x03Artist\0"));
+    body.extend(make_id3v2_frame(4, b"TDRC", [0, 0], b"\x002024"));
+    body.extend(make_id3v2_frame(4, b"TXXX", [0, 0], b"\x00KEY\0VALUE"));
+    body.extend(make_id3v2_frame(4, b"COMM", [0, 0], b"\x00jpn\0Comment"));
+    body.extend(make_id3v2_frame(4, b"PRIV", [0, 0], b"owner\0\x01\x02"));
+    // 圧縮(0x08)とデータ長(0x01)
+    let lyrics = b"\x00eng\0Lyrics text";
+    let mut compressed = (lyrics.len() as u32).to_be_bytes().to_vec();
+    compressed.extend(zlib_stored(lyrics));
+    body.extend(make_id3v2_frame(4, b"USLT", [0, 0x09], &compressed));
+    // 非同期化(0x02) 0xFF 0xE0 -> 0xFF 0x00 0xE0
+    body.extend(make_id3v2_frame(
+        4,
+        b"APIC",
+        [0, 0x02],
+        b"\x00image/png\0\x03\0\xFF\x00\xE0",
+    ));
+    let mut chapter = b"ch0\0".to_vec();
+    for value in [0u32, 1000, 0xFFFFFFFF, 0xFFFFFFFF] {
+        chapter.extend_from_slice(&value.to_be_bytes());
+    }
+    chapter.extend(make_id3v2_frame(4, b"TIT2", [0, 0], b"\x00Chapter 1\0"));
+    body.extend(make_id3v2_frame(4, b"CHAP", [0, 0], &chapter));
+    body.extend(make_id3v2_frame(4, b"CTOC", [0, 0], b"toc\0\x03\x01ch0\0"));
+    body.extend([0u8; 16]);
+    let data = make_id3v2_tag(4, 0, &body);
+
+    let tag = decode_id3v2_tag(&data)?;
+    assert_eq!(tag.frames().count(), 10);
+    let mut buffer = [0u8; 256];
+    for raw in tag.frames() {
+        match raw.decode(&mut buffer)? {
+            ID3v2Frame::Text { id, text } => match &id {
+                b"TIT2" => assert_eq!(to_string(&text), "タイトル"),
+                b"TPE1" => assert_eq!(text.as_str(), Some("Artist")),
+                b"TDRC" => assert_eq!(text.as_str(), Some("2024")),
+                _ => panic!(),
+            },
+            ID3v2Frame::UserText { description, value } => {
+                assert_eq!(description.as_str(), Some("KEY"));
+                assert_eq!(value.as_str(), Some("VALUE"));
+            }
+            ID3v2Frame::Comment {
+                language,
+                description,
+                text,
+            } => {
+                assert_eq!(&language, b"jpn");
+                assert!(description.data.is_empty());
+                assert_eq!(text.as_str(), Some("Comment"));
+            }
+            ID3v2Frame::Lyrics { language, text, .. } => {
+                assert!(raw.flags.compression);
+                assert_eq!(&language, b"eng");
+                assert_eq!(text.as_str(), Some("Lyrics text"));
+            }
+            ID3v2Frame::Picture {
+                mime_type,
+                picture_type,
+                data,
+                ..
+            } => {
+                assert_eq!(mime_type, b"image/png");
+                assert_eq!(picture_type, 3);
+                assert_eq!(data, &[0xFF, 0xE0]);
+            }
+            ID3v2Frame::Private { owner, data } => {
+                assert_eq!(owner, b"owner");
+                assert_eq!(data, &[1, 2]);
+            }
+            ID3v2Frame::Chapter {
+                element_id,
+                end_time,
+                mut sub_frames,
+                ..
+            } => {
+                assert_eq!(element_id, b"ch0");
+                assert_eq!(end_time, 1000);
+                match sub_frames.next().unwrap().decode(&mut [])? {
+                    ID3v2Frame::Text { text, .. } => {
+                        assert_eq!(text.as_str(), Some("Chapter 1"))
+                    }
+                    _ => panic!(),
+                }
+            }
+            ID3v2Frame::TableOfContents {
+                element_id,
+                top_level,
+                ordered,
+                num_children,
+                child_element_ids,
+                ..
+            } => {
+                assert_eq!(element_id, b"toc");
+                assert!(top_level && ordered);
+                assert_eq!(num_children, 1);
+                assert_eq!(child_element_ids, b"ch0\0");
+            }
+            _ => panic!(),
+        }
+    }
+    // 展開が必要なフレームはバッファがなければエラー
+    assert!(tag.find_frame(b"USLT").unwrap().decode(&mut []).is_err());
+
+    // v2.3: タグ全体の非同期化と拡張ヘッダ, フッタなし
+    let mut body = vec![0, 0, 0, 6, 0, 0, 0, 0, 0, 0];
+    body.extend(make_id3v2_frame(3, b"TALB", [0, 0], b"\x00Album\xFF"));
+    body.extend(make_id3v2_frame(3, b"TRCK", [0, 0], b"\x003/12"));
+    // 0xFFの直後に0x00を挿入
+    let mut unsync = Vec::new();
+    for &b in &body {
+        unsync.push(b);
+        if b == 0xFF {
+            unsync.push(0);
+        }
+    }
+    let data = make_id3v2_tag(3, 0xC0, &unsync);
+    assert!(decode_id3v2_tag(&data).is_err());
+    let mut buffer = [0u8; 64];
+    let tag = decode_id3v2_tag_with_buffer(&data, &mut buffer)?;
+    match tag.find_frame(b"TALB").unwrap().decode(&mut [])? {
+        ID3v2Frame::Text { text, .. } => assert_eq!(to_string(&text), "Album\u{FF}"),
+        _ => panic!(),
+    }
+    match tag.find_frame(b"TRCK").unwrap().decode(&mut [])? {
+        ID3v2Frame::Text { text, .. } => assert_eq!(text.as_str(), Some("3/12")),
+        _ => panic!(),
+    }
+
+    // v2.2: 3文字のフレームIDはv2.3のIDに変換
+    let mut body = b"TT2\0\0\x06\x00Title".to_vec();
+    body.extend_from_slice(b"PIC\0\0\x07\x00JPG\x03\0\xAA");
+    let data = make_id3v2_tag(2, 0, &body);
+    let tag = decode_id3v2_tag(&data)?;
+    let ids: Vec<[u8; 4]> = tag.frames().map(|frame| frame.id).collect();
+    assert_eq!(ids, [*b"TIT2", *b"APIC"]);
+    match tag.find_frame(b"APIC").unwrap().decode(&mut [])? {
+        ID3v2Frame::Picture {
+            mime_type, data, ..
+        } => {
+            assert_eq!(mime_type, b"JPG");
+            assert_eq!(data, &[0xAA]);
+        }
+        _ => panic!(),
+    }
+
+    // v2.4のフッタはタグサイズに含める
+    let mut data = make_id3v2_tag(4, 0x10, &make_id3v2_frame(4, b"TIT2", [0, 0], b"\x00A"));
+    let footer_size = data.len() + 10;
+    data.extend_from_slice(b"3DI\x04\0\x10");
+    data.extend_from_within(6..10);
+    assert_eq!(get_id3v2tag_size(&data)?, footer_size);
+
+    Ok(())
+}
+
+#[test]
+fn id3v1_test() -> Result<(), Box<dyn std::error::Error>> {
+    // ffmpegが出力した空のID3v1タグ
+    let data = std::fs::read("./tests/data/y004_32_encffmpeg.mp3")?;
+    let tag = decode_id3v1_tag(&data).unwrap();
+    assert!(tag.title.is_empty());
+    assert!(tag.enhanced.is_none());
+    assert!(tag.genre_name().is_none());
+
+    // MPEG1 Layer3 48kHz モノラル 64kbpsの無音フレームの後にTAG+とID3v1.1タグを付与
+    const HEADER_64KBPS: [u8; 4] = [0xFF, 0xFB, 0x54, 0xC0];
+    const NUM_FRAMES: usize = 4;
+    let mut data = Vec::new();
+    for _ in 0..NUM_FRAMES {
+        let mut frame = vec![0u8; 192];
+        frame[..4].copy_from_slice(&HEADER_64KBPS);
+        data.extend_from_slice(&frame);
+    }
+    let mut enhanced = vec![0u8; ID3V1_ENHANCED_TAG_SIZE];
+    enhanced[..4].copy_from_slice(b"TAG+");
+    enhanced[4..18].copy_from_slice(b" (Extended Mix");
+    enhanced[184] = 2;
+    // 同期コードに一致するバイト列を含める
+    enhanced[185..189].copy_from_slice(&HEADER_64KBPS);
+    enhanced[215..221].copy_from_slice(b"000:10");
+    data.extend_from_slice(&enhanced);
+    let mut tag = vec![b' '; ID3V1_TAG_SIZE];
+    tag[..3].copy_from_slice(b"TAG");
+    tag[3..8].copy_from_slice(b"Title");
+    tag[33..39].copy_from_slice(b"Artist");
+    tag[93..97].copy_from_slice(b"2001");
+    tag[97..104].copy_from_slice(b"Comment");
+    tag[125] = 0;
+    tag[126] = 7;
+    tag[127] = 17;
+    data.extend_from_slice(&tag);
+
+    let tag = decode_id3v1_tag(&data).unwrap();
+    assert_eq!(tag.title, b"Title");
+    assert_eq!(tag.artist, b"Artist");
+    assert!(tag.album.is_empty());
+    assert_eq!(tag.year, b"2001");
+    assert_eq!(tag.comment, b"Comment");
+    assert_eq!(tag.track, Some(7));
+    assert_eq!(tag.genre_name(), Some("Rock"));
+    let enhanced = tag.enhanced.unwrap();
+    assert_eq!(enhanced.title, b" (Extended Mix");
+    assert_eq!(enhanced.speed, 2);
+    assert_eq!(enhanced.genre, &HEADER_64KBPS);
+    assert_eq!(enhanced.start_time, b"000:10");
+    assert!(enhanced.end_time.is_empty());
+
+    // タグは音声としてデコードされない
+    let format = get_format_information(&data)?;
+    assert_eq!(format.num_samples, 1152 * NUM_FRAMES);
+    let mut output = vec![1.0f32; 1152 * (NUM_FRAMES + 1)];
+    let mut decoder = MP3Decoder::new();
+    let (read_size, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(read_size, 192 * NUM_FRAMES);
+    assert_eq!(num_decoded_samples, 1152 * NUM_FRAMES);
+
+    Ok(())
+}
+
+// APEタグのヘッダ・フッタの作成
+fn make_ape_footer(version: u32, size: usize, num_items: u32, flags: u32) -> Vec<u8> {
+    let mut footer = b"APETAGEX".to_vec();
+    for value in [version, size as u32, num_items, flags] {
+        footer.extend_from_slice(&value.to_le_bytes());
+    }
+    footer.extend_from_slice(&[0u8; 8]);
+    footer
+}
+// APEタグの項目の作成
+fn make_ape_item(key: &str, flags: u32, value: &[u8]) -> Vec<u8> {
+    let mut item = (value.len() as u32).to_le_bytes().to_vec();
+    item.extend_from_slice(&flags.to_le_bytes());
+    item.extend_from_slice(key.as_bytes());
+    item.push(0);
+    item.extend_from_slice(value);
+    item
+}
+
+#[test]
+fn ape_lyrics3_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer3 48kHz モノラル 64kbpsの無音フレーム
+    const HEADER_64KBPS: [u8; 4] = [0xFF, 0xFB, 0x54, 0xC0];
+    const NUM_FRAMES: usize = 4;
+    let mut data = Vec::new();
+    for _ in 0..NUM_FRAMES {
+        let mut frame = vec![0u8; 192];
+        frame[..4].copy_from_slice(&HEADER_64KBPS);
+        data.extend_from_slice(&frame);
+    }
+    let audio_size = data.len();
+
+    // APEv2タグ（ヘッダ付き）
+    let mut items = Vec::new();
+    items.extend(make_ape_item("TITLE", 0, "タイトル".as_bytes()));
+    items.extend(make_ape_item("Year", 1, b"1999"));
+    items.extend(make_ape_item("Cover Art (Front)", 1 << 1, &HEADER_64KBPS));
+    let size = items.len() + 32;
+    data.extend(make_ape_footer(2000, size, 3, (1 << 31) | (1 << 29)));
+    data.extend_from_slice(&items);
+    data.extend(make_ape_footer(2000, size, 3, 1 << 31));
+
+    // Lyrics3v2タグ
+    let mut lyrics = b"LYRICSBEGIN".to_vec();
+    lyrics.extend_from_slice(b"IND00002");
+    lyrics.extend_from_slice(b"10");
+    lyrics.extend_from_slice(b"EAR00006Artist");
+    lyrics.extend_from_slice(b"LYR00011[00:01]La\xFF\xFB");
+    lyrics.extend_from_slice(format!("{:06}", lyrics.len()).as_bytes());
+    lyrics.extend_from_slice(b"LYRICS200");
+    data.extend_from_slice(&lyrics);
+
+    // ID3v1タグ
+    let mut id3v1 = vec![0u8; 128];
+    id3v1[..3].copy_from_slice(b"TAG");
+    id3v1[3..8].copy_from_slice(b"Title");
+    id3v1[63..68].copy_from_slice(b"Album");
+    id3v1[127] = 8;
+    data.extend_from_slice(&id3v1);
+
+    assert_eq!(get_trailing_tags_size(&data), data.len() - audio_size);
+
+    let tags = decode_tags(&data);
+    assert!(tags.id3v2.iter().all(|tag| tag.is_none()));
+    let ape = tags.ape.unwrap();
+    assert_eq!(ape.version, 2000);
+    assert_eq!(ape.num_items, 3);
+    assert_eq!(ape.items().count(), 3);
+    let item = ape.find_item("title").unwrap();
+    assert!(item.item_type == APEItemType::Text);
+    assert_eq!(item.as_str(), Some("タイトル"));
+    let item = ape.find_item("Year").unwrap();
+    assert!(item.read_only);
+    let item = ape.find_item("Cover Art (Front)").unwrap();
+    assert!(item.item_type == APEItemType::Binary);
+    assert_eq!(item.value, &HEADER_64KBPS);
+    assert!(item.as_str().is_none());
+
+    let lyrics3 = tags.lyrics3.unwrap();
+    let ids: Vec<[u8; 3]> = lyrics3.fields().map(|field| field.id).collect();
+    assert_eq!(ids, [*b"IND", *b"EAR", *b"LYR"]);
+    assert_eq!(lyrics3.find_field(b"LYR").unwrap().value.len(), 11);
+
+    // 統合APIはID3v2, APE, Lyrics3v2, ID3v1の順に優先
+    let to_string = |field| tags.get(field).map(|text| text.chars().collect::<String>());
+    assert_eq!(to_string(MP3TagField::Title).as_deref(), Some("タイトル"));
+    assert_eq!(to_string(MP3TagField::Year).as_deref(), Some("1999"));
+    assert_eq!(to_string(MP3TagField::Artist).as_deref(), Some("Artist"));
+    assert_eq!(to_string(MP3TagField::Album).as_deref(), Some("Album"));
+    assert_eq!(to_string(MP3TagField::Genre).as_deref(), Some("Jazz"));
+    assert!(tags.get(MP3TagField::Comment).is_none());
+
+    // タグは音声としてデコードされない
+    let format = get_format_information(&data)?;
+    assert_eq!(format.num_samples, 1152 * NUM_FRAMES);
+    let mut output = vec![1.0f32; 1152 * (NUM_FRAMES + 1)];
+    let mut decoder = MP3Decoder::new();
+    let (read_size, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(read_size, audio_size);
+    assert_eq!(num_decoded_samples, 1152 * NUM_FRAMES);
+
+    // APEv1はヘッダを持たない
+    let mut data = data[..audio_size].to_vec();
+    let items = make_ape_item("Artist", 0, b"APEv1");
+    data.extend_from_slice(&items);
+    data.extend(make_ape_footer(1000, items.len() + 32, 1, 0));
+    assert_eq!(get_trailing_tags_size(&data), items.len() + 32);
+    let tags = decode_tags(&data);
+    assert_eq!(tags.ape.unwrap().version, 1000);
+    assert_eq!(
+        tags.get(MP3TagField::Artist).unwrap().as_str(),
+        Some("APEv1")
+    );
+
+    Ok(())
+}
+
+#[test]
+fn multiple_id3v2_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer3 48kHz モノラル 64kbpsの無音フレーム
+    const HEADER_64KBPS: [u8; 4] = [0xFF, 0xFB, 0x54, 0xC0];
+    const NUM_FRAMES: usize = 4;
+    let mut frame = vec![0u8; 192];
+    frame[..4].copy_from_slice(&HEADER_64KBPS);
+
+    // 先頭に2つの連続したタグ 2つ目のタグのSEEKフレームは1フレーム後のタグを指す
+    let mut data = make_id3v2_tag(3, 0, &make_id3v2_frame(3, b"TIT2", [0, 0], b"\x00First"));
+    let mut body = make_id3v2_frame(4, b"TPE1", [0, 0], b"\x00Second");
+    body.extend(make_id3v2_frame(4, b"SEEK", [0, 0], &192u32.to_be_bytes()));
+    data.extend(make_id3v2_tag(4, 0, &body));
+    let leading_size = data.len();
+    data.extend_from_slice(&frame);
+    // 同期コードに一致するバイト列を含むタグ
+    data.extend(make_id3v2_tag(
+        4,
+        0,
+        &make_id3v2_frame(4, b"TALB", [0, 0], &[0, 0xFF, 0xFB, 0x54, 0xC0]),
+    ));
+    for _ in 1..NUM_FRAMES {
+        data.extend_from_slice(&frame);
+    }
+    let audio_end = data.len();
+
+    // 末尾にフッタ付きのタグとID3v1タグを追加
+    let body = make_id3v2_frame(4, b"TIT2", [0, 0], b"\x00Appended");
+    let appended = make_id3v2_tag(4, 0x10, &body);
+    data.extend_from_slice(&appended);
+    data.extend_from_slice(b"3DI\x04\x00\x10");
+    data.extend_from_slice(&appended[6..10]);
+    let mut id3v1 = vec![0u8; 128];
+    id3v1[..3].copy_from_slice(b"TAG");
+    data.extend_from_slice(&id3v1);
+
+    assert_eq!(get_leading_tags_size(&data), leading_size);
+    assert_eq!(get_trailing_tags_size(&data), data.len() - audio_end);
+
+    // 後ろのタグの内容を優先して統合
+    let tags = decode_tags(&data);
+    assert_eq!(tags.id3v2.iter().flatten().count(), 4);
+    let to_string = |field| tags.get(field).map(|text| text.chars().collect::<String>());
+    assert_eq!(to_string(MP3TagField::Title).as_deref(), Some("Appended"));
+    assert_eq!(to_string(MP3TagField::Artist).as_deref(), Some("Second"));
+    assert!(tags.find_id3v2_frame(b"TALB").is_some());
+
+    // タグは音声としてデコードされない
+    let format = get_format_information(&data)?;
+    assert_eq!(format.num_samples, 1152 * NUM_FRAMES);
+    let mut output = vec![1.0f32; 1152 * (NUM_FRAMES + 1)];
+    let mut decoder = MP3Decoder::new();
+    let (read_size, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(read_size, audio_end);
+    assert_eq!(num_decoded_samples, 1152 * NUM_FRAMES);
+
+    Ok(())
+}
+
+#[test]
+fn replay_gain_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer2 48kHz 64kbps モノラル 第0サブバンドのみに一定値(14/15)を持つフレームを並べる
+    const HEADER: [u8; 4] = [0xFF, 0xFD, 0x44, 0xC0];
+    const FRAME_SIZE: usize = 192;
+    const NUM_FRAMES: usize = 10;
+    let mut audio = vec![0u8; FRAME_SIZE * NUM_FRAMES];
+    for frame in audio.chunks_mut(FRAME_SIZE) {
+        frame[..4].copy_from_slice(&HEADER);
+        let mut pos = 32;
+        put_bits(frame, &mut pos, 3, 4);
+        pos += 2 * 4 + 8 * 4 + 12 * 3 + 4 * 2;
+        put_bits(frame, &mut pos, 2, 2);
+        put_bits(frame, &mut pos, 3, 6);
+        for _ in 0..36 {
+            put_bits(frame, &mut pos, 14, 4);
+        }
+    }
+
+    // ID3v2: UTF-16のTXXXでトラックゲイン, RVA2でアルバムゲイン(-3082/512dB)とピーク(0x7800/2^15)
+    let mut txxx = vec![1u8];
+    for text in ["replaygain_track_gain", "+6.0206 dB"] {
+        txxx.extend_from_slice(&[0xFF, 0xFE]);
+        txxx.extend(text.encode_utf16().flat_map(|c| c.to_le_bytes()));
+        txxx.extend_from_slice(&[0, 0]);
+    }
+    let mut body = make_id3v2_frame(4, b"TXXX", [0, 0], &txxx);
+    let mut rva2 = b"album\0".to_vec();
+    rva2.extend_from_slice(&[2, 0, 0, 0]);
+    rva2.push(1);
+    rva2.extend_from_slice(&(-3082i16).to_be_bytes());
+    rva2.extend_from_slice(&[16, 0x78, 0x00]);
+    body.extend(make_id3v2_frame(4, b"RVA2", [0, 0], &rva2));
+    let mut data = make_id3v2_tag(4, 0, &body);
+    data.extend_from_slice(&audio);
+
+    // APE: トラックゲインはID3v2の値を優先
+    let mut items = make_ape_item("REPLAYGAIN_TRACK_GAIN", 0, b"+1.00 dB");
+    items.extend(make_ape_item("REPLAYGAIN_TRACK_PEAK", 0, b"0.933333"));
+    let size = items.len() + 32;
+    data.extend(make_ape_footer(2000, size, 2, (1 << 31) | (1 << 29)));
+    data.extend_from_slice(&items);
+    data.extend(make_ape_footer(2000, size, 2, 1 << 31));
+
+    let replay_gain = get_replay_gain(&data);
+    assert!((replay_gain.track_gain.unwrap() - 6.0206).abs() < 1e-4);
+    assert!((replay_gain.track_peak.unwrap() - 0.933333).abs() < 1e-4);
+    assert!((replay_gain.album_gain.unwrap() + 3082.0 / 512.0).abs() < 1e-4);
+    assert!((replay_gain.album_peak.unwrap() - 0.9375).abs() < 1e-4);
+
+    // ReplayGainを適用しない場合の出力
+    let decode = |decoder: &mut MP3Decoder| -> Result<Vec<f32>, MP3DecodeError> {
+        let mut output = vec![0.0f32; 1152 * NUM_FRAMES];
+        decoder.decode_whole(&data, &mut [&mut output])?;
+        Ok(output)
+    };
+    let mut decoder = MP3Decoder::new();
+    let reference = decode(&mut decoder)?;
+
+    // アルバムゲイン(約-6dB)
+    decoder.set_replay_gain_mode(MP3ReplayGainMode::Album);
+    let scale = 10.0f32.powf(-3082.0 / 512.0 / 20.0);
+    for (out, reference) in decode(&mut decoder)?.iter().zip(&reference) {
+        assert!((out - reference * scale).abs() < 1e-5);
+    }
+
+    // トラックゲイン(約+6dB) クリッピング防止なし
+    decoder.set_replay_gain_mode(MP3ReplayGainMode::Track);
+    decoder.set_clipping_prevention(false);
+    for (out, reference) in decode(&mut decoder)?.iter().zip(&reference) {
+        assert!((out - reference * 2.0).abs() < 1e-4);
+    }
+
+    // クリッピング防止ありではピークが1.0になるように抑える
+    decoder.set_clipping_prevention(true);
+    let output = decode(&mut decoder)?;
+    for &out in &output[1152..] {
+        assert!((out - 14.0 / 15.0 / 0.933333).abs() < 1e-3);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn stream_decode_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer2 48kHz 64kbps モノラル 第0サブバンドのみに一定値(14/15)を持つフレームを並べる
+    const HEADER: [u8; 4] = [0xFF, 0xFD, 0x44, 0xC0];
+    const FRAME_SIZE: usize = 192;
+    const NUM_FRAMES: usize = 10;
+    let mut audio = vec![0u8; FRAME_SIZE * NUM_FRAMES];
+    for frame in audio.chunks_mut(FRAME_SIZE) {
+        frame[..4].copy_from_slice(&HEADER);
+        let mut pos = 32;
+        put_bits(frame, &mut pos, 3, 4);
+        pos += 2 * 4 + 8 * 4 + 12 * 3 + 4 * 2;
+        put_bits(frame, &mut pos, 2, 2);
+        put_bits(frame, &mut pos, 3, 6);
+        for _ in 0..36 {
+            put_bits(frame, &mut pos, 14, 4);
+        }
+    }
+    // 途中のフレームの未使用領域の末尾に、フレーム全体を指すAPEタグのフッタと同じバイト列を置く
+    let footer = &mut audio[FRAME_SIZE * 5 - 32..FRAME_SIZE * 5];
+    footer[..8].copy_from_slice(b"APETAGEX");
+    footer[8..12].copy_from_slice(&1000u32.to_le_bytes());
+    footer[12..16].copy_from_slice(&(FRAME_SIZE as u32).to_le_bytes());
+
+    // 内部バッファより大きいID3v2タグを先頭に、ID3v1タグを末尾に付与
+    let body = make_id3v2_frame(4, b"PRIV", [0, 0], &vec![0xFF; 2 * MP3_STREAM_BUFFER_SIZE]);
+    let mut data = make_id3v2_tag(4, 0, &body);
+    data.extend_from_slice(&audio);
+    let mut id3v1 = vec![0u8; ID3V1_TAG_SIZE];
+    id3v1[..3].copy_from_slice(b"TAG");
+    data.extend_from_slice(&id3v1);
+
+    let mut reference = vec![0.0f32; 1152 * NUM_FRAMES];
+    MP3Decoder::new().decode_whole(&data, &mut [&mut reference])?;
+
+    // 任意の長さの断片で与えてもdecode_wholeと同じ結果になる
+    for chunk_size in [1, 7, 1000, data.len()] {
+        let mut decoder = MP3StreamDecoder::new();
+        let mut buffer = [[0.0f32; 1152]; 2];
+        let mut output = Vec::new();
+        assert!(matches!(
+            decoder.decode(&mut buffer),
+            Err(MP3DecodeError::NeedMoreData)
+        ));
+        for chunk in data.chunks(chunk_size) {
+            let mut chunk = chunk;
+            while !chunk.is_empty() {
+                let size = decoder.feed(chunk);
+                chunk = &chunk[size..];
+                loop {
+                    match decoder.decode(&mut buffer) {
+                        Ok((header, concealed)) => {
+                            assert!(header.layer == MP3Layer::Layer2);
+                            assert!(!concealed);
+                            output.extend_from_slice(&buffer[0]);
+                        }
+                        Err(MP3DecodeError::NeedMoreData) => break,
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+            }
+        }
+
+        // 終端を通知して残りを取り出す
+        decoder.flush();
+        loop {
+            match decoder.decode(&mut buffer) {
+                Ok(_) => output.extend_from_slice(&buffer[0]),
+                Err(MP3DecodeError::EndOfStream) => break,
+                Err(e) => return Err(e.into()),
+            }
+        }
+        assert!(output == reference);
+    }
+
+    Ok(())
+}
+
+#[cfg(feature = "std")]
+#[test]
+fn reader_test() -> Result<(), Box<dyn std::error::Error>> {
+    use baremp3::reader::*;
+    use std::io::Cursor;
+
+    // MPEG1 Layer2 48kHz 64kbps モノラル 第0サブバンドのみに一定値(14/15)を持つフレームを並べる
+    const HEADER: [u8; 4] = [0xFF, 0xFD, 0x44, 0xC0];
+    const FRAME_SIZE: usize = 192;
+    const NUM_FRAMES: usize = 10;
+    let mut audio = vec![0u8; FRAME_SIZE * NUM_FRAMES];
+    for frame in audio.chunks_mut(FRAME_SIZE) {
+        frame[..4].copy_from_slice(&HEADER);
+        let mut pos = 32;
+        put_bits(frame, &mut pos, 3, 4);
+        pos += 2 * 4 + 8 * 4 + 12 * 3 + 4 * 2;
+        put_bits(frame, &mut pos, 2, 2);
+        put_bits(frame, &mut pos, 3, 6);
+        for _ in 0..36 {
+            put_bits(frame, &mut pos, 14, 4);
+        }
+    }
+    let mut data = make_id3v2_tag(4, 0, &make_id3v2_frame(4, b"TIT2", [0, 0], b"\0title"));
+    data.extend_from_slice(&audio);
+
+    let mut reference = vec![0.0f32; 1152 * NUM_FRAMES];
+    MP3Decoder::new().decode_whole(&data, &mut [&mut reference])?;
+
+    // フレーム単位の読み出し
+    let mut reader = MP3Reader::new(Cursor::new(&data))?;
+    let mut num_frames = 0;
+    for frame in &mut reader {
+        let frame = frame?;
+        assert_eq!(frame.num_channels, 1);
+        assert_eq!(frame.num_samples, 1152);
+        assert!(frame.header.layer == MP3Layer::Layer2);
+        let start = 1152 * num_frames;
+        assert!(frame.samples[0][..1152] == reference[start..start + 1152]);
+        num_frames += 1;
+    }
+    assert_eq!(num_frames, NUM_FRAMES);
+
+    // フレーム境界をまたぐサンプル単位の読み出し
+    reader.rewind()?;
+    let mut output = Vec::new();
+    let mut buffer = [0.0f32; 1000];
+    loop {
+        let num_samples = reader.read_samples(&mut buffer)?;
+        if num_samples == 0 {
+            break;
+        }
+        output.extend_from_slice(&buffer[..num_samples]);
+    }
+    assert!(output == reference);
+
+    // 16bit整数での読み出し
+    reader.rewind()?;
+    let mut output = vec![0i16; 1152 * NUM_FRAMES + 1];
+    assert_eq!(reader.read_interleaved_i16(&mut output)?, 1152 * NUM_FRAMES);
+    for (out, reference) in output.iter().zip(&reference) {
+        assert_eq!(*out, (reference * i16::MAX as f32).round() as i16);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn frame_iterator_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer3 48kHz モノラル 64kbps(192byte)と128kbps(384byte)の無音フレームを交互に並べる
+    const HEADER_64KBPS: [u8; 4] = [0xFF, 0xFB, 0x54, 0xC0];
+    const HEADER_128KBPS: [u8; 4] = [0xFF, 0xFB, 0x94, 0xC0];
+    const NUM_FRAMES: usize = 6;
+
+    // 先頭にID3v2タグとXingタグのフレーム(64kbps)を付与
+    let mut data = make_id3v2_tag(4, 0, &make_id3v2_frame(4, b"TIT2", [0, 0], b"\0title"));
+    let mut xing = vec![0u8; 192];
+    xing[..4].copy_from_slice(&HEADER_64KBPS);
+    xing[21..25].copy_from_slice(b"Xing");
+    data.extend_from_slice(&xing);
+
+    // フレーム間に同期コードを含まないデータ・不正なヘッダを挟む
+    let mut expected = Vec::new();
+    for i in 0..NUM_FRAMES {
+        let (header, size) = if i % 2 == 0 {
+            (HEADER_64KBPS, 192)
+        } else {
+            (HEADER_128KBPS, 384)
+        };
+        match i {
+            2 => data.extend_from_slice(&[0x00, 0x12, 0x34]),
+            4 => data.extend_from_slice(&[0xFF, 0xFF, 0xFF]),
+            _ => {}
+        }
+        let mut frame = vec![0u8; size];
+        frame[..4].copy_from_slice(&header);
+        // メインデータの先頭に目印を置く
+        frame[21] = i as u8 + 1;
+        expected.push((data.len(), size));
+        data.extend_from_slice(&frame);
+    }
+
+    // 末尾にID3v1タグを付与
+    let mut id3v1 = vec![0u8; ID3V1_TAG_SIZE];
+    id3v1[..3].copy_from_slice(b"TAG");
+    data.extend_from_slice(&id3v1);
+
+    let frames: Vec<MP3FrameInformation> = MP3FrameIterator::new(&data).collect();
+    assert_eq!(frames.len(), NUM_FRAMES);
+    for (i, (frame, &(offset, size))) in frames.iter().zip(&expected).enumerate() {
+        assert_eq!(frame.offset, offset);
+        assert_eq!(frame.size, size);
+        assert!(frame.header.layer == MP3Layer::Layer3);
+        assert!(frame.header.sampling_rate == MP3SamplingRate::Hz48000);
+        assert_eq!(frame.side_info.maindata_begin, 0);
+        assert_eq!(frame.maindata, offset + 21..offset + size);
+        assert_eq!(data[frame.maindata.start], i as u8 + 1);
+    }
+
+    assert_eq!(
+        frames
+            .iter()
+            .map(|frame| get_num_samples_per_frame(&frame.header))
+            .sum::<usize>(),
+        1152 * NUM_FRAMES
+    );
+
+    Ok(())
+}
+
+#[test]
+fn seek_test() -> Result<(), Box<dyn std::error::Error>> {
+    for path in [
+        "./tests/data/y004_128_encffmpeg.mp3",
+        "./tests/data/y004_32_encffmpeg.mp3",
+        "./tests/data/alphabet02all_01_32_encffmpeg.mp3",
+        "./tests/data/y004_320_encdist10.mpg",
+    ] {
+        let data = std::fs::read(path)?;
+        let format = get_format_information(&data)?;
+
+        for gapless in [false, true] {
+            // 先頭からデコードした結果を正解とする
+            let mut decoder = MP3Decoder::new();
+            decoder.set_gapless(gapless);
+            let mut reference = vec![vec![0.0f32; format.num_samples]; format.num_channels];
+            let (left, right) = reference.split_at_mut(1);
+            let mut output: Vec<&mut [f32]> = vec![&mut left[0]];
+            if let Some(right) = right.first_mut() {
+                output.push(right);
+            }
+            let (_, num_samples) = decoder.decode_whole(&data, &mut output)?;
+
+            // シーク位置からのデコード結果が先頭からのデコード結果と一致
+            for target in [
+                0,
+                1,
+                1152 * 5 + 100,
+                1152 * 20 + 1151,
+                1152 * 100 + 7,
+                num_samples - 10,
+            ] {
+                let mut buffer = [[0.0f32; 1152]; 2];
+                let (mut read_pos, mut num_frame_samples) =
+                    decoder.seek_to_sample(&data, target, &mut buffer)?;
+                let mut sample = target;
+                for _ in 0..3 {
+                    let num_compare = min(num_frame_samples, num_samples - sample);
+                    for ch in 0..format.num_channels {
+                        assert!(
+                            buffer[ch][..num_compare]
+                                == reference[ch][sample..sample + num_compare]
+                        );
+                    }
+                    sample += num_compare;
+                    if sample == num_samples {
+                        break;
+                    }
+                    let (size, header, _, _) =
+                        decoder.decode_frame(&data[read_pos..], &mut buffer)?;
+                    num_frame_samples = get_num_samples_per_frame(&header);
+                    read_pos += size;
+                }
+            }
+
+            // 終端以降へのシーク
+            let mut buffer = [[0.0f32; 1152]; 2];
+            let (read_pos, num_frame_samples) =
+                decoder.seek_to_sample(&data, format.num_samples + 1152 * 10, &mut buffer)?;
+            assert_eq!(num_frame_samples, 0);
+            assert!(matches!(
+                decoder.decode_frame(&data[read_pos..], &mut [[0.0f32; 1152]; 2]),
+                Err(MP3DecodeError::EndOfStream)
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn seek_vbr_test() -> Result<(), Box<dyn std::error::Error>> {
+    // ビットレートの異なるファイル(MPEG1 Layer3 44.1kHz ステレオ)のフレームを区間ごとに並べてVBRにする
+    const NUM_CHUNKS: usize = 6;
+    const NUM_CHUNK_FRAMES: usize = 60;
+    let sources = [
+        std::fs::read("./tests/data/y004_128_encffmpeg.mp3")?,
+        std::fs::read("./tests/data/y004_32_encffmpeg.mp3")?,
+        std::fs::read("./tests/data/y004_320_encdist10.mpg")?,
+    ];
+    let mut audio = Vec::new();
+    let mut frame_offsets = Vec::new();
+    for chunk in 0..NUM_CHUNKS {
+        let source = &sources[chunk % sources.len()];
+        for frame in MP3FrameIterator::new(source)
+            .skip(chunk * NUM_CHUNK_FRAMES)
+            .take(NUM_CHUNK_FRAMES)
+        {
+            frame_offsets.push(audio.len());
+            audio.extend_from_slice(&source[frame.offset..frame.offset + frame.size]);
+        }
+    }
+    let num_frames = frame_offsets.len();
+    assert_eq!(num_frames, NUM_CHUNKS * NUM_CHUNK_FRAMES);
+
+    // 先頭にXingタグのフレーム(128kbps, 417byte)を付与 TOCは百分率ごとのフレーム位置
+    const TAG_FRAME_SIZE: usize = 417;
+    let num_bytes = TAG_FRAME_SIZE + audio.len();
+    let mut data = vec![0u8; TAG_FRAME_SIZE];
+    data[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+    let mut tag = Vec::new();
+    tag.extend_from_slice(b"Xing");
+    tag.extend_from_slice(&0x7u32.to_be_bytes());
+    tag.extend_from_slice(&(num_frames as u32).to_be_bytes());
+    tag.extend_from_slice(&(num_bytes as u32).to_be_bytes());
+    tag.extend(
+        (0..100).map(|i| {
+            ((TAG_FRAME_SIZE + frame_offsets[i * num_frames / 100]) * 256 / num_bytes) as u8
+        }),
+    );
+    // サイドインフォメーション(32byte)の直後に配置
+    data[36..36 + tag.len()].copy_from_slice(&tag);
+    data.extend_from_slice(&audio);
+
+    let format = get_format_information(&data)?;
+    assert!(format.vbr);
+    assert!(format.xing.unwrap().toc.is_some());
+    assert_eq!(format.num_samples, 1152 * num_frames);
+
+    // 先頭からデコードした結果を正解とする
+    let mut decoder = MP3Decoder::new();
+    let mut reference = vec![vec![0.0f32; format.num_samples]; 2];
+    let (left, right) = reference.split_at_mut(1);
+    decoder.decode_whole(&data, &mut [&mut left[0], &mut right[0]])?;
+
+    // 各区間の中ほどへのシーク結果が先頭からのデコード結果と一致
+    for chunk in 0..NUM_CHUNKS {
+        let target = 1152 * (chunk * NUM_CHUNK_FRAMES + 30) + 500;
+        let mut buffer = [[0.0f32; 1152]; 2];
+        let (read_pos, num_frame_samples) = decoder.seek_to_sample(&data, target, &mut buffer)?;
+        assert_eq!(num_frame_samples, 1152 - 500);
+        assert_eq!(read_pos, TAG_FRAME_SIZE + frame_offsets[target / 1152 + 1]);
+        for ch in 0..2 {
+            assert!(
+                buffer[ch][..num_frame_samples]
+                    == reference[ch][target..target + num_frame_samples]
+            );
+        }
+
+        // 続くフレームも一致
+        decoder.decode_frame(&data[read_pos..], &mut buffer)?;
+        let next = target + num_frame_samples;
+        for ch in 0..2 {
+            assert!(buffer[ch] == reference[ch][next..next + 1152]);
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn seek_index_test() -> Result<(), Box<dyn std::error::Error>> {
+    let data = std::fs::read("./tests/data/y004_128_encffmpeg.mp3")?;
+    let format = get_format_information(&data)?;
+    let num_frames = format.num_samples / format.num_samples_per_frame;
+
+    // 先頭からデコードした結果を正解とする
+    let mut decoder = MP3Decoder::new();
+    decoder.set_gapless(true);
+    let mut reference = vec![vec![0.0f32; format.num_samples]; 2];
+    let (left, right) = reference.split_at_mut(1);
+    let (_, num_samples) = decoder.decode_whole(&data, &mut [&mut left[0], &mut right[0]])?;
+
+    for interval in [1, 7, 100] {
+        // インデックス作成
+        let index_size = get_seek_index_size(num_frames, interval);
+        let mut index = vec![0u8; index_size];
+        assert!(matches!(
+            build_seek_index(&data, interval, &mut index[..index_size - 1]),
+            Err(MP3DecodeError::InsufficientBuffer)
+        ));
+        let size = build_seek_index(&data, interval, &mut index)?;
+        assert_eq!(size, index_size);
+
+        // 読み込んだインデックスでのシーク結果はインデックスなしの場合と一致
+        let seek_index = MP3SeekIndex::load(&index, &data)?;
+        assert_eq!(seek_index.interval(), interval);
+        assert_eq!(seek_index.num_entries(), num_frames.div_ceil(interval));
+        assert_eq!(seek_index.num_samples(), format.num_samples as u64);
+        assert_eq!(seek_index.entry(1).unwrap().sample, 1152 * interval as u64);
+        for target in [0, 1152 * 3 + 5, 1152 * 150 + 1000, num_samples - 1] {
+            let mut expected_buffer = [[0.0f32; 1152]; 2];
+            let expected = decoder.seek_to_sample(&data, target, &mut expected_buffer)?;
+            let mut buffer = [[0.0f32; 1152]; 2];
+            let (read_pos, num_frame_samples) =
+                decoder.seek_to_sample_with_index(&data, &seek_index, target, &mut buffer)?;
+            assert_eq!((read_pos, num_frame_samples), expected);
+            assert!(buffer == expected_buffer);
+            let num_compare = min(num_frame_samples, num_samples - target);
+            for ch in 0..2 {
+                assert!(buffer[ch][..num_compare] == reference[ch][target..target + num_compare]);
+            }
+        }
+
+        // データサイズ・先頭フレームが異なるデータには使用できない
+        assert!(MP3SeekIndex::load(&index, &data[..data.len() - 1]).is_err());
+        let mut modified = data.clone();
+        let first_offset = seek_index.entry(0).unwrap().offset as usize;
+        modified[first_offset + 2] ^= 0x02;
+        assert!(MP3SeekIndex::load(&index, &modified).is_err());
+        assert!(MP3SeekIndex::load(&index[..size - 1], &data).is_err());
+    }
+
+    Ok(())
+}
+
+#[test]
+fn resync_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer3 48kHz モノラル 64kbps(192byte)の無音フレームを並べる
+    const HEADER: [u8; 4] = [0xFF, 0xFB, 0x54, 0xC0];
+    const FRAME_SIZE: usize = 192;
+    const NUM_FRAMES: usize = 6;
+    let mut data = Vec::new();
+    let mut frame_offsets = Vec::new();
+    let mut garbage = 0..0;
+    for i in 0..NUM_FRAMES {
+        // 3フレーム目の後ろに偽の同期ヘッダを含む不正なデータを挟む
+        if i == 3 {
+            let start = data.len();
+            data.extend_from_slice(&[0x00, 0x11]);
+            data.extend_from_slice(&HEADER);
+            data.extend_from_slice(&[0u8; 40]);
+            garbage = start..data.len();
+        }
+        let mut frame = vec![0u8; FRAME_SIZE];
+        frame[..4].copy_from_slice(&HEADER);
+        frame_offsets.push(data.len());
+        data.extend_from_slice(&frame);
+    }
+
+    // フレームごとにデコードし、フレーム位置と読み飛ばした範囲を記録
+    let decode = |num_sync_frames: usize| -> Result<_, MP3DecodeError> {
+        let mut decoder = MP3Decoder::new();
+        decoder.set_num_sync_frames(num_sync_frames);
+        let mut buffer = [[0.0f32; 1152]; 2];
+        let (mut pos, mut offsets, mut skipped) = (0, Vec::new(), Vec::new());
+        loop {
+            match decoder.decode_frame(&data[pos..], &mut buffer) {
+                Ok((size, _, _, _)) => {
+                    let mut frame_start = pos;
+                    if let Some(range) = decoder.skipped_range() {
+                        skipped.push(pos + range.start..pos + range.end);
+                        frame_start += range.end;
+                    }
+                    offsets.push(frame_start);
+                    pos += size;
+                }
+                Err(MP3DecodeError::EndOfStream) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((offsets, skipped))
+    };
+
+    // 連続するフレームで検証すれば偽の同期ヘッダを読み飛ばす
+    let (offsets, skipped) = decode(3)?;
+    assert_eq!(offsets, frame_offsets);
+    assert_eq!(skipped, vec![garbage.clone()]);
+
+    // 候補のみの検査では偽の同期ヘッダをフレームとして扱う
+    let (offsets, _) = decode(1)?;
+    assert!(offsets.contains(&(garbage.start + 2)));
+    assert!(!offsets.contains(&frame_offsets[3]));
+
+    // フレームの列挙でも次のヘッダまで検証して偽の同期ヘッダを読み飛ばす
+    let offsets: Vec<usize> = MP3FrameIterator::new(&data).map(|f| f.offset).collect();
+    assert_eq!(offsets, frame_offsets);
+    let mut iter = MP3FrameIterator::new(&data);
+    iter.set_num_sync_frames(1);
+    let offsets: Vec<usize> = iter.map(|f| f.offset).collect();
+    assert!(offsets.contains(&(garbage.start + 2)));
+
+    Ok(())
+}
+
+#[test]
+fn concealment_test() -> Result<(), Box<dyn std::error::Error>> {
+    // MPEG1 Layer2 48kHz 64kbps モノラル 第0サブバンドのみに一定値を持つフレーム
+    const HEADER: [u8; 4] = [0xFF, 0xFD, 0x44, 0xC0];
+    const FRAME_SIZE: usize = 192;
+    let mut frame = [0u8; FRAME_SIZE];
+    frame[..4].copy_from_slice(&HEADER);
+    let mut pos = 32;
+    put_bits(&mut frame, &mut pos, 3, 4);
+    pos += 2 * 4 + 8 * 4 + 12 * 3 + 4 * 2;
+    put_bits(&mut frame, &mut pos, 2, 2);
+    put_bits(&mut frame, &mut pos, 3, 6);
+    for _ in 0..36 {
+        put_bits(&mut frame, &mut pos, 14, 4);
+    }
+
+    // 欠落したフレームを各方法で隠蔽
+    let conceal = |mode: MP3ConcealmentMode| -> Result<_, MP3DecodeError> {
+        let mut decoder = MP3Decoder::new();
+        decoder.set_concealment_mode(mode);
+        let mut buffer = [[0.0f32; 1152]; 1];
+        let mut header = None;
+        for _ in 0..3 {
+            let (_, frame_header, _, concealed) = decoder.decode_frame(&frame, &mut buffer)?;
+            assert!(!concealed);
+            header = Some(frame_header);
+        }
+        let header = header.unwrap();
+        let mut concealed = [[0.0f32; 1152]; 1];
+        decoder.conceal_frame(&header, &mut concealed)?;
+        assert!(decoder.is_concealed());
+        assert!(!decoder.decode_frame(&frame, &mut buffer)?.3);
+        Ok(concealed[0])
+    };
+
+    // 無音は合成フィルタの残響の後に0
+    let output = conceal(MP3ConcealmentMode::Silence)?;
+    assert!(output[512..].iter().all(|&x| x == 0.0));
+
+    // 繰り返しは直前のフレームと同じ信号が続く
+    let output = conceal(MP3ConcealmentMode::Repeat)?;
+    for &out in output.iter() {
+        assert!((out - 14.0 / 15.0).abs() < 1e-3);
+    }
+
+    // フェードアウトは減衰する
+    let output = conceal(MP3ConcealmentMode::FadeOut)?;
+    assert!((output[0] - 14.0 / 15.0).abs() < 1e-3);
+    assert!(output[1151].abs() < output[0].abs() / 2.0);
+    assert!(output[1151].abs() > 0.0);
+
+    // 途中で切れた末尾のフレームはエラーとせず隠蔽
+    let mut data = frame.repeat(4);
+    data.extend_from_slice(&frame[..6]);
+    let mut decoder = MP3Decoder::new();
+    decoder.set_concealment_mode(MP3ConcealmentMode::Repeat);
+    let mut output = vec![0.0f32; 1152 * 5];
+    let (_, num_decoded_samples) = decoder.decode_whole(&data, &mut [&mut output])?;
+    assert_eq!(num_decoded_samples, 1152 * 5);
+    for &out in &output[1152 * 4..] {
+        assert!((out - 14.0 / 15.0).abs() < 1e-3);
+    }
+
+    // Layer3のフレームを1つ破損させ、後続フレームがビットリザーバで参照するデータを欠落させる
+    let data = std::fs::read("./tests/data/y004_128_encffmpeg.mp3")?;
+    let frames: Vec<_> = MP3FrameIterator::new(&data).collect();
+    let lost = (10..frames.len() - 1)
+        .find(|&i| frames[i + 1].side_info.maindata_begin > 0)
+        .unwrap();
+    let mut damaged = data.clone();
+    damaged[frames[lost].offset..frames[lost].offset + frames[lost].size].fill(0);
+
+    // フレームごとにデコードし、隠蔽したフレーム番号と出力を記録
+    let decode = |data: &[u8]| -> Result<_, MP3DecodeError> {
+        let mut decoder = MP3Decoder::new();
+        decoder.set_concealment_mode(MP3ConcealmentMode::FadeOut);
+        let mut buffer = [[0.0f32; 1152]; 2];
+        let (mut pos, mut concealed, mut output) = (0, Vec::new(), Vec::new());
+        loop {
+            match decoder.decode_frame(&data[pos..], &mut buffer) {
+                Ok((size, header, _, is_concealed)) => {
+                    if is_concealed {
+                        concealed.push(output.len());
+                    }
+                    let num_samples = get_num_samples_per_frame(&header);
+                    output.push([
+                        buffer[0][..num_samples].to_vec(),
+                        buffer[1][..num_samples].to_vec(),
+                    ]);
+                    pos += size;
+                }
+                Err(MP3DecodeError::EndOfStream) => break,
+                Err(e) => return Err(e),
+            }
+        }
+        Ok((concealed, output))
+    };
+    let (concealed, reference) = decode(&data)?;
+    assert!(concealed.is_empty());
+    let (concealed, output) = decode(&damaged)?;
+    assert_eq!(output.len(), reference.len() - 1);
+    assert!(concealed.contains(&lost));
+    assert!(concealed.iter().all(|&i| i >= lost && i < lost + 4));
+
+    // 欠落前は一致し、隠蔽したフレームから十分離れると再び一致する
+    let last_concealed = *concealed.last().unwrap();
+    assert_eq!(output[..lost], reference[..lost]);
+    assert_eq!(
+        output[last_concealed + 3..],
+        reference[last_concealed + 4..]
+    );
+    for frame in &output[lost..=last_concealed] {
+        assert!(frame.iter().flatten().all(|x| x.is_finite()));
+    }
+
+    // ファイル全体のデコードも中断しない
+    let format = get_format_information(&data)?;
+    let mut output = vec![vec![0.0f32; format.num_samples]; format.num_channels];
+    let mut output: Vec<&mut [f32]> = output.iter_mut().map(|ch| ch.as_mut_slice()).collect();
+    let (_, num_decoded_samples) = MP3Decoder::new().decode_whole(&damaged, &mut output)?;
+    assert_eq!(num_decoded_samples, format.num_samples - 1152);
+
+    Ok(())
+}
+
+#[test]
+fn fuzz_regression_test() -> Result<(), Box<dyn std::error::Error>> {
+    // ファジング(fuzz/)で見つかった不正なデータをターゲットと同じ手順で処理し、パニックしないことを確認
+    // 返り値のエラーは問わない
+    let read_corpus = |target: &str| -> std::io::Result<Vec<Vec<u8>>> {
+        let mut corpus = Vec::new();
+        for entry in std::fs::read_dir(format!("./tests/data/fuzz/{}", target))? {
+            corpus.push(std::fs::read(entry?.path())?);
+        }
+        Ok(corpus)
+    };
+
+    // decode_frame: 先頭1byteでデコードオプションを選択
+    for data in read_corpus("decode_frame")? {
+        let Some((&options, data)) = data.split_first() else {
+            continue;
+        };
+        let mut decoder = MP3Decoder::new();
+        decoder.set_crc_policy(match options & 0x3 {
+            0 => MP3CrcPolicy::Ignore,
+            1 => MP3CrcPolicy::Reject,
+            _ => MP3CrcPolicy::Conceal,
+        });
+        decoder.set_concealment_mode(match (options >> 2) & 0x3 {
+            0 => MP3ConcealmentMode::Silence,
+            1 => MP3ConcealmentMode::Repeat,
+            _ => MP3ConcealmentMode::FadeOut,
+        });
+        decoder.set_num_sync_frames(1 + ((options >> 4) & 0x3) as usize);
+        decoder.set_gapless((options & 0x40) != 0);
+        decoder.set_deemphasis((options & 0x80) != 0);
+        let mut buffer = [[0.0f32; 1152]; 2];
+        let mut pos = 0;
+        while let Ok((size, header, _, _)) = decoder.decode_frame(&data[pos..], &mut buffer) {
+            if (size & 1) != 0 {
+                let _ = decoder.conceal_frame(&header, &mut buffer);
+            }
+            pos += size;
+            if pos >= data.len() {
+                break;
+            }
+        }
+    }
+
+    // get_format_information: フォーマット情報を取得してファイル全体をデコード
+    for data in read_corpus("get_format_information")? {
+        let Ok(format) = get_format_information(&data) else {
+            continue;
+        };
+        let _ = get_replay_gain(&data);
+        if format.num_samples > (1 << 20) {
+            continue;
+        }
+        let mut decoder = MP3Decoder::new();
+        decoder.set_gapless(true);
+        let mut output = vec![vec![0.0f32; format.num_samples]; format.num_channels];
+        let mut output: Vec<&mut [f32]> = output.iter_mut().map(|ch| ch.as_mut_slice()).collect();
+        let _ = decoder.decode_whole(&data, &mut output);
+        let mut buffer = [[0.0f32; 1152]; 2];
+        let _ = decoder.seek_to_sample(&data, format.num_samples / 2, &mut buffer);
+    }
+
+    // tags: 全てのタグの項目を読み出す
+    for data in read_corpus("tags")? {
+        let _ = get_leading_tags_size(&data);
+        let _ = get_trailing_tags_size(&data);
+        let tags = decode_tags(&data);
+        for field in [
+            MP3TagField::Title,
+            MP3TagField::Artist,
+            MP3TagField::Album,
+            MP3TagField::Year,
+            MP3TagField::Comment,
+            MP3TagField::Track,
+            MP3TagField::Genre,
+        ] {
+            if let Some(text) = tags.get(field) {
+                let _ = text.chars().count();
+            }
+        }
+        let _ = tags.replay_gain();
+        let mut buffer = [0u8; 4096];
+        for tag in tags.id3v2.iter().flatten() {
+            for raw in tag.frames() {
+                let _ = raw.decode(&mut buffer);
+            }
+        }
+        if let Some(tag) = decode_ape_tag(&data) {
+            for item in tag.items() {
+                let _ = item.as_str();
+            }
+        }
+        if let Some(tag) = decode_lyrics3_tag(&data) {
+            for _ in tag.fields() {}
+        }
+        if let Some(tag) = decode_id3v1_tag(&data) {
+            let _ = tag.genre_name();
+        }
+    }
+
+    Ok(())
+}